@@ -0,0 +1,473 @@
+//! Export of downloaded collections as ZIP, tar.gz or CBZ archives.
+//!
+//! Archives are written entry by entry to any `Write` sink, so the web server can
+//! stream them straight to the client and the CLI can write them to a file without
+//! ever holding a whole collection in memory.
+
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Path as UrlPath, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio_util::io::{ReaderStream, SyncIoBridge};
+use tracing::{error, info};
+
+use pinterestx::download::sidecar_path;
+use pinterestx::formats::MediaFormat;
+use pinterestx::paths::get_output_root;
+
+use crate::web::AppState;
+
+/// Size of the in-memory pipe between the archive writer and the HTTP response
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Supported archive formats
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    /// ZIP archive
+    Zip,
+    /// Gzip-compressed tarball
+    Tar,
+    /// Comic book archive: a ZIP of the media files in name order, and the manifest when one is asked for
+    Cbz,
+}
+
+impl ArchiveFormat {
+    /// File extension used for archives of this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar.gz",
+            ArchiveFormat::Cbz => "cbz",
+        }
+    }
+
+    /// MIME type sent when streaming this format over HTTP
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "application/zip",
+            ArchiveFormat::Tar => "application/gzip",
+            ArchiveFormat::Cbz => "application/vnd.comicbook+zip",
+        }
+    }
+}
+
+/// A file on disk and the name it gets inside the archive
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Optional JSON manifest describing the archived files
+#[derive(Serialize, Debug)]
+pub struct Manifest {
+    pub genre: String,
+    pub query: String,
+    pub created_at: u64,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ManifestFile {
+    pub name: String,
+    pub size: u64,
+    /// Contents of the image's metadata sidecar, if it has one
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Name of the manifest file inside archives
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// Collects the images and videos of a collection directory and its subfolders (the
+/// boards of a profile, or the sections of a board) as archive entries named by their
+/// path relative to `dir`, sorted by name. Metadata sidecars are left out, as their
+/// contents go into the manifest instead, and so is anything else that isn't media,
+/// like leftovers of interrupted downloads.
+pub fn collection_entries(dir: &Path) -> Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    add_entries(dir, "", &mut entries)?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Adds the media under `dir` to `entries`, with `prefix` in front of their names
+fn add_entries(dir: &Path, prefix: &str, entries: &mut Vec<ArchiveEntry>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let file_type = entry.file_type()?;
+        if name.starts_with('.') {
+            continue;
+        }
+        if file_type.is_dir() {
+            add_entries(&entry.path(), &format!("{}{}/", prefix, name), entries)?;
+            continue;
+        }
+        let is_media = Path::new(&name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| MediaFormat::from_extension(extension).is_some());
+        if is_media && file_type.is_file() {
+            entries.push(ArchiveEntry { name: format!("{}{}", prefix, name), path: entry.path() });
+        }
+    }
+    Ok(())
+}
+
+/// Builds a manifest for a set of entries, pulling in each file's metadata sidecar
+pub fn build_manifest(genre: &str, query: &str, entries: &[ArchiveEntry]) -> Manifest {
+    let files = entries
+        .iter()
+        .map(|entry| {
+            let size = std::fs::metadata(&entry.path).map(|m| m.len()).unwrap_or(0);
+            let metadata = std::fs::read_to_string(sidecar_path(&entry.path))
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok());
+            ManifestFile { name: entry.name.clone(), size, metadata }
+        })
+        .collect();
+
+    Manifest {
+        genre: genre.to_string(),
+        query: query.to_string(),
+        created_at: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        files,
+    }
+}
+
+/// Writes an archive to `writer`, one entry at a time.
+/// This is blocking I/O and should run on a blocking thread when called from async code.
+pub fn write_archive<W: Write>(
+    writer: W,
+    format: ArchiveFormat,
+    entries: &[ArchiveEntry],
+    manifest: Option<&Manifest>,
+) -> Result<()> {
+    let manifest_bytes = manifest.map(serde_json::to_vec_pretty).transpose()?;
+
+    match format {
+        ArchiveFormat::Zip | ArchiveFormat::Cbz => {
+            let mut zip = zip::ZipWriter::new_stream(writer);
+            // Images are already compressed, so storing them is both faster and just as small
+            let stored = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored)
+                .large_file(true);
+            let deflated = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated);
+
+            for entry in entries {
+                zip.start_file(entry.name.as_str(), stored)?;
+                let mut file = std::fs::File::open(&entry.path)?;
+                std::io::copy(&mut file, &mut zip)?;
+            }
+            if let Some(bytes) = &manifest_bytes {
+                zip.start_file(MANIFEST_NAME, deflated)?;
+                zip.write_all(bytes)?;
+            }
+            zip.finish()?.flush()?;
+        }
+        ArchiveFormat::Tar => {
+            let encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+            let mut tar = tar::Builder::new(encoder);
+
+            for entry in entries {
+                tar.append_path_with_name(&entry.path, &entry.name)?;
+            }
+            if let Some(bytes) = &manifest_bytes {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(bytes.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(manifest.map(|m| m.created_at).unwrap_or(0));
+                header.set_cksum();
+                tar.append_data(&mut header, MANIFEST_NAME, bytes.as_slice())?;
+            }
+            tar.into_inner()?.finish()?.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Exports a collection to an archive file on disk (used by the `export` CLI command).
+/// The collection is given as the folders leading to it: a genre and a query, or
+/// deeper, like a single board of a downloaded profile.
+pub fn export_collection_to_file(
+    folders: &[&str],
+    format: ArchiveFormat,
+    output: &Path,
+    include_manifest: bool,
+) -> Result<usize> {
+    let name = folders.join("/");
+    let collection_dir = crate::gallery::collection_dir(folders).map_err(|status| match status {
+        StatusCode::BAD_REQUEST => anyhow::anyhow!("Invalid collection '{}'", name),
+        _ => anyhow::anyhow!("Collection '{}' does not exist under {}", name, get_output_root().unwrap_or_default().display()),
+    })?;
+
+    let entries = collection_entries(&collection_dir)?;
+    let (genre, query) = name.split_once('/').unwrap_or((&name, ""));
+    let manifest = include_manifest.then(|| build_manifest(genre, query, &entries));
+
+    write_archive_file(output, |writer| write_archive(writer, format, &entries, manifest.as_ref()))?;
+    Ok(entries.len())
+}
+
+/// Writes an archive file through a `.part` file beside `output`, which is renamed
+/// into place once complete, so a failed export doesn't leave a truncated archive
+fn write_archive_file(output: &Path, write: impl FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<()>) -> Result<()> {
+    let mut partial = output.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    let written = (|| {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(&partial)?);
+        write(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        Ok(std::fs::rename(&partial, output)?)
+    })();
+    if written.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    written
+}
+
+/// Builds the archive export routes
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/export/*path", get(export_collection_handler))
+        .route("/jobs/:id/export", get(export_job_handler))
+}
+
+#[derive(Deserialize, Debug)]
+struct ExportQuery {
+    format: Option<ArchiveFormat>,
+    #[serde(default)]
+    manifest: bool,
+}
+
+/// Streams an archive as an HTTP response.
+/// The archive is produced on a blocking thread that writes into a bounded pipe,
+/// so memory use stays constant no matter how large the collection is.
+fn stream_archive(
+    format: ArchiveFormat,
+    entries: Vec<ArchiveEntry>,
+    manifest: Option<Manifest>,
+    download_name: &str,
+) -> Response {
+    let (reader, writer) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+
+    tokio::task::spawn_blocking(move || {
+        let writer = SyncIoBridge::new(writer);
+        if let Err(e) = write_archive(writer, format, &entries, manifest.as_ref()) {
+            // The client has most likely disconnected; the truncated body signals the failure
+            error!("Failed to stream archive: {}", e);
+        }
+    });
+
+    let filename = format!("{}.{}", archive_file_stem(download_name), format.extension());
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response()
+}
+
+/// Turns a collection name into a safe ASCII file name for Content-Disposition
+fn archive_file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if stem.is_empty() { "collection".to_string() } else { stem }
+}
+
+async fn export_collection_handler(
+    UrlPath(path): UrlPath<String>,
+    Query(params): Query<ExportQuery>,
+) -> Response {
+    // As on the command line: `<genre>/<query>`, or deeper for a board or section
+    let folders: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    if folders.len() < 2 {
+        return (StatusCode::BAD_REQUEST, "Export a <genre>/<query> collection").into_response();
+    }
+    let collection_dir = match crate::gallery::collection_dir(&folders) {
+        Ok(dir) => dir,
+        Err(status) => return status.into_response(),
+    };
+
+    let entries = match collection_entries(&collection_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to list collection {:?}: {}", collection_dir, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let format = params.format.unwrap_or(ArchiveFormat::Zip);
+    let name = folders.join("/");
+    info!("Exporting {} as {:?} ({} files)", name, format, entries.len());
+    let (genre, query) = name.split_once('/').unwrap_or((&name, ""));
+    let manifest = params.manifest.then(|| build_manifest(genre, query, &entries));
+    stream_archive(format, entries, manifest, &folders.join("-"))
+}
+
+async fn export_job_handler(
+    State(state): State<AppState>,
+    UrlPath(id): UrlPath<u64>,
+    Query(params): Query<ExportQuery>,
+) -> Response {
+    let Some(job) = state.jobs.get(id) else {
+        return (StatusCode::NOT_FOUND, "Unknown job").into_response();
+    };
+    let Some(output_dir) = job.output_dir.as_ref().filter(|_| job.is_finished()) else {
+        return (StatusCode::CONFLICT, "Job has not finished yet").into_response();
+    };
+
+    // Only include the files this job produced that are still on disk
+    let entries: Vec<ArchiveEntry> = job
+        .files
+        .iter()
+        .map(|name| ArchiveEntry { name: name.clone(), path: output_dir.join(name) })
+        .filter(|entry| entry.path.is_file())
+        .collect();
+
+    let format = params.format.unwrap_or(ArchiveFormat::Zip);
+    info!("Exporting job {} as {:?} ({} files)", id, format, entries.len());
+    let manifest = params.manifest.then(|| build_manifest(&job.genre, &job.query, &entries));
+    stream_archive(format, entries, manifest, &format!("{}-{}-job{}", job.genre, job.query, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::isolate_test_dirs;
+    use std::io::Read;
+
+    #[test]
+    fn archives_only_the_media_of_a_collection() {
+        let dir = std::env::temp_dir().join(format!("pinterestx-archive-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("kittens")).unwrap();
+        std::fs::create_dir_all(dir.join(".thumbnails")).unwrap();
+        for (name, content) in [
+            ("kittens/image_001.png", "png"),
+            ("kittens/image_001.png.json", "{}"),
+            (".thumbnails/image_001.jpg", "thumbnail"),
+            ("image_002.webp", "webp"),
+            ("image_001.jpg", "jpeg"),
+            ("image_001.jpg.json", r#"{"source_url":"https://i.pinimg.com/originals/aa/01/tabby.jpg"}"#),
+            ("image_003.jpg.part", "half a jpeg"),
+            ("image_003.jpg.tmp", "half a jpeg"),
+            (".DS_Store", "finder"),
+            ("notes.txt", "notes"),
+        ] {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+
+        let entries = collection_entries(&dir).unwrap();
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["image_001.jpg", "image_002.webp", "kittens/image_001.png"]);
+
+        let manifest = build_manifest("cats", "tabby", &entries);
+        let mut archive = Vec::new();
+        write_archive(&mut archive, ArchiveFormat::Cbz, &entries, Some(&manifest)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+        let names: Vec<&str> = zip.file_names().collect();
+        assert_eq!(names, ["image_001.jpg", "image_002.webp", "kittens/image_001.png", "manifest.json"]);
+        let mut image = String::new();
+        zip.by_name("image_001.jpg").unwrap().read_to_string(&mut image).unwrap();
+        assert_eq!(image, "jpeg");
+
+        let manifest: serde_json::Value = serde_json::from_reader(zip.by_name(MANIFEST_NAME).unwrap()).unwrap();
+        assert_eq!(manifest["genre"], "cats");
+        assert_eq!(manifest["files"][0]["name"], "image_001.jpg");
+        assert_eq!(manifest["files"][0]["size"], 4);
+        assert_eq!(manifest["files"][0]["metadata"]["source_url"], "https://i.pinimg.com/originals/aa/01/tabby.jpg");
+        assert_eq!(manifest["files"][1]["metadata"], serde_json::Value::Null);
+        assert_eq!(manifest["files"][2]["name"], "kittens/image_001.png");
+    }
+
+    #[test]
+    fn exports_nested_collections_from_the_output_root() {
+        let home = isolate_test_dirs();
+        let profile = get_output_root().unwrap().join("export").join("alice");
+        std::fs::create_dir_all(profile.join("cats")).unwrap();
+        std::fs::create_dir_all(profile.join("dogs")).unwrap();
+        std::fs::write(profile.join("cats").join("image_001.jpg"), "cat").unwrap();
+        std::fs::write(profile.join("dogs").join("image_001.jpg"), "dog").unwrap();
+
+        let output = home.join("alice.tar.gz");
+        assert_eq!(export_collection_to_file(&["export", "alice"], ArchiveFormat::Tar, &output, false).unwrap(), 2);
+        let file = flate2::read::GzDecoder::new(std::fs::File::open(&output).unwrap());
+        let mut tar = tar::Archive::new(file);
+        let names: Vec<String> =
+            tar.entries().unwrap().map(|entry| entry.unwrap().path().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, ["cats/image_001.jpg", "dogs/image_001.jpg"]);
+
+        let output = home.join("cats.zip");
+        assert_eq!(export_collection_to_file(&["export", "alice", "cats"], ArchiveFormat::Zip, &output, false).unwrap(), 1);
+        assert!(export_collection_to_file(&["export", "..", "alice"], ArchiveFormat::Zip, &output, false).is_err());
+        assert!(export_collection_to_file(&["export", "bob"], ArchiveFormat::Zip, &output, false).is_err());
+    }
+
+    #[test]
+    fn leaves_no_archive_behind_when_an_export_fails() {
+        let home = isolate_test_dirs();
+        let output = home.join("failed.zip");
+        let partial = home.join("failed.zip.part");
+
+        let failed = write_archive_file(&output, |writer| {
+            writer.write_all(b"PK half an archive")?;
+            anyhow::bail!("disk full")
+        });
+        assert_eq!(failed.unwrap_err().to_string(), "disk full");
+        assert!(!output.exists());
+        assert!(!partial.exists());
+
+        write_archive_file(&output, |writer| Ok(writer.write_all(b"PK")?)).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), b"PK");
+        assert!(!partial.exists());
+    }
+
+    #[tokio::test]
+    async fn exports_nested_collections_over_http() {
+        isolate_test_dirs();
+        let board = get_output_root().unwrap().join("web-export").join("alice").join("cats");
+        std::fs::create_dir_all(&board).unwrap();
+        std::fs::write(board.join("image_001.jpg"), "cat").unwrap();
+        let export = |path: &str| {
+            export_collection_handler(UrlPath(path.to_string()), Query(ExportQuery { format: Some(ArchiveFormat::Tar), manifest: false }))
+        };
+
+        let response = export("web-export/alice/cats").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_DISPOSITION], "attachment; filename=\"web-export-alice-cats.tar.gz\"");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(body.as_ref()));
+        let names: Vec<String> =
+            tar.entries().unwrap().map(|entry| entry.unwrap().path().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, ["image_001.jpg"]);
+
+        assert_eq!(export("web-export").await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(export("web-export/../alice").await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(export("web-export/bob/cats").await.status(), StatusCode::NOT_FOUND);
+    }
+}
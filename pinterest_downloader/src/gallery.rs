@@ -0,0 +1,806 @@
+//! Gallery browser for collections that have already been downloaded.
+//!
+//! Collections live under the output root as `<genre>/<query>/<file>`, with an
+//! optional `<file>.json` sidecar written by the downloader. Profiles and board
+//! sections add more levels (`<genre>/<user>/<board>/<file>`), so any folder can hold
+//! both files and sub-collections. Images are shown as thumbnails and videos with
+//! the browser's own player. Every path that comes in from a request is resolved
+//! against the output root and rejected if it would escape it.
+
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract::{Path as UrlPath, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Form, Router,
+};
+use fnv::FnvHasher;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::hash::Hasher;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tera::Context;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use tracing::{error, info};
+
+use pinterestx::download::sidecar_path;
+use pinterestx::formats::MediaFormat;
+use pinterestx::paths::{get_app_data_dir, get_output_root};
+
+use crate::web::{AppState, HtmlTemplate};
+
+/// Number of thumbnails shown per gallery page
+const PAGE_SIZE: usize = 48;
+
+/// Longest edge of a generated thumbnail, in pixels
+const THUMBNAIL_SIZE: u32 = 320;
+
+/// Builds the gallery routes
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/gallery", get(genres_handler))
+        .route("/gallery/*path", get(gallery_handler).post(move_handler).delete(delete_handler))
+        .route("/media/*path", get(media_handler))
+        .route("/thumbs/*path", get(thumbnail_handler))
+}
+
+#[derive(Deserialize, Debug)]
+struct PageQuery {
+    page: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MoveRequest {
+    /// Collection to move to, as `<genre>/<query>` or deeper
+    target: String,
+}
+
+/// A folder as shown in the gallery, with the number of collections (for a genre)
+/// or of files, sub-collections included, in it
+#[derive(Serialize, Debug)]
+struct FolderEntry {
+    name: String,
+    count: usize,
+}
+
+/// One level of the breadcrumb trail above a folder
+#[derive(Serialize, Debug)]
+struct Crumb {
+    name: String,
+    href: String,
+}
+
+/// An image or video in a collection, as shown in the gallery
+#[derive(Serialize, Debug)]
+struct MediaEntry {
+    name: String,
+    /// Shown with a video player rather than a thumbnail
+    video: bool,
+}
+
+/// Error type for gallery handlers, rendered as a plain status response
+#[derive(Debug)]
+struct GalleryError(StatusCode, String);
+
+impl IntoResponse for GalleryError {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+impl From<anyhow::Error> for GalleryError {
+    fn from(err: anyhow::Error) -> Self {
+        error!("Gallery error: {}", err);
+        GalleryError(StatusCode::INTERNAL_SERVER_ERROR, "Internal gallery error".to_string())
+    }
+}
+
+impl From<pinterestx::Error> for GalleryError {
+    fn from(err: pinterestx::Error) -> Self {
+        anyhow::Error::from(err).into()
+    }
+}
+
+fn not_found() -> GalleryError {
+    GalleryError(StatusCode::NOT_FOUND, "Not found".to_string())
+}
+
+/// Validates a single path component taken from a request.
+/// Rejects anything that could be used to navigate outside of its parent directory.
+fn safe_component(name: &str) -> Result<&str, GalleryError> {
+    let invalid = name.is_empty()
+        || name.starts_with('.')
+        || name.contains(['/', '\\', '\0'])
+        || Path::new(name).components().count() != 1;
+
+    if invalid {
+        return Err(GalleryError(StatusCode::BAD_REQUEST, "Invalid path component".to_string()));
+    }
+    Ok(name)
+}
+
+/// Splits a path taken from a request, such as `Art/alice/cats/image_001.jpg`, into
+/// its folder and file names, each checked with [`safe_component`]
+fn path_parts(path: &str) -> Result<Vec<&str>, GalleryError> {
+    path.trim_end_matches('/').split('/').map(safe_component).collect()
+}
+
+/// Percent-encodes path components and joins them into a URL path
+fn encode_path(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .map(|part| utf8_percent_encode(part, NON_ALPHANUMERIC).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Resolves `<root>/<parts...>` and makes sure the canonical result is still inside the root.
+/// This also catches symlinks that point outside of the output directory.
+fn resolve_within_root(parts: &[&str]) -> Result<PathBuf, GalleryError> {
+    let root = get_output_root()?;
+    let mut path = root.clone();
+    for part in parts {
+        path.push(safe_component(part)?);
+    }
+
+    let canonical_root = root.canonicalize().map_err(|_| not_found())?;
+    let canonical = path.canonicalize().map_err(|_| not_found())?;
+    if !canonical.starts_with(&canonical_root) {
+        return Err(not_found());
+    }
+    Ok(canonical)
+}
+
+/// Creates `<root>/<parts...>` one level at a time. Each existing level is resolved
+/// and checked to be inside the root before anything is created in it, so a genre
+/// folder that is a symlink to elsewhere is refused without touching its target.
+fn create_within_root(parts: &[&str]) -> Result<PathBuf, GalleryError> {
+    for depth in 0..parts.len() {
+        let parent = resolve_within_root(&parts[..depth])?;
+        match std::fs::create_dir(parent.join(safe_component(parts[depth])?)) {
+            Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(anyhow::Error::from(e).into()),
+            _ => {}
+        }
+    }
+    resolve_within_root(parts)
+}
+
+/// Resolves a collection directory from untrusted folder names: a genre and query,
+/// or deeper for the boards of a profile and the sections of a board
+pub fn collection_dir(folders: &[&str]) -> Result<PathBuf, StatusCode> {
+    let dir = resolve_within_root(folders).map_err(|e| e.0)?;
+    if !dir.is_dir() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(dir)
+}
+
+/// Resolves an image or video inside a collection, refusing other files such as sidecars
+fn resolve_media(parts: &[&str]) -> Result<PathBuf, GalleryError> {
+    if parts.len() < 2 || parts.last().and_then(|file| media_format(file)).is_none() {
+        return Err(not_found());
+    }
+    let path = resolve_within_root(parts)?;
+    if !path.is_file() {
+        return Err(not_found());
+    }
+    Ok(path)
+}
+
+/// The format of a media file, from the extension the downloader saved it with.
+/// `.jpeg` is also taken, for files added to a collection by hand.
+fn media_format(name: &str) -> Option<MediaFormat> {
+    let extension = Path::new(name).extension().and_then(|ext| ext.to_str())?;
+    if extension.eq_ignore_ascii_case("jpeg") {
+        return Some(MediaFormat::Jpeg);
+    }
+    MediaFormat::from_extension(extension)
+}
+
+fn is_video(name: &str) -> bool {
+    media_format(name).is_some_and(MediaFormat::is_video)
+}
+
+fn content_type_for(path: &Path) -> &'static str {
+    path.to_str()
+        .and_then(media_format)
+        .map(MediaFormat::mime_type)
+        .unwrap_or("application/octet-stream")
+}
+
+/// Lists visible sub-directories of a directory, sorted by name
+fn list_folders(dir: &Path) -> Result<Vec<String>> {
+    let mut folders = Vec::new();
+    if !dir.exists() {
+        return Ok(folders);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with('.') && entry.file_type()?.is_dir() {
+            folders.push(name);
+        }
+    }
+    folders.sort();
+    Ok(folders)
+}
+
+/// Lists the images and videos in a collection directory, sorted by name
+fn list_media(dir: &Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with('.') && entry.file_type()?.is_file() && media_format(&name).is_some() {
+            files.push(name);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Counts the images and videos in a folder and all of its sub-folders
+fn count_media(dir: &Path) -> usize {
+    let files = list_media(dir).map(|files| files.len()).unwrap_or(0);
+    let nested: usize = list_folders(dir)
+        .unwrap_or_default()
+        .iter()
+        .map(|name| count_media(&dir.join(name)))
+        .sum();
+    files + nested
+}
+
+/// Adds every collection below `dir` to `collections` as a `/`-separated path. Folders
+/// count from the second level down, so the boards of a profile and the sections of a
+/// board are included. Symlinked folders aren't followed.
+fn add_collections(dir: &Path, prefix: &str, collections: &mut Vec<String>) -> Result<()> {
+    for name in list_folders(dir)? {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}/{}", prefix, name) };
+        if !prefix.is_empty() {
+            collections.push(path.clone());
+        }
+        add_collections(&dir.join(&name), &path, collections)?;
+    }
+    Ok(())
+}
+
+/// Reads an image's sidecar as a list of displayable key/value pairs
+fn read_sidecar(image_path: &Path) -> Vec<(String, String)> {
+    let Ok(content) = std::fs::read_to_string(sidecar_path(image_path)) else {
+        return Vec::new();
+    };
+    let Ok(serde_json::Value::Object(map)) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return Vec::new();
+    };
+    map.into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// Gets the thumbnail cache directory
+fn get_thumbnail_cache_dir() -> PathBuf {
+    let mut path = get_app_data_dir();
+    path.push("thumbnails");
+    path
+}
+
+/// Path of the cached thumbnail for an image.
+/// The key includes size and modification time so replaced files get a fresh thumbnail.
+fn thumbnail_cache_path(image_path: &Path) -> Result<PathBuf> {
+    let metadata = std::fs::metadata(image_path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut hasher = FnvHasher::default();
+    hasher.write(image_path.to_string_lossy().as_bytes());
+    hasher.write_u64(metadata.len());
+    hasher.write_u64(modified);
+
+    Ok(get_thumbnail_cache_dir().join(format!("{:016x}.jpg", hasher.finish())))
+}
+
+/// Returns the cached thumbnail for an image, generating it if needed
+fn ensure_thumbnail(image_path: &Path) -> Result<PathBuf> {
+    let thumb_path = thumbnail_cache_path(image_path)?;
+    if thumb_path.exists() {
+        return Ok(thumb_path);
+    }
+
+    std::fs::create_dir_all(get_thumbnail_cache_dir())?;
+    let image = image::open(image_path)?;
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8();
+
+    // Write to a temporary file first so concurrent requests never see a partial thumbnail
+    let tmp_path = thumb_path.with_extension("jpg.tmp");
+    thumbnail.save_with_format(&tmp_path, image::ImageFormat::Jpeg)?;
+    std::fs::rename(&tmp_path, &thumb_path)?;
+
+    Ok(thumb_path)
+}
+
+/// Removes the cached thumbnail of an image that is about to be moved or deleted
+fn remove_thumbnail(image_path: &Path) {
+    if let Ok(thumb_path) = thumbnail_cache_path(image_path) {
+        let _ = std::fs::remove_file(thumb_path);
+    }
+}
+
+/// What a `Range` request header asks of a file
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// No range, or one that isn't understood: the whole file
+    Whole,
+    /// The bytes from the first offset to the second, inclusive
+    Part(u64, u64),
+    /// A range that starts past the end of the file
+    Unsatisfiable,
+}
+
+/// Reads a `Range` header for a file of `len` bytes. Only a single `bytes=` range is
+/// supported; anything else is answered with the whole file, as HTTP allows.
+fn byte_range(header: Option<&str>, len: u64) -> ByteRange {
+    let Some((start, end)) = header
+        .and_then(|value| value.trim().strip_prefix("bytes="))
+        .filter(|range| !range.contains(','))
+        .and_then(|range| range.split_once('-'))
+    else {
+        return ByteRange::Whole;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    // `bytes=-N` asks for the last N bytes
+    if start.is_empty() {
+        return match end.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if len == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Part(len.saturating_sub(suffix), len - 1),
+            Err(_) => ByteRange::Whole,
+        };
+    }
+    let Ok(start) = start.parse::<u64>() else {
+        return ByteRange::Whole;
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    let end = if end.is_empty() { Ok(len - 1) } else { end.parse::<u64>().map(|end| end.min(len - 1)) };
+    match end {
+        Ok(end) if end >= start => ByteRange::Part(start, end),
+        _ => ByteRange::Whole,
+    }
+}
+
+/// Finds a file name in `dir` that doesn't clash with an existing file
+fn unique_destination(dir: &Path, file: &str) -> PathBuf {
+    let candidate = dir.join(file);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let stem = Path::new(file).file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    let extension = Path::new(file).extension().and_then(|s| s.to_str()).unwrap_or("jpg");
+    (2..)
+        .map(|n| dir.join(format!("{}_{}.{}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .expect("unbounded range always yields a free file name")
+}
+
+async fn genres_handler() -> Result<HtmlTemplate, GalleryError> {
+    let root = get_output_root()?;
+    let genres = list_folders(&root)?
+        .into_iter()
+        .map(|name| {
+            let count = list_folders(&root.join(&name)).map(|q| q.len()).unwrap_or(0);
+            FolderEntry { name, count }
+        })
+        .collect::<Vec<_>>();
+
+    let mut context = Context::new();
+    context.insert("view", "genres");
+    context.insert("folders", &genres);
+    Ok(HtmlTemplate("gallery.html".to_string(), context))
+}
+
+/// Shows a folder below a genre, or a file in the lightbox
+async fn gallery_handler(
+    UrlPath(path): UrlPath<String>,
+    Query(params): Query<PageQuery>,
+) -> Result<Response, GalleryError> {
+    let parts = path_parts(&path)?;
+    let resolved = resolve_within_root(&parts)?;
+    if resolved.is_file() {
+        return Ok(lightbox(&parts)?.into_response());
+    }
+
+    let subfolders = list_folders(&resolved)?
+        .into_iter()
+        .map(|name| {
+            let count = count_media(&resolved.join(&name));
+            FolderEntry { name, count }
+        })
+        .collect::<Vec<_>>();
+    let files = list_media(&resolved)?;
+
+    let total_pages = files.len().div_ceil(PAGE_SIZE).max(1);
+    let page = params.page.unwrap_or(1).clamp(1, total_pages);
+    let page_files: Vec<MediaEntry> = files
+        .iter()
+        .skip((page - 1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(|name| MediaEntry { name: name.clone(), video: is_video(name) })
+        .collect();
+
+    let crumbs: Vec<Crumb> = (1..=parts.len())
+        .map(|depth| Crumb {
+            name: parts[depth - 1].to_string(),
+            href: format!("/gallery/{}", encode_path(&parts[..depth])),
+        })
+        .collect();
+
+    let mut context = Context::new();
+    context.insert("view", "folder");
+    context.insert("base", &encode_path(&parts));
+    context.insert("crumbs", &crumbs);
+    // Collections are exported from `<genre>/<query>` down, as on the command line
+    context.insert("exportable", &(parts.len() >= 2));
+    context.insert("folders", &subfolders);
+    context.insert("files", &page_files);
+    context.insert("total_files", &files.len());
+    context.insert("page", &page);
+    context.insert("total_pages", &total_pages);
+    Ok(HtmlTemplate("gallery.html".to_string(), context).into_response())
+}
+
+/// Renders the lightbox for the image or video at `parts`
+fn lightbox(parts: &[&str]) -> Result<HtmlTemplate, GalleryError> {
+    let image_path = resolve_media(parts)?;
+    let metadata = read_sidecar(&image_path);
+    let (file, folder) = parts.split_last().expect("media paths have a folder and a file");
+
+    // Offer every existing collection as a move target
+    let mut collections = Vec::new();
+    add_collections(&get_output_root()?, "", &mut collections)?;
+
+    let mut context = Context::new();
+    context.insert("base", &encode_path(folder));
+    context.insert("folder", &folder.join("/"));
+    context.insert("file", file);
+    context.insert("video", &is_video(file));
+    context.insert("metadata", &metadata);
+    context.insert("collections", &collections);
+    Ok(HtmlTemplate("partials/lightbox.html".to_string(), context))
+}
+
+async fn media_handler(UrlPath(path): UrlPath<String>, headers: HeaderMap) -> Result<Response, GalleryError> {
+    let image_path = resolve_media(&path_parts(&path)?)?;
+    let mut file = tokio::fs::File::open(&image_path).await.map_err(|_| not_found())?;
+    let len = file.metadata().await.map_err(anyhow::Error::from)?.len();
+    let content_type = content_type_for(&image_path);
+
+    // Files are streamed from disk, and video players seek with ranges
+    let range = headers.get(header::RANGE).and_then(|value| value.to_str().ok());
+    let response = match byte_range(range, len) {
+        ByteRange::Whole => (
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (header::CONTENT_LENGTH, len.to_string()),
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+            ],
+            Body::from_stream(ReaderStream::new(file)),
+        )
+            .into_response(),
+        ByteRange::Part(start, end) => {
+            file.seek(SeekFrom::Start(start)).await.map_err(anyhow::Error::from)?;
+            (
+                StatusCode::PARTIAL_CONTENT,
+                [
+                    (header::CONTENT_TYPE, content_type.to_string()),
+                    (header::CONTENT_LENGTH, (end - start + 1).to_string()),
+                    (header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len)),
+                    (header::ACCEPT_RANGES, "bytes".to_string()),
+                ],
+                Body::from_stream(ReaderStream::new(file.take(end - start + 1))),
+            )
+                .into_response()
+        }
+        ByteRange::Unsatisfiable => (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{}", len))],
+        )
+            .into_response(),
+    };
+    Ok(response)
+}
+
+async fn thumbnail_handler(UrlPath(path): UrlPath<String>) -> Result<Response, GalleryError> {
+    let parts = path_parts(&path)?;
+    // Videos are shown with the browser's player, which draws their first frame
+    if parts.last().is_some_and(|file| is_video(file)) {
+        return Err(not_found());
+    }
+    let image_path = resolve_media(&parts)?;
+
+    let source = image_path.clone();
+    let thumbnail = tokio::task::spawn_blocking(move || ensure_thumbnail(&source))
+        .await
+        .map_err(anyhow::Error::from)?;
+
+    // Fall back to the original if the image can't be decoded (e.g. unsupported format)
+    let (path, content_type) = match thumbnail {
+        Ok(thumb_path) => (thumb_path, "image/jpeg"),
+        Err(e) => {
+            error!("Failed to generate thumbnail for {:?}: {}", image_path, e);
+            let content_type = content_type_for(&image_path);
+            (image_path, content_type)
+        }
+    };
+
+    let content = tokio::fs::read(&path).await.map_err(|_| not_found())?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, "private, max-age=86400"),
+        ],
+        content,
+    )
+        .into_response())
+}
+
+async fn delete_handler(UrlPath(path): UrlPath<String>) -> Result<Response, GalleryError> {
+    let image_path = resolve_media(&path_parts(&path)?)?;
+
+    remove_thumbnail(&image_path);
+    let _ = std::fs::remove_file(sidecar_path(&image_path));
+    std::fs::remove_file(&image_path).map_err(anyhow::Error::from)?;
+    info!("Deleted {:?} from the gallery", image_path);
+
+    // Ask HTMX to reload the page so the grid and pagination are refreshed
+    Ok(([("HX-Refresh", "true")], StatusCode::OK).into_response())
+}
+
+async fn move_handler(
+    UrlPath(path): UrlPath<String>,
+    Form(request): Form<MoveRequest>,
+) -> Result<Response, GalleryError> {
+    let parts = path_parts(&path)?;
+    let image_path = resolve_media(&parts)?;
+    let (file, folder) = parts.split_last().expect("media paths have a folder and a file");
+
+    let target = path_parts(request.target.trim().trim_matches('/'))?;
+    if target.len() < 2 {
+        return Err(GalleryError(StatusCode::BAD_REQUEST, "Move to a <genre>/<collection> folder".to_string()));
+    }
+    if target == folder {
+        return Ok(([("HX-Refresh", "true")], StatusCode::OK).into_response());
+    }
+
+    let target_dir = create_within_root(&target)?;
+
+    let destination = unique_destination(&target_dir, file);
+    remove_thumbnail(&image_path);
+    std::fs::rename(&image_path, &destination).map_err(anyhow::Error::from)?;
+
+    let sidecar = sidecar_path(&image_path);
+    if sidecar.exists() {
+        let _ = std::fs::rename(&sidecar, sidecar_path(&destination));
+    }
+    info!("Moved {:?} to {:?}", image_path, destination);
+
+    Ok(([("HX-Refresh", "true")], StatusCode::OK).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::isolate_test_dirs;
+
+    /// Creates `<root>/<genre>/<query>` with an image in it
+    fn collection(genre: &str, query: &str) -> PathBuf {
+        let dir = get_output_root().unwrap().join(genre).join(query);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("image_001.jpg"), "jpeg").unwrap();
+        dir
+    }
+
+    fn status(result: Result<PathBuf, GalleryError>) -> StatusCode {
+        result.unwrap_err().0
+    }
+
+    async fn render(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn rejects_components_that_leave_their_folder() {
+        for name in ["", ".", "..", ".hidden", "/etc", "a/b", "a\\b", "../cats", "cats/..", "nul\0byte"] {
+            assert!(safe_component(name).is_err(), "{:?}", name);
+        }
+        for name in ["cats", "Digital Art", "image_001.jpg", "cats%2F..%2F..%2Fetc", "caf\u{e9}"] {
+            assert_eq!(safe_component(name).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn resolves_only_paths_inside_the_root() {
+        let home = isolate_test_dirs();
+        let dir = collection("art", "resolve");
+        assert_eq!(resolve_within_root(&["art", "resolve"]).unwrap(), dir.canonicalize().unwrap());
+        assert!(resolve_within_root(&["art", "resolve", "image_001.jpg"]).unwrap().is_file());
+
+        std::fs::write(home.join("secret.txt"), "secret").unwrap();
+        assert_eq!(status(resolve_within_root(&["..", "secret.txt"])), StatusCode::BAD_REQUEST);
+        assert_eq!(status(resolve_within_root(&["art", "/etc"])), StatusCode::BAD_REQUEST);
+        assert_eq!(status(resolve_within_root(&["art", "../../secret.txt"])), StatusCode::BAD_REQUEST);
+        assert_eq!(status(resolve_within_root(&["art", "..%2F..%2Fsecret.txt"])), StatusCode::BAD_REQUEST);
+        // Separators arrive decoded from the URL; if they stay encoded they're just
+        // part of a name, which doesn't exist
+        assert_eq!(status(resolve_within_root(&["art", "x%2F..%2F..%2Fsecret.txt"])), StatusCode::NOT_FOUND);
+        assert_eq!(status(resolve_within_root(&["art", "missing"])), StatusCode::NOT_FOUND);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_genres_symlinked_outside_the_root() {
+        let home = isolate_test_dirs();
+        let outside = home.join("outside");
+        std::fs::create_dir_all(outside.join("loot")).unwrap();
+        std::os::unix::fs::symlink(&outside, get_output_root().unwrap().join("escape")).unwrap();
+
+        assert_eq!(status(resolve_within_root(&["escape"])), StatusCode::NOT_FOUND);
+        assert_eq!(status(resolve_within_root(&["escape", "loot"])), StatusCode::NOT_FOUND);
+        assert_eq!(collection_dir(&["escape", "loot"]), Err(StatusCode::NOT_FOUND));
+
+        // Moving into the symlinked genre creates nothing outside the root
+        assert_eq!(status(create_within_root(&["escape", "new"])), StatusCode::NOT_FOUND);
+        assert!(!outside.join("new").exists());
+    }
+
+    #[tokio::test]
+    async fn shows_videos_with_a_player_instead_of_a_thumbnail() {
+        isolate_test_dirs();
+        let dir = collection("art", "videos");
+        std::fs::write(dir.join("image_002.mp4"), "mp4").unwrap();
+        std::fs::write(dir.join("image_002.mp4.json"), "{}").unwrap();
+        std::fs::write(dir.join("image_003.ts.part"), "half a stream").unwrap();
+        assert_eq!(list_media(&dir).unwrap(), ["image_001.jpg", "image_002.mp4"]);
+        assert_eq!(content_type_for(&dir.join("image_002.mp4")), "video/mp4");
+
+        let html = render(gallery_handler(UrlPath("art/videos".into()), Query(PageQuery { page: None })).await.unwrap()).await;
+        assert!(html.contains(r#"<img src="/thumbs/art/videos/image%5F001%2Ejpg""#), "{}", html);
+        assert!(html.contains(r#"<video src="/media/art/videos/image%5F002%2Emp4#t=0.1""#), "{}", html);
+
+        let thumbnail = thumbnail_handler(UrlPath("art/videos/image_002.mp4".into())).await;
+        assert_eq!(thumbnail.unwrap_err().0, StatusCode::NOT_FOUND);
+        let media = media_handler(UrlPath("art/videos/image_002.mp4".into()), HeaderMap::new()).await.unwrap();
+        assert_eq!(media.headers()[header::CONTENT_TYPE], "video/mp4");
+    }
+
+    #[test]
+    fn reads_single_byte_ranges() {
+        assert_eq!(byte_range(None, 10), ByteRange::Whole);
+        assert_eq!(byte_range(Some("bytes=2-5"), 10), ByteRange::Part(2, 5));
+        assert_eq!(byte_range(Some("bytes=2-"), 10), ByteRange::Part(2, 9));
+        assert_eq!(byte_range(Some("bytes=8-100"), 10), ByteRange::Part(8, 9));
+        assert_eq!(byte_range(Some("bytes=-3"), 10), ByteRange::Part(7, 9));
+        assert_eq!(byte_range(Some("bytes=-30"), 10), ByteRange::Part(0, 9));
+        assert_eq!(byte_range(Some("bytes=10-"), 10), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range(Some("bytes=-0"), 10), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range(Some("bytes=0-"), 0), ByteRange::Unsatisfiable);
+        for ignored in ["bytes=5-2", "bytes=0-1,4-5", "items=0-1", "bytes=a-b"] {
+            assert_eq!(byte_range(Some(ignored), 10), ByteRange::Whole, "{}", ignored);
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_media_with_ranges() {
+        isolate_test_dirs();
+        let dir = collection("art", "ranges");
+        std::fs::write(dir.join("image_002.mp4"), "0123456789").unwrap();
+        let get = |range: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(range) = range {
+                headers.insert(header::RANGE, range.parse().unwrap());
+            }
+            media_handler(UrlPath("art/ranges/image_002.mp4".into()), headers)
+        };
+
+        let whole = get(None).await.unwrap();
+        assert_eq!(whole.status(), StatusCode::OK);
+        assert_eq!(whole.headers()[header::CONTENT_LENGTH], "10");
+        assert_eq!(whole.headers()[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(render(whole).await, "0123456789");
+
+        let part = get(Some("bytes=2-5")).await.unwrap();
+        assert_eq!(part.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(part.headers()[header::CONTENT_RANGE], "bytes 2-5/10");
+        assert_eq!(part.headers()[header::CONTENT_LENGTH], "4");
+        assert_eq!(render(part).await, "2345");
+
+        let past_the_end = get(Some("bytes=20-")).await.unwrap();
+        assert_eq!(past_the_end.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(past_the_end.headers()[header::CONTENT_RANGE], "bytes */10");
+    }
+
+    #[tokio::test]
+    async fn forgets_thumbnails_of_moved_and_deleted_images() {
+        isolate_test_dirs();
+        let dir = collection("art", "thumbs");
+        std::fs::write(dir.join("image_002.jpg"), "jpeg").unwrap();
+        let cached = |name: &str| {
+            let thumb = thumbnail_cache_path(&dir.join(name).canonicalize().unwrap()).unwrap();
+            std::fs::create_dir_all(thumb.parent().unwrap()).unwrap();
+            std::fs::write(&thumb, "thumbnail").unwrap();
+            thumb
+        };
+
+        let moved = cached("image_001.jpg");
+        let form = Form(MoveRequest { target: "art/thumbs-moved".to_string() });
+        move_handler(UrlPath("art/thumbs/image_001.jpg".into()), form).await.unwrap();
+        assert!(!moved.exists());
+
+        let deleted = cached("image_002.jpg");
+        delete_handler(UrlPath("art/thumbs/image_002.jpg".into())).await.unwrap();
+        assert!(!deleted.exists());
+    }
+
+    #[tokio::test]
+    async fn browses_profiles_and_sections_below_a_genre() {
+        isolate_test_dirs();
+        let board = collection("profiles", "alice/cats");
+        collection("profiles", "alice/cats/Kittens");
+        collection("profiles", "alice/dogs");
+        let page = || Query(PageQuery { page: None });
+
+        let html = render(gallery_handler(UrlPath("profiles".into()), page()).await.unwrap()).await;
+        assert!(html.contains(r#"href="/gallery/profiles/alice""#), "{}", html);
+        assert!(html.contains("3 files"), "{}", html);
+
+        // A board shows its own images along with its sections
+        let html = render(gallery_handler(UrlPath("profiles/alice/cats".into()), page()).await.unwrap()).await;
+        assert!(html.contains(r#"href="/gallery/profiles/alice/cats/Kittens""#), "{}", html);
+        assert!(html.contains(r#"<img src="/thumbs/profiles/alice/cats/image%5F001%2Ejpg""#), "{}", html);
+        assert!(html.contains(r#"href="/export/profiles/alice/cats?format=zip"#), "{}", html);
+
+        let html = render(gallery_handler(UrlPath("profiles/alice/cats/image_001.jpg".into()), page()).await.unwrap()).await;
+        // Tera escapes the slashes, which the browser reads back as they were
+        assert!(html.contains(r#"value="profiles&#x2F;alice&#x2F;cats""#), "{}", html);
+        assert!(html.contains(r#"<option value="profiles&#x2F;alice&#x2F;cats&#x2F;Kittens">"#), "{}", html);
+
+        let form = |target: &str| Form(MoveRequest { target: target.to_string() });
+        move_handler(UrlPath("profiles/alice/cats/image_001.jpg".into()), form("profiles/alice/dogs/")).await.unwrap();
+        assert!(!board.join("image_001.jpg").exists());
+        assert!(board.parent().unwrap().join("dogs").join("image_001_2.jpg").is_file());
+        let moved = move_handler(UrlPath("profiles/alice/dogs/image_001.jpg".into()), form("profiles")).await;
+        assert_eq!(moved.unwrap_err().0, StatusCode::BAD_REQUEST);
+
+        delete_handler(UrlPath("profiles/alice/cats/Kittens/image_001.jpg".into())).await.unwrap();
+        assert!(!board.join("Kittens").join("image_001.jpg").exists());
+        let deleted = delete_handler(UrlPath("profiles/alice/../../escape/image_001.jpg".into())).await;
+        assert_eq!(deleted.unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn creates_move_targets_inside_the_root() {
+        isolate_test_dirs();
+        let dir = create_within_root(&["moved", "here"]).unwrap();
+        assert_eq!(dir, get_output_root().unwrap().join("moved").join("here").canonicalize().unwrap());
+        // Existing folders are reused
+        assert_eq!(create_within_root(&["moved", "here"]).unwrap(), dir);
+        assert_eq!(status(create_within_root(&["moved", ".."])), StatusCode::BAD_REQUEST);
+    }
+}
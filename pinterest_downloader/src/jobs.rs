@@ -0,0 +1,454 @@
+//! Tracking of download jobs started from the web interface.
+//!
+//! Jobs double as the server-side download history: the registry is persisted to
+//! `history.json` in the application data directory, so history survives restarts
+//! and is shared by every browser that talks to the server.
+
+use anyhow::Result;
+use axum::{
+    extract::{Path as UrlPath, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tera::Context;
+use tracing::{error, info};
+
+use pinterestx::accounts::DEFAULT_ACCOUNT;
+use pinterestx::boards::{BoardFilters, SectionsMode};
+use pinterestx::media::MediaFilter;
+use pinterestx::paths::{get_app_data_dir, write_file_atomically};
+use pinterestx::search::SearchArgs;
+use pinterestx::download::DEFAULT_WORKERS;
+use pinterestx::{DownloadOptions, DownloadSummary};
+
+use crate::web::{spawn_download_job, AppState, HtmlTemplate};
+
+/// Number of jobs kept in the persisted history
+const MAX_HISTORY: usize = 100;
+
+/// Number of history entries shown on the index page
+pub const RECENT_HISTORY: usize = 10;
+
+/// State of a download job
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Finished,
+    Failed,
+}
+
+/// A download job and, once it has finished, what it produced
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub url: String,
+    pub genre: String,
+    pub query: String,
+    pub quality: String,
+    pub max_images: usize,
+    /// Account whose session the job downloads with
+    #[serde(default = "default_account")]
+    pub account: String,
+    /// Scope, refinements and filters of a search
+    #[serde(default)]
+    pub search: SearchArgs,
+    /// Kinds of media the job keeps
+    #[serde(default)]
+    pub media: MediaFilter,
+    /// What the job does with a board's sections
+    #[serde(default)]
+    pub sections: Option<SectionsMode>,
+    /// Which boards of a profile the job downloads
+    #[serde(default)]
+    pub boards: BoardFilters,
+    /// Number of related pins downloaded along with a pin
+    #[serde(default)]
+    pub related: usize,
+    /// Extraction strategies the job was limited to
+    #[serde(default)]
+    pub strategies: Vec<String>,
+    /// Whether the results of every strategy were merged
+    #[serde(default)]
+    pub merge: bool,
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    pub status: JobStatus,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub output_dir: Option<PathBuf>,
+    /// File names (relative to `output_dir`) saved by this job
+    pub files: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl Job {
+    pub fn is_finished(&self) -> bool {
+        self.status == JobStatus::Finished
+    }
+
+    /// Rebuilds the options this job was started with, for re-running it
+    pub fn to_options(&self) -> DownloadOptions {
+        DownloadOptions {
+            url: self.url.clone(),
+            genre: Some(self.genre.clone()),
+            query: Some(self.query.clone()),
+            quality: self.quality.clone(),
+            max_images: self.max_images,
+            account: self.account.clone(),
+            search: self.search.clone(),
+            media: self.media,
+            sections: self.sections,
+            boards: self.boards.clone(),
+            related: self.related,
+            strategies: self.strategies.clone(),
+            merge: self.merge,
+            workers: self.workers,
+        }
+    }
+}
+
+impl From<&DownloadOptions> for Job {
+    /// A new running job for `options`, not yet numbered
+    fn from(options: &DownloadOptions) -> Self {
+        Job {
+            id: 0,
+            url: options.url.clone(),
+            genre: options.genre.clone().unwrap_or_else(|| "Uncategorized".to_string()),
+            query: options.query.clone().unwrap_or_else(|| "Pinterest".to_string()),
+            quality: options.quality.clone(),
+            max_images: options.max_images,
+            account: options.account.clone(),
+            search: options.search.clone(),
+            media: options.media,
+            sections: options.sections,
+            boards: options.boards.clone(),
+            related: options.related,
+            strategies: options.strategies.clone(),
+            merge: options.merge,
+            workers: options.workers,
+            status: JobStatus::Running,
+            started_at: now(),
+            finished_at: None,
+            output_dir: None,
+            files: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+fn default_account() -> String {
+    DEFAULT_ACCOUNT.to_string()
+}
+
+fn default_workers() -> usize {
+    DEFAULT_WORKERS
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Registry {
+    next_id: u64,
+    jobs: Vec<Job>,
+}
+
+/// Registry of jobs, shared between the web handlers and the background tasks
+#[derive(Clone)]
+pub struct JobRegistry {
+    inner: Arc<Mutex<Registry>>,
+    path: Option<PathBuf>,
+}
+
+/// Gets the path of the persisted download history
+pub fn get_history_path() -> PathBuf {
+    let mut path = get_app_data_dir();
+    path.push("history.json");
+    path
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl JobRegistry {
+    /// Loads the registry from `path`, starting empty if the file doesn't exist yet.
+    /// Jobs that were still running when the server stopped are marked as failed.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut registry: Registry = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(path)?)?
+        } else {
+            Registry::default()
+        };
+
+        for job in registry.jobs.iter_mut().filter(|job| job.status == JobStatus::Running) {
+            job.status = JobStatus::Failed;
+            job.error = Some("Interrupted by a server restart".to_string());
+        }
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(registry)),
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Records a new running job and returns its id
+    pub fn start(&self, options: &DownloadOptions) -> u64 {
+        self.modify(|registry| {
+            registry.next_id += 1;
+            let id = registry.next_id;
+            registry.jobs.push(Job { id, ..Job::from(options) });
+
+            // Drop the oldest finished jobs once the history is full. Running jobs are
+            // kept, or their outcome would have nowhere to go.
+            let mut excess = registry.jobs.len().saturating_sub(MAX_HISTORY);
+            registry.jobs.retain(|job| {
+                let evict = excess > 0 && job.status != JobStatus::Running;
+                excess -= usize::from(evict);
+                !evict
+            });
+            id
+        })
+    }
+
+    /// Marks a job as finished and records the files it saved
+    pub fn finish(&self, id: u64, summary: &DownloadSummary) {
+        self.update(id, |job| {
+            job.status = JobStatus::Finished;
+            job.output_dir = Some(summary.output_dir.clone());
+            job.files = summary.files.clone();
+        });
+    }
+
+    /// Marks a job as failed
+    pub fn fail(&self, id: u64, error: &str) {
+        self.update(id, |job| {
+            job.status = JobStatus::Failed;
+            job.error = Some(error.to_string());
+        });
+    }
+
+    /// Looks up a job by id
+    pub fn get(&self, id: u64) -> Option<Job> {
+        let registry = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        registry.jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    /// Lists jobs, most recent first
+    pub fn list(&self) -> Vec<Job> {
+        let registry = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        registry.jobs.iter().rev().cloned().collect()
+    }
+
+    /// Removes every job that isn't currently running
+    pub fn clear(&self) {
+        self.modify(|registry| registry.jobs.retain(|job| job.status == JobStatus::Running));
+    }
+
+    fn update(&self, id: u64, f: impl FnOnce(&mut Job)) {
+        self.modify(|registry| {
+            if let Some(job) = registry.jobs.iter_mut().find(|job| job.id == id) {
+                f(job);
+                job.finished_at = Some(now());
+            }
+        });
+    }
+
+    /// Applies a change to the registry and persists the result
+    fn modify<T>(&self, f: impl FnOnce(&mut Registry) -> T) -> T {
+        let mut registry = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let result = f(&mut registry);
+
+        if let Some(path) = &self.path {
+            let saved = serde_json::to_vec_pretty(&*registry)
+                .map_err(pinterestx::Error::from)
+                .and_then(|content| write_file_atomically(path, &content));
+            if let Err(e) = saved {
+                error!("Failed to save download history: {}", e);
+            }
+        }
+        result
+    }
+}
+
+impl Default for JobRegistry {
+    /// An in-memory registry that is never persisted
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Registry::default())),
+            path: None,
+        }
+    }
+}
+
+/// Builds the job and history routes
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/jobs/:id", get(job_status_handler))
+        .route("/api/history", get(list_history_handler).delete(clear_history_api_handler))
+        .route("/api/history/:id/rerun", post(rerun_api_handler))
+        .route("/history", delete(clear_history_handler))
+        .route("/history/:id/rerun", post(rerun_handler))
+}
+
+async fn job_status_handler(State(state): State<AppState>, UrlPath(id): UrlPath<u64>) -> Response {
+    match state.jobs.get(id) {
+        Some(job) => Json(job).into_response(),
+        None => (StatusCode::NOT_FOUND, "Unknown job").into_response(),
+    }
+}
+
+async fn list_history_handler(State(state): State<AppState>) -> Json<Vec<Job>> {
+    Json(state.jobs.list())
+}
+
+async fn clear_history_api_handler(State(state): State<AppState>) -> StatusCode {
+    state.jobs.clear();
+    StatusCode::NO_CONTENT
+}
+
+#[derive(Serialize)]
+struct RerunResponse {
+    job_id: u64,
+}
+
+async fn rerun_api_handler(State(state): State<AppState>, UrlPath(id): UrlPath<u64>) -> Response {
+    let Some(job) = state.jobs.get(id) else {
+        return (StatusCode::NOT_FOUND, "Unknown job").into_response();
+    };
+    info!("Re-running job {} ({})", id, job.url);
+    let job_id = spawn_download_job(&state, job.to_options());
+    Json(RerunResponse { job_id }).into_response()
+}
+
+/// HTMX variant of the clear action: returns the (now empty) history partial
+async fn clear_history_handler(State(state): State<AppState>) -> HtmlTemplate {
+    state.jobs.clear();
+    let mut context = Context::new();
+    context.insert("history", &state.jobs.list());
+    HtmlTemplate("partials/history.html".to_string(), context)
+}
+
+/// HTMX variant of the re-run action: returns the same partial as a new download
+async fn rerun_handler(State(state): State<AppState>, UrlPath(id): UrlPath<u64>) -> HtmlTemplate {
+    let mut context = Context::new();
+    match state.jobs.get(id) {
+        Some(job) => {
+            info!("Re-running job {} ({})", id, job.url);
+            let options = job.to_options();
+            context.insert("url", &options.url);
+            if options.max_images > 0 {
+                context.insert("max_images", &options.max_images);
+            }
+            let job_id = spawn_download_job(&state, options);
+            context.insert("success", &true);
+            context.insert("job_id", &job_id);
+        }
+        None => {
+            context.insert("success", &false);
+            context.insert("error_message", &format!("Unknown download #{}", id));
+        }
+    }
+    HtmlTemplate("partials/download_results.html".to_string(), context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::isolate_test_dirs;
+    use pinterestx::source::SearchScope;
+
+    #[test]
+    fn keeps_running_jobs_when_the_history_is_full() {
+        let jobs = JobRegistry::default();
+        let options = DownloadOptions { url: "https://www.pinterest.com/alice/cats/".to_string(), ..Default::default() };
+        let ids: Vec<u64> = (0..=MAX_HISTORY).map(|_| jobs.start(&options)).collect();
+        assert_eq!(jobs.list().len(), MAX_HISTORY + 1);
+
+        jobs.fail(ids[0], "boom");
+        jobs.fail(ids[1], "boom");
+        jobs.start(&options);
+
+        // The two oldest failed jobs make room, the running ones all stay
+        assert_eq!(jobs.list().len(), MAX_HISTORY);
+        assert!(jobs.get(ids[0]).is_none());
+        assert!(jobs.get(ids[1]).is_none());
+        jobs.fail(ids[2], "boom");
+        assert_eq!(jobs.get(ids[2]).unwrap().status, JobStatus::Failed);
+    }
+
+    #[test]
+    fn reruns_jobs_with_their_search_and_media_options() {
+        let path = isolate_test_dirs().join("data").join("history-rerun.json");
+        let options = DownloadOptions {
+            url: "https://www.pinterest.com/search/pins/?q=cats".to_string(),
+            media: MediaFilter::Videos,
+            sections: Some(SectionsMode::Folders),
+            search: SearchArgs {
+                scope: Some(SearchScope::Boards),
+                refinements: vec!["tabby".to_string()],
+                filters: vec![("color".to_string(), "red".to_string())],
+            },
+            ..Default::default()
+        };
+        let id = JobRegistry::load(&path).unwrap().start(&options);
+
+        let rerun = JobRegistry::load(&path).unwrap().get(id).unwrap().to_options();
+        assert_eq!(rerun.url, options.url);
+        assert_eq!(rerun.search, options.search);
+        assert_eq!(rerun.media, MediaFilter::Videos);
+        assert_eq!(rerun.sections, Some(SectionsMode::Folders));
+    }
+
+    #[test]
+    fn loads_history_saved_before_search_options() {
+        let path = isolate_test_dirs().join("data").join("history-old.json");
+        let job = r#"{ "id": 1, "url": "https://www.pinterest.com/alice/cats/", "genre": "Art", "query": "Cats",
+            "quality": "original", "max_images": 0, "status": "finished", "started_at": 1, "finished_at": 2,
+            "output_dir": null, "files": [], "error": null }"#;
+        std::fs::write(&path, format!(r#"{{ "next_id": 1, "jobs": [{}] }}"#, job)).unwrap();
+
+        let options = JobRegistry::load(&path).unwrap().get(1).unwrap().to_options();
+        assert_eq!(options.account, DEFAULT_ACCOUNT);
+        assert!(!options.search.is_set());
+        assert_eq!(options.media, MediaFilter::All);
+        assert_eq!(options.sections, None);
+        assert_eq!(options.workers, DEFAULT_WORKERS);
+    }
+
+    #[test]
+    fn rebuilds_every_download_option() {
+        let options = DownloadOptions {
+            url: "https://www.pinterest.com/alice/".to_string(),
+            genre: Some("Art".to_string()),
+            query: Some("Alice".to_string()),
+            quality: "736x".to_string(),
+            max_images: 50,
+            workers: 2,
+            sections: Some(SectionsMode::List),
+            boards: BoardFilters {
+                include: vec!["cat*".to_string()],
+                exclude: vec!["*old*".to_string()],
+                per_board_max: 10,
+            },
+            related: 5,
+            media: MediaFilter::Images,
+            search: SearchArgs {
+                scope: Some(SearchScope::Pins),
+                refinements: vec!["tabby".to_string()],
+                filters: vec![("color".to_string(), "red".to_string())],
+            },
+            strategies: vec!["resources".to_string(), "html".to_string()],
+            merge: true,
+            account: "work".to_string(),
+        };
+        assert_eq!(Job::from(&options).to_options(), options);
+    }
+}
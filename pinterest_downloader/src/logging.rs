@@ -0,0 +1,116 @@
+//! Logging setup.
+//!
+//! Everything the downloader reports goes through `tracing`. The CLI renders events as
+//! plain progress lines, the server uses the standard timestamped format, and either can
+//! switch to JSON lines for log aggregation.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use std::fmt;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+use crate::progress::LogWriter;
+
+/// Log filter used when neither `--log-level` nor `RUST_LOG` is set
+const DEFAULT_FILTER: &str = "info";
+
+/// Output format for log events
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, including span context
+    Json,
+}
+
+/// Where the logs are going to be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogMode {
+    /// Interactive command: short progress lines without timestamps
+    Cli,
+    /// Long-running web server: timestamped lines
+    Server,
+}
+
+/// Installs the global subscriber.
+/// `level` takes precedence over `RUST_LOG` and accepts the same filter directives.
+pub fn init(level: Option<&str>, format: LogFormat, mode: LogMode) -> Result<()> {
+    let filter = match level {
+        Some(level) => EnvFilter::try_new(level)
+            .map_err(|e| anyhow::anyhow!("Invalid log level '{}': {}", level, e))?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER)),
+    };
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = match (format, mode) {
+        (LogFormat::Json, _) => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .try_init(),
+        (LogFormat::Text, LogMode::Server) => builder.with_target(false).try_init(),
+        (LogFormat::Text, LogMode::Cli) => builder
+            .event_format(CliFormat)
+            .with_writer(LogWriter)
+            .try_init(),
+    };
+    result.map_err(|e| anyhow::anyhow!("Failed to initialize logging: {}", e))
+}
+
+/// Compact event format for the command line.
+/// Only the message is shown (plus the error, if the event carries one); the
+/// structured fields are meant for the JSON output.
+struct CliFormat;
+
+impl<S, N> FormatEvent<S, N> for CliFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        match *event.metadata().level() {
+            Level::ERROR => write!(writer, "Error: ")?,
+            Level::WARN => write!(writer, "Warning: ")?,
+            _ => {}
+        }
+        write!(writer, "{}", visitor.message)?;
+        if let Some(error) = visitor.error {
+            write!(writer, ": {}", error)?;
+        }
+        writeln!(writer)
+    }
+}
+
+/// Picks the message and error fields out of an event
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    error: Option<String>,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            "error" => self.error = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            "error" => self.error = Some(format!("{:?}", value)),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! User-defined download presets, persisted to `presets.json` in the application data directory.
+
+use anyhow::Result;
+use axum::{
+    extract::{Path as UrlPath, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Form, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tera::Context;
+use tracing::error;
+
+use pinterestx::paths::{get_app_data_dir, write_file_atomically};
+
+use crate::web::{AppState, HtmlTemplate};
+
+/// A saved set of download form values
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Preset {
+    pub id: u64,
+    pub name: String,
+    pub url: Option<String>,
+    pub genre: Option<String>,
+    pub query: Option<String>,
+    pub quality: Option<String>,
+    pub max_images: Option<usize>,
+}
+
+/// Values for a new preset, as sent by the API or the download form
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct NewPreset {
+    #[serde(default)]
+    pub name: String,
+    pub url: Option<String>,
+    pub genre: Option<String>,
+    pub query: Option<String>,
+    pub quality: Option<String>,
+    pub max_images: Option<String>,
+}
+
+/// Store of presets, shared between web handlers
+#[derive(Clone)]
+pub struct PresetStore {
+    presets: Arc<Mutex<Vec<Preset>>>,
+    path: Option<PathBuf>,
+}
+
+/// Gets the path of the persisted presets
+pub fn get_presets_path() -> PathBuf {
+    let mut path = get_app_data_dir();
+    path.push("presets.json");
+    path
+}
+
+/// Presets offered before the user has saved any of their own
+fn default_presets() -> Vec<Preset> {
+    [
+        ("Art: Digital Art", "Art", "Digital Art"),
+        ("Wallpapers: Nature", "Wallpapers", "Nature"),
+        ("Design: UI Inspiration", "Design", "UI Inspiration"),
+        ("Fashion: Outfits", "Fashion", "Outfits"),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, (name, genre, query))| Preset {
+        id: i as u64 + 1,
+        name: name.to_string(),
+        url: None,
+        genre: Some(genre.to_string()),
+        query: Some(query.to_string()),
+        quality: None,
+        max_images: None,
+    })
+    .collect()
+}
+
+/// Treats empty form fields as missing
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+impl PresetStore {
+    /// Loads presets from `path`, falling back to the built-in defaults if the file doesn't exist
+    pub fn load(path: &Path) -> Result<Self> {
+        let presets = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(path)?)?
+        } else {
+            default_presets()
+        };
+        Ok(Self {
+            presets: Arc::new(Mutex::new(presets)),
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Lists all presets in the order they were created
+    pub fn list(&self) -> Vec<Preset> {
+        self.presets.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Adds a preset and returns it
+    pub fn add(&self, new: NewPreset) -> Result<Preset> {
+        let genre = non_empty(new.genre);
+        let query = non_empty(new.query);
+        let name = non_empty(Some(new.name)).unwrap_or_else(|| match (&genre, &query) {
+            (Some(genre), Some(query)) => format!("{}: {}", genre, query),
+            (Some(name), None) | (None, Some(name)) => name.clone(),
+            (None, None) => "Untitled preset".to_string(),
+        });
+        let max_images = match non_empty(new.max_images) {
+            Some(value) => Some(value.parse::<usize>().map_err(|_| anyhow::anyhow!("Invalid max images: {}", value))?),
+            None => None,
+        };
+
+        let mut presets = self.presets.lock().unwrap_or_else(|e| e.into_inner());
+        let preset = Preset {
+            id: presets.iter().map(|p| p.id).max().unwrap_or(0) + 1,
+            name,
+            url: non_empty(new.url),
+            genre,
+            query,
+            quality: non_empty(new.quality),
+            max_images,
+        };
+        presets.push(preset.clone());
+        self.save(&presets);
+        Ok(preset)
+    }
+
+    /// Removes a preset, returning whether it existed
+    pub fn remove(&self, id: u64) -> bool {
+        let mut presets = self.presets.lock().unwrap_or_else(|e| e.into_inner());
+        let before = presets.len();
+        presets.retain(|p| p.id != id);
+        let removed = presets.len() != before;
+        if removed {
+            self.save(&presets);
+        }
+        removed
+    }
+
+    fn save(&self, presets: &[Preset]) {
+        let Some(path) = &self.path else { return };
+        let saved = serde_json::to_vec_pretty(presets)
+            .map_err(pinterestx::Error::from)
+            .and_then(|content| write_file_atomically(path, &content));
+        if let Err(e) = saved {
+            error!("Failed to save presets: {}", e);
+        }
+    }
+}
+
+impl Default for PresetStore {
+    /// An in-memory store with the default presets that is never persisted
+    fn default() -> Self {
+        Self {
+            presets: Arc::new(Mutex::new(default_presets())),
+            path: None,
+        }
+    }
+}
+
+/// Builds the preset routes
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/presets", get(list_presets_handler).post(create_preset_api_handler))
+        .route("/api/presets/:id", delete(delete_preset_api_handler))
+        .route("/presets", post(create_preset_handler))
+        .route("/presets/:id", delete(delete_preset_handler))
+}
+
+async fn list_presets_handler(State(state): State<AppState>) -> Json<Vec<Preset>> {
+    Json(state.presets.list())
+}
+
+async fn create_preset_api_handler(State(state): State<AppState>, Json(new): Json<NewPreset>) -> Response {
+    match state.presets.add(new) {
+        Ok(preset) => (StatusCode::CREATED, Json(preset)).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+async fn delete_preset_api_handler(State(state): State<AppState>, UrlPath(id): UrlPath<u64>) -> StatusCode {
+    if state.presets.remove(id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+fn presets_partial(state: &AppState) -> HtmlTemplate {
+    let mut context = Context::new();
+    context.insert("presets", &state.presets.list());
+    HtmlTemplate("partials/presets.html".to_string(), context)
+}
+
+/// HTMX variant: saves the current download form as a preset.
+/// The preset name comes from the `HX-Prompt` header when the user was asked for one.
+async fn create_preset_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(mut new): Form<NewPreset>,
+) -> Response {
+    if let Some(name) = headers.get("HX-Prompt").and_then(|v| v.to_str().ok()) {
+        new.name = name.to_string();
+    }
+    match state.presets.add(new) {
+        Ok(_) => presets_partial(&state).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// HTMX variant: deletes a preset and returns the updated list
+async fn delete_preset_handler(State(state): State<AppState>, UrlPath(id): UrlPath<u64>) -> HtmlTemplate {
+    state.presets.remove(id);
+    presets_partial(&state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::isolate_test_dirs;
+
+    fn names(store: &PresetStore) -> Vec<String> {
+        store.list().into_iter().map(|preset| preset.name).collect()
+    }
+
+    #[test]
+    fn starts_with_the_default_presets() {
+        let path = isolate_test_dirs().join("data").join("presets-defaults.json");
+        let store = PresetStore::load(&path).unwrap();
+        assert_eq!(names(&store), ["Art: Digital Art", "Wallpapers: Nature", "Design: UI Inspiration", "Fashion: Outfits"]);
+        // Nothing is written until a preset changes
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn saves_and_deletes_presets_across_restarts() {
+        let path = isolate_test_dirs().join("data").join("presets-round-trip.json");
+        let store = PresetStore::load(&path).unwrap();
+        let preset = store
+            .add(NewPreset {
+                name: " ".to_string(),
+                url: Some("https://www.pinterest.com/alice/cats/".to_string()),
+                genre: Some("Animals".to_string()),
+                query: Some("Cats".to_string()),
+                quality: Some(String::new()),
+                max_images: Some("25".to_string()),
+            })
+            .unwrap();
+        assert_eq!(preset.id, 5);
+        assert_eq!(preset.name, "Animals: Cats");
+
+        let reloaded = PresetStore::load(&path).unwrap();
+        let saved = reloaded.list().into_iter().find(|p| p.id == preset.id).unwrap();
+        assert_eq!(saved.url.as_deref(), Some("https://www.pinterest.com/alice/cats/"));
+        assert_eq!(saved.quality, None);
+        assert_eq!(saved.max_images, Some(25));
+
+        assert!(reloaded.remove(1));
+        assert!(!reloaded.remove(1));
+        let reloaded = PresetStore::load(&path).unwrap();
+        assert_eq!(names(&reloaded), ["Wallpapers: Nature", "Design: UI Inspiration", "Fashion: Outfits", "Animals: Cats"]);
+    }
+
+    #[test]
+    fn rejects_an_invalid_image_limit() {
+        let path = isolate_test_dirs().join("data").join("presets-invalid.json");
+        let store = PresetStore::load(&path).unwrap();
+        let error = store.add(NewPreset { max_images: Some("lots".to_string()), ..Default::default() }).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid max images: lots");
+        assert_eq!(store.list().len(), 4);
+        assert!(!path.exists());
+    }
+}
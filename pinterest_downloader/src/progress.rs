@@ -0,0 +1,243 @@
+//! Progress display for download runs.
+//!
+//! On an interactive terminal the CLI draws live progress bars from the library's
+//! progress events: one for the whole run (images, bytes per second, ETA) and one per
+//! download worker showing its current file. Everywhere else, including the web
+//! server and piped output, progress is reported as plain log lines. Either way the
+//! CLI ends the run with a summary table of its [`DownloadStats`].
+
+use indicatif::{HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use once_cell::sync::Lazy;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::info;
+use tracing_subscriber::fmt::MakeWriter;
+
+use pinterestx::progress::{DownloadStats, ProgressEvent};
+
+/// How many failed URLs are listed per error kind in the summary
+const MAX_LISTED_FAILURES: usize = 5;
+
+/// Progress bars currently on screen, so log output can be printed above them
+static ACTIVE_BARS: Lazy<Mutex<Option<MultiProgress>>> = Lazy::new(|| Mutex::new(None));
+
+/// How progress is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// Live progress bars (interactive terminals only)
+    Bars,
+    /// One log line per image
+    Lines,
+}
+
+impl ProgressMode {
+    /// Uses progress bars when stdout is a terminal, plain lines otherwise
+    pub fn detect() -> Self {
+        if std::io::stdout().is_terminal() {
+            ProgressMode::Bars
+        } else {
+            ProgressMode::Lines
+        }
+    }
+}
+
+/// The bars of a run, drawn once it has started
+struct Bars {
+    overall: ProgressBar,
+    workers: Vec<ProgressBar>,
+}
+
+/// Shows the progress events of one download run; the bars are removed when it's dropped
+pub struct ProgressReporter {
+    mode: ProgressMode,
+    total: AtomicUsize,
+    started: Instant,
+    bytes: AtomicU64,
+    bars: OnceLock<Bars>,
+}
+
+impl ProgressReporter {
+    pub fn new(mode: ProgressMode) -> Self {
+        Self {
+            mode,
+            total: AtomicUsize::new(0),
+            started: Instant::now(),
+            bytes: AtomicU64::new(0),
+            bars: OnceLock::new(),
+        }
+    }
+
+    /// Shows one event; pass it to [`pinterestx::download`] as `|event| reporter.report(event)`
+    pub fn report(&self, event: ProgressEvent<'_>) {
+        match event {
+            ProgressEvent::Started { total, workers, .. } => {
+                self.total.store(total, Ordering::Relaxed);
+                if self.mode == ProgressMode::Bars {
+                    let _ = self.bars.set(draw_bars(total, workers));
+                }
+            }
+            ProgressEvent::FileStarted { worker, filename } => {
+                if let Some(bar) = self.worker_bar(worker) {
+                    bar.set_message(filename.to_string());
+                    bar.tick();
+                }
+            }
+            ProgressEvent::Bytes { worker, filename, file_bytes, chunk } => {
+                let bytes = self.bytes.fetch_add(chunk, Ordering::Relaxed) + chunk;
+                if let Some(bar) = self.worker_bar(worker) {
+                    bar.set_message(format!("{} ({})", filename, HumanBytes(file_bytes)));
+                }
+                if let Some(bars) = self.bars.get() {
+                    let rate = bytes as f64 / self.started.elapsed().as_secs_f64().max(0.001);
+                    bars.overall.set_message(format!("{}  {}/s", HumanBytes(bytes), HumanBytes(rate as u64)));
+                }
+            }
+            ProgressEvent::Saved { worker, index, filename, size } => {
+                self.file_done(worker);
+                if self.mode == ProgressMode::Lines {
+                    info!(file = %filename, bytes = size, "[{}/{}] Saved {} ({})", index, self.total(), filename, HumanBytes(size));
+                }
+            }
+            ProgressEvent::Duplicate { worker, index } => {
+                self.file_done(worker);
+                if self.mode == ProgressMode::Lines {
+                    info!("[{}/{}] Skipped duplicate of an already downloaded image", index, self.total());
+                }
+            }
+            // The library logs failures itself
+            ProgressEvent::Failed { worker, .. } => self.file_done(worker),
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    fn worker_bar(&self, worker: usize) -> Option<&ProgressBar> {
+        self.bars.get().and_then(|bars| bars.workers.get(worker))
+    }
+
+    /// Moves the overall bar on and shows a worker as idle again
+    fn file_done(&self, worker: usize) {
+        if let Some(bars) = self.bars.get() {
+            bars.overall.inc(1);
+        }
+        if let Some(bar) = self.worker_bar(worker) {
+            bar.set_message("idle");
+        }
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        let Some(bars) = self.bars.get() else { return };
+        bars.overall.finish_and_clear();
+        for bar in &bars.workers {
+            bar.finish_and_clear();
+        }
+        if let Some(multi) = ACTIVE_BARS.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            let _ = multi.clear();
+        }
+    }
+}
+
+/// Puts the bars of a run on screen
+fn draw_bars(total: usize, workers: usize) -> Bars {
+    let multi = MultiProgress::with_draw_target(ProgressDrawTarget::stdout());
+    let overall = multi.add(ProgressBar::new(total as u64));
+    overall.set_style(
+        ProgressStyle::with_template("{bar:30.cyan/blue} {pos}/{len} images  {msg}  ETA {eta}")
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    overall.enable_steady_tick(Duration::from_millis(200));
+
+    let worker_style = ProgressStyle::with_template("  {spinner} worker {prefix}: {msg}")
+        .expect("valid progress template");
+    let workers = (1..=workers)
+        .map(|i| {
+            let bar = multi.add(ProgressBar::new_spinner());
+            bar.set_style(worker_style.clone());
+            bar.set_prefix(i.to_string());
+            bar.set_message("idle");
+            bar
+        })
+        .collect();
+    *ACTIVE_BARS.lock().unwrap_or_else(|e| e.into_inner()) = Some(multi);
+    Bars { overall, workers }
+}
+
+/// Renders the end-of-run summary table
+pub fn render_summary(stats: &DownloadStats) -> String {
+    let mut out = String::new();
+    let rows = [
+        ("Downloaded", stats.downloaded.to_string()),
+        ("Duplicates", stats.duplicates.to_string()),
+        ("Failed", stats.failed.to_string()),
+        ("Filtered", stats.filtered.to_string()),
+        ("Total size", HumanBytes(stats.bytes).to_string()),
+        ("Elapsed", HumanDuration(stats.elapsed).to_string()),
+    ];
+
+    out.push_str("+------------+--------------+\n");
+    for (label, value) in rows {
+        out.push_str(&format!("| {:<10} | {:>12} |\n", label, value));
+    }
+    out.push_str("+------------+--------------+\n");
+
+    if !stats.failures.is_empty() {
+        out.push_str("\nFailures:\n");
+        for (kind, urls) in &stats.failures {
+            out.push_str(&format!("  {} ({})\n", kind, urls.len()));
+            for url in urls.iter().take(MAX_LISTED_FAILURES) {
+                out.push_str(&format!("    {}\n", url));
+            }
+            if urls.len() > MAX_LISTED_FAILURES {
+                out.push_str(&format!("    ... and {} more\n", urls.len() - MAX_LISTED_FAILURES));
+            }
+        }
+    }
+    out
+}
+
+/// Log writer for the CLI that prints above any active progress bars
+#[derive(Clone, Copy, Default)]
+pub struct LogWriter;
+
+/// One buffered log event, written out when dropped
+pub struct LogLine(Vec<u8>);
+
+impl Write for LogLine {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogLine {
+    fn drop(&mut self) {
+        let write = || {
+            let mut stdout = std::io::stdout().lock();
+            let _ = stdout.write_all(&self.0);
+            let _ = stdout.flush();
+        };
+        match ACTIVE_BARS.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            Some(multi) => multi.suspend(write),
+            None => write(),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogWriter {
+    type Writer = LogLine;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogLine(Vec::new())
+    }
+}
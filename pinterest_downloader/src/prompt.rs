@@ -0,0 +1,121 @@
+//! Asking for cookie consent before a run.
+//!
+//! Consent is given up front (see [`pinterestx::consent`]) or else asked for on the
+//! terminal. When stdin isn't a terminal, the run fails instead of waiting for an
+//! answer that will never come.
+
+use anyhow::Result;
+use std::io::{IsTerminal, Write};
+use tracing::info;
+
+use pinterestx::config::get_config_path;
+use pinterestx::consent::{self, has_cookie_consent, ChoiceSource, ConsentChoice};
+
+/// What to do about consent before a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConsentAction {
+    /// Record consent given ahead of time
+    Grant(ChoiceSource),
+    /// Turn cookies off for this run only, leaving stored consent and cookies alone
+    Disable,
+    /// Go on with the consent already stored
+    Keep,
+    /// Ask on the terminal
+    Ask,
+    /// Nobody is there to ask
+    Fail,
+}
+
+/// Decides what to do from the choice given ahead of time, whether consent is already
+/// stored, and whether stdin is a terminal
+fn consent_action(choice: Option<(ConsentChoice, ChoiceSource)>, stored: bool, interactive: bool) -> ConsentAction {
+    match choice {
+        Some((ConsentChoice::Accept, _)) if stored => ConsentAction::Keep,
+        Some((ConsentChoice::Accept, source)) => ConsentAction::Grant(source),
+        Some((ConsentChoice::Decline, _)) => ConsentAction::Disable,
+        None if stored => ConsentAction::Keep,
+        None if interactive => ConsentAction::Ask,
+        None => ConsentAction::Fail,
+    }
+}
+
+/// Makes sure the consent question for an account is answered before a run:
+/// by the choice given ahead of time, by stored consent, or by asking the user
+pub fn ensure_consent(account: &str, flag: Option<ConsentChoice>) -> Result<()> {
+    let choice = consent::resolve_choice(flag)?;
+    match consent_action(choice, has_cookie_consent(account), std::io::stdin().is_terminal()) {
+        ConsentAction::Grant(source) => {
+            consent::grant_consent(account)?;
+            info!("Cookie consent granted for account '{}' by {}", account, source.describe());
+        }
+        ConsentAction::Disable => consent::disable_cookies(),
+        ConsentAction::Keep => {}
+        ConsentAction::Ask => request_cookie_consent(account)?,
+        ConsentAction::Fail => {
+            return Err(anyhow::anyhow!(
+                "Cookie consent hasn't been given for account '{}' and stdin is not a terminal, so it can't be asked for. \
+                 Pass --accept-cookies or --no-cookies, set {}=accept|decline, or set \"cookie_consent\" in {}",
+                account,
+                consent::CONSENT_ENV,
+                get_config_path().display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Requests cookie consent for an account from the user
+fn request_cookie_consent(account: &str) -> Result<()> {
+    println!("\n===== COOKIE CONSENT NOTICE =====");
+    println!("PinterestX would like to store Pinterest session cookies to:");
+    println!("1. Improve image extraction capabilities");
+    println!("2. Bypass anti-scraping measures");
+    println!("3. Access content that requires authentication");
+    println!();
+    println!("These cookies will be stored locally on your computer and are not sent to any third parties.");
+    println!("You can clear the cookies at any time using the 'clear-cookies' command.");
+    println!();
+
+    // Prompt for consent
+    print!("Do you consent to storing Pinterest cookies? (yes/no): ");
+    std::io::stdout().flush()?;
+
+    let mut response = String::new();
+    std::io::stdin().read_line(&mut response)?;
+
+    let response = response.trim().to_lowercase();
+    if response == "yes" || response == "y" {
+        // Save consent
+        consent::grant_consent(account)?;
+        println!("Thank you. Cookie consent has been granted.");
+    } else {
+        println!("Cookie consent declined. PinterestX will function with limited capabilities.");
+        println!("Note: Some Pinterest content may not be accessible without cookies.");
+        println!("You can enable cookies later by rerunning the application.");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_asks_when_the_choice_is_given() {
+        let accept = Some((ConsentChoice::Accept, ChoiceSource::Env));
+        let decline = Some((ConsentChoice::Decline, ChoiceSource::Config));
+        assert_eq!(consent_action(accept, false, false), ConsentAction::Grant(ChoiceSource::Env));
+        assert_eq!(consent_action(accept, true, true), ConsentAction::Keep);
+        // Declining works without a terminal, and overrides stored consent for the run
+        assert_eq!(consent_action(decline, false, false), ConsentAction::Disable);
+        assert_eq!(consent_action(decline, true, true), ConsentAction::Disable);
+    }
+
+    #[test]
+    fn asks_only_on_a_terminal() {
+        assert_eq!(consent_action(None, true, false), ConsentAction::Keep);
+        assert_eq!(consent_action(None, false, true), ConsentAction::Ask);
+        assert_eq!(consent_action(None, false, false), ConsentAction::Fail);
+    }
+}
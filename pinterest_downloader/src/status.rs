@@ -0,0 +1,166 @@
+//! Reports printed by the status commands: `consent`, `cookies status`,
+//! `strategies`, `diagnose` and `download --sections list`.
+
+use anyhow::Result;
+use cookie_store::{CookieDomain, CookieExpiration};
+use indicatif::HumanDuration;
+use std::time::{Duration, SystemTime};
+
+use pinterestx::accounts;
+use pinterestx::boards;
+use pinterestx::config::Config;
+use pinterestx::consent::{self, consent_timestamp, get_cookie_consent_path, has_cookie_consent, ConsentChoice};
+use pinterestx::cookies;
+use pinterestx::diagnostics;
+use pinterestx::resolve;
+use pinterestx::source::PinterestSource;
+use pinterestx::encryption;
+use pinterestx::metrics::StrategyMetrics;
+use pinterestx::{DownloadOptions, Registry, Session, Target};
+
+/// Prints the consent state of every account (for the `consent` command)
+pub fn print_consent(flag: Option<ConsentChoice>) -> Result<()> {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    println!("  {:<20} {:<12} GRANTED", "ACCOUNT", "CONSENT");
+    for account in accounts::list() {
+        let (state, granted) = match consent_timestamp(&account) {
+            Some(at) => {
+                let ago = HumanDuration(Duration::from_secs(now.saturating_sub(at)));
+                ("granted", format!("{} ago (unix time {})", ago, at))
+            }
+            None if get_cookie_consent_path(&account).exists() => ("granted", "unknown".to_string()),
+            None => ("not given", "-".to_string()),
+        };
+        println!("  {:<20} {:<12} {}", account, state, granted);
+    }
+
+    match consent::resolve_choice(flag)? {
+        Some((ConsentChoice::Accept, source)) => println!("\nCookies are accepted without asking, by {}.", source.describe()),
+        Some((ConsentChoice::Decline, source)) => println!("\nCookies are turned off, by {}.", source.describe()),
+        None => println!("\nAccounts without consent are asked for it when used interactively."),
+    }
+    Ok(())
+}
+
+/// Prints the stored cookies and when they expire, without their values
+pub fn print_cookies(account: &str) -> Result<()> {
+    let path = cookies::get_cookies_path(account);
+    println!("Account:      {}", account);
+    println!("Cookie store: {}", path.display());
+    println!("Encryption:   {}", encryption::key_source().describe());
+    if !has_cookie_consent(account) {
+        println!("Cookie consent has not been given; no cookies are stored.");
+        return Ok(());
+    }
+
+    let jar = cookies::load_jar(account)?;
+    let store = jar.lock().unwrap_or_else(|e| e.into_inner());
+    let mut cookies: Vec<_> = store.iter_unexpired().collect();
+    if cookies.is_empty() {
+        println!("No cookies stored.");
+        return Ok(());
+    }
+    cookies.sort_by_key(|c| (String::from(&c.domain), c.name().to_string()));
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    println!("{} cookies:", cookies.len());
+    println!("  {:<28} {:<22} {:<24} FLAGS", "NAME", "DOMAIN", "EXPIRES");
+    for cookie in cookies {
+        let expires = match &cookie.expires {
+            CookieExpiration::AtUtc(at) => {
+                let remaining = (at.unix_timestamp() - now).max(0) as u64;
+                format!("in {}", HumanDuration(Duration::from_secs(remaining)))
+            }
+            CookieExpiration::SessionEnd => "end of session".to_string(),
+        };
+        let domain = match &cookie.domain {
+            CookieDomain::Suffix(domain) => format!(".{}", domain),
+            other => String::from(other),
+        };
+        let flags: Vec<&str> = [
+            (cookie.secure() == Some(true)).then_some("secure"),
+            (cookie.http_only() == Some(true)).then_some("httponly"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        println!("  {:<28} {:<22} {:<24} {}", cookie.name(), domain, expires, flags.join(","));
+    }
+    Ok(())
+}
+
+/// Prints the extraction strategies in the order they're tried, and their recorded results
+pub fn print_strategies() -> Result<()> {
+    let config = Config::load()?;
+    let registry = Registry::configured(&config.strategy_order, &[])?;
+    println!("Extraction strategies, in the order they're tried:");
+    for extractor in registry.extractors() {
+        println!("  {:<14} {}", extractor.name(), extractor.description());
+    }
+    println!();
+
+    let metrics = StrategyMetrics::load()?;
+    if metrics.strategies.is_empty() {
+        println!("No extraction runs recorded yet.");
+        return Ok(());
+    }
+    print!("{}", metrics.render(&registry.names(), now()));
+    Ok(())
+}
+
+/// Runs every extraction strategy on a URL, up to `limit` images each, and prints how
+/// each did (for `diagnose`)
+pub async fn print_diagnosis(input: &str, account: &str, limit: usize, session: &Session) -> Result<()> {
+    let url = resolve::resolve(input, session).await?;
+    let source = PinterestSource::parse(&url);
+    match &source {
+        Some(source) => println!("Diagnosing {} ({})\n", url, source),
+        None => println!("Diagnosing {} (not a known kind of Pinterest page)\n", url),
+    }
+
+    let options = DownloadOptions { url: url.to_string(), account: account.to_string(), max_images: limit, ..Default::default() };
+    let target = Target::new(&url, source.as_ref(), &options);
+    let config = Config::load()?;
+    let registry = Registry::configured(&config.strategy_order, &[])?;
+    let reports = diagnostics::compare_strategies(&registry, &target, session, limit).await;
+    println!("Each strategy stops after {} images (--limit).", limit);
+    print!("{}", diagnostics::render_comparison(&reports));
+    if reports.iter().any(|report| report.capped) {
+        println!("\n+ stopped at the limit; it may find more with a higher --limit.");
+    }
+
+    match session.debug_dump() {
+        Some(dump) => println!("\nThe pages and resource responses fetched, with a report of each page, were saved to {}.", dump.dir().display()),
+        None => println!("\nRun with --debug-dump <dir> to save the pages and resource responses fetched and a report of what matched."),
+    }
+    Ok(())
+}
+
+/// Prints the sections of the board a URL points to (for `--sections list`)
+pub async fn print_sections(input: &str, session: &Session) -> Result<()> {
+    let (user, board, sections) = boards::list_sections(input, session).await?;
+    if sections.is_empty() {
+        println!("Board {}/{} has no sections.", user, board);
+        return Ok(());
+    }
+
+    println!("Sections of board {}/{}:", user, board);
+    for section in &sections {
+        println!("  {:<30} {:>6} pins  {}", section.slug, section.pin_count, section.title);
+    }
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
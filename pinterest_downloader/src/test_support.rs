@@ -0,0 +1,22 @@
+//! Shared setup for the unit tests of the web server's modules.
+
+use std::path::PathBuf;
+use std::sync::Once;
+
+use pinterestx::paths::{get_app_data_dir, set_app_data_dir, set_output_root};
+
+/// Points the data directory and the output root at a fresh directory for this test
+/// run, so presets, history and collections don't touch the user's own. Returns the
+/// directory, which holds `data` and `Pictures`.
+pub fn isolate_test_dirs() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pinterest-downloader-unit-{}", std::process::id()));
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("Pictures")).unwrap();
+        set_app_data_dir(dir.join("data")).expect("the data directory is used before the tests isolate it");
+        set_output_root(dir.join("Pictures")).expect("the output root is used before the tests isolate it");
+        get_app_data_dir();
+    });
+    dir
+}
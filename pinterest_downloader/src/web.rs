@@ -0,0 +1,306 @@
+//! The web interface: a download form, with job history, presets, a gallery and
+//! archive export mounted from their own modules.
+
+use anyhow::Result;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use once_cell::sync::Lazy;
+use rust_embed::RustEmbed;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::Mutex as StdMutex;
+use tera::{Context, Tera};
+use tower_http::trace::TraceLayer;
+use tracing::{error, info, info_span, warn, Instrument};
+use url::Url;
+
+use pinterestx::accounts::{self, DEFAULT_ACCOUNT};
+use pinterestx::consent;
+use pinterestx::search::{self, SearchArgs};
+use pinterestx::session::SessionPool;
+use pinterestx::source::SearchScope;
+use pinterestx::{download, DownloadOptions};
+
+use crate::jobs::{self, JobRegistry};
+use crate::presets::{self, PresetStore};
+use crate::progress::{ProgressMode, ProgressReporter};
+use crate::{archive, clear_cookies, gallery};
+
+#[derive(RustEmbed)]
+#[folder = "templates/"]
+pub struct Templates;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub jobs: JobRegistry,
+    pub presets: PresetStore,
+    pub sessions: SessionPool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct DownloadRequest {
+    url: String,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    genre: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    query: Option<String>,
+    quality: String,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    max_images: Option<usize>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    account: Option<String>,
+    /// Search scope, for search URLs
+    #[serde(default, deserialize_with = "empty_as_none")]
+    scope: Option<SearchScope>,
+    /// Comma-separated refinement terms, for search URLs
+    #[serde(default, deserialize_with = "empty_as_none")]
+    refine: Option<String>,
+    /// Comma-separated KEY=VALUE search filters, for search URLs
+    #[serde(default, deserialize_with = "empty_as_none")]
+    search_filters: Option<String>,
+}
+
+/// Deserializes an empty form field as `None` rather than failing to parse it
+fn empty_as_none<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match Option::<String>::deserialize(deserializer)?.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+impl From<DownloadRequest> for DownloadOptions {
+    fn from(request: DownloadRequest) -> Self {
+        let list = |value: &Option<String>| -> Vec<String> {
+            value.iter().flat_map(|value| value.split(',')).map(str::trim).filter(|item| !item.is_empty()).map(str::to_string).collect()
+        };
+        let filters = list(&request.search_filters)
+            .iter()
+            .filter_map(|filter| match search::parse_filter(filter) {
+                Ok(filter) => Some(filter),
+                Err(e) => {
+                    warn!("Ignoring search filter: {}", e);
+                    None
+                }
+            })
+            .collect();
+        let search = SearchArgs { scope: request.scope, refinements: list(&request.refine), filters };
+        Self {
+            url: request.url,
+            genre: request.genre,
+            query: request.query,
+            quality: request.quality,
+            max_images: request.max_images.unwrap_or(0),
+            search,
+            account: request.account.unwrap_or_else(|| DEFAULT_ACCOUNT.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+pub struct HtmlTemplate(pub String, pub Context);
+
+impl IntoResponse for HtmlTemplate {
+    fn into_response(self) -> Response {
+        let Self(template_name, context) = self;
+        // Lock the globally initialized Tera instance
+        match TEMPLATES.lock() {
+            Ok(tera) => {
+                match tera.render(&template_name, &context) {
+                    Ok(html) => Html(html).into_response(),
+                    Err(err) => (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Failed to render template '{}':\n{}", template_name, err),
+                    ).into_response()
+                }
+            }
+            Err(poisoned) => {
+                // Handle the case where the mutex is poisoned
+                 (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Template engine lock poisoned: {}", poisoned),
+                ).into_response()
+            }
+        }
+    }
+}
+
+// Initialize Tera from embedded assets
+static TEMPLATES: Lazy<StdMutex<Tera>> = Lazy::new(|| {
+    let mut tera = Tera::default();
+    // Iterate over embedded files and add them to Tera
+    for file_path in Templates::iter() {
+        let path = file_path.as_ref();
+        if let Some(file) = Templates::get(path) {
+            if let Ok(content) = std::str::from_utf8(file.data.as_ref()) {
+                if tera.add_raw_template(path, content).is_err() {
+                    eprintln!("Failed to load embedded template: {}", path);
+                }
+            } else {
+                 eprintln!("Failed to read embedded template as UTF-8: {}", path);
+            }
+        } else {
+            eprintln!("Failed to get embedded template file: {}", path);
+        }
+    }
+    tera.autoescape_on(vec![".html"]); // Adjust as needed
+    StdMutex::new(tera)
+});
+
+/// Cookie consent request from the web interface
+#[derive(Deserialize, Debug)]
+struct CookieConsentRequest {
+    consent: bool,
+}
+
+/// Handler for cookie consent from the web interface
+async fn cookie_consent_handler(
+    Json(payload): Json<CookieConsentRequest>,
+) -> impl IntoResponse {
+    info!("Received cookie consent: {}", payload.consent);
+    
+    if payload.consent {
+        if let Err(e) = consent::grant_consent(DEFAULT_ACCOUNT) {
+            error!("Failed to create cookie consent file: {}", e);
+        }
+    } else {
+        // Remove any cookie consent file and cookies
+        let _ = consent::revoke_consent(DEFAULT_ACCOUNT);
+        
+        // Also clear any cookies
+        let _ = clear_cookies(DEFAULT_ACCOUNT);
+    }
+    
+    // Return success
+    StatusCode::OK
+}
+
+/// Serves the web interface on http://127.0.0.1:3000
+pub async fn run_server() -> Result<()> {
+    info!("Starting web server...");
+
+    // Trigger lazy initialization of templates to catch errors early
+    if TEMPLATES.lock().is_err() {
+        return Err(anyhow::anyhow!("Failed to initialize template engine"));
+    }
+
+    let app_state = AppState {
+        jobs: JobRegistry::load(&jobs::get_history_path())?,
+        presets: PresetStore::load(&presets::get_presets_path())?,
+        sessions: SessionPool::default(),
+    };
+
+    let app = Router::new()
+        .route("/", get(root_handler))
+        .route("/download", post(download_handler))
+        .route("/api/cookie-consent", post(cookie_consent_handler))
+        .merge(jobs::routes())
+        .merge(presets::routes())
+        .merge(gallery::routes())
+        .merge(archive::routes())
+        .layer(TraceLayer::new_for_http()) // Apply logging
+        .with_state(app_state); // Pass state if needed by handlers
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    info!("Web server listening on {}", addr);
+    info!("Open http://127.0.0.1:3000 in your browser to access the Pinterest Downloader.");
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+async fn root_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let mut context = Context::new();
+    let history: Vec<_> = state.jobs.list().into_iter().take(jobs::RECENT_HISTORY).collect();
+    context.insert("history", &history);
+    context.insert("presets", &state.presets.list());
+    context.insert("accounts", &accounts::list());
+    HtmlTemplate("index.html".to_string(), context)
+}
+
+/// Registers a download job and runs it in the background, returning the job id
+pub fn spawn_download_job(state: &AppState, options: DownloadOptions) -> u64 {
+    let job_id = state.jobs.start(&options);
+    let jobs = state.jobs.clone();
+    let sessions = state.sessions.clone();
+
+    let job_span = info_span!("job", id = job_id);
+    tokio::spawn(
+        async move {
+            info!("Starting background download {} for {}", job_id, options.url);
+            let result = async {
+                let session = sessions.get(&options.account)?;
+                let reporter = ProgressReporter::new(ProgressMode::Lines);
+                download(&options, &session, |event| reporter.report(event)).await
+            }
+            .await;
+            match result {
+                Ok(summary) => {
+                    info!(
+                        "Download {} completed: {} downloaded, {} duplicates, {} failed",
+                        job_id, summary.stats.downloaded, summary.stats.duplicates, summary.stats.failed
+                    );
+                    jobs.finish(job_id, &summary);
+                }
+                Err(e) => {
+                    error!(error = %e, "Download {} failed", job_id);
+                    jobs.fail(job_id, &e.to_string());
+                }
+            }
+        }
+        .instrument(job_span),
+    );
+
+    job_id
+}
+
+async fn download_handler(
+    State(state): State<AppState>,
+    Form(payload): Form<DownloadRequest>,
+) -> impl IntoResponse {
+    info!("Received download request: {:?}", payload);
+    let mut context = Context::new();
+    context.insert("url", &payload.url);
+    
+    // Also add the max_images to the context for display
+    if let Some(max) = payload.max_images {
+        if max > 0 {
+            context.insert("max_images", &max);
+        }
+    }
+
+    if let Some(account) = &payload.account {
+        if let Err(e) = accounts::parse_name(account) {
+            context.insert("success", &false);
+            context.insert("error_message", &e.to_string());
+            return HtmlTemplate("partials/download_results.html".to_string(), context);
+        }
+    }
+
+    match Url::parse(&payload.url) {
+        Ok(_) => {
+            // Spawn a background task to process the download
+            let job_id = spawn_download_job(&state, DownloadOptions::from(payload.clone()));
+
+            context.insert("success", &true);
+            context.insert("job_id", &job_id);
+        }
+        Err(e) => {
+            context.insert("success", &false);
+            context.insert("error_message", &format!("Invalid URL: {}", e));
+        }
+    }
+
+    HtmlTemplate("partials/download_results.html".to_string(), context)
+}
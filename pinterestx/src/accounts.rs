@@ -0,0 +1,114 @@
+//! Named Pinterest accounts.
+//!
+//! Each account is a separate session profile with its own cookie jar and cookie
+//! consent. The `default` account keeps its files directly in the application data
+//! directory, where they have always been; other accounts live in
+//! `accounts/<name>/`.
+
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+use crate::paths::get_app_data_dir;
+
+/// Account used when none is given
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// Longest accepted account name
+const MAX_NAME_LEN: usize = 32;
+
+/// Checks an account name, for use as a clap value parser.
+/// Names become directory names, so only letters, digits, `-` and `_` are allowed.
+pub fn parse_name(name: &str) -> Result<String> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err(Error::InvalidInput(format!(
+            "Invalid account name '{}': use up to {} letters, digits, '-' or '_'",
+            name,
+            MAX_NAME_LEN
+        )))
+    }
+}
+
+/// Directory holding an account's cookies and consent
+pub fn account_dir(account: &str) -> PathBuf {
+    let mut path = get_app_data_dir();
+    if account != DEFAULT_ACCOUNT {
+        path.push("accounts");
+        path.push(account);
+        if !path.exists() {
+            let _ = std::fs::create_dir_all(&path);
+        }
+    }
+    path
+}
+
+/// Lists the known accounts, starting with the default one
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(get_app_data_dir().join("accounts"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| parse_name(name).is_ok() && name != DEFAULT_ACCOUNT)
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names.insert(0, DEFAULT_ACCOUNT.to_string());
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consent::{grant_consent, has_cookie_consent};
+    use crate::cookies::{get_cookies_path, load_jar, save_jar};
+    use crate::paths::isolate_test_data;
+    use url::Url;
+
+    #[test]
+    fn accepts_only_safe_directory_names() {
+        for name in ["default", "alice", "Work_2-b", &"a".repeat(MAX_NAME_LEN)] {
+            assert_eq!(parse_name(name).unwrap(), name);
+        }
+        for name in ["", ".", "..", "../alice", "a/b", "a\\b", "with space", "caf\u{e9}", &"a".repeat(MAX_NAME_LEN + 1)] {
+            let error = parse_name(name).unwrap_err();
+            assert!(matches!(error, Error::InvalidInput(_)), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn keeps_accounts_apart() {
+        let data_dir = isolate_test_data();
+        assert_eq!(account_dir(DEFAULT_ACCOUNT), data_dir);
+        assert_eq!(account_dir("alice"), data_dir.join("accounts/alice"));
+        assert_ne!(get_cookies_path("alice"), get_cookies_path("bob"));
+        assert_ne!(get_cookies_path("alice"), get_cookies_path(DEFAULT_ACCOUNT));
+
+        // Consent and cookies are each account's own
+        grant_consent("alice").unwrap();
+        grant_consent("bob").unwrap();
+        assert!(!has_cookie_consent(DEFAULT_ACCOUNT));
+        let url = Url::parse("https://www.pinterest.com/").unwrap();
+        for account in ["alice", "bob"] {
+            let jar = load_jar(account).unwrap();
+            jar.lock().unwrap().parse(&format!("_pinterest_sess={}; Max-Age=3600", account), &url).unwrap();
+            save_jar(&jar, account).unwrap();
+        }
+        for account in ["alice", "bob"] {
+            let jar = load_jar(account).unwrap();
+            let store = jar.lock().unwrap();
+            assert_eq!(store.get("www.pinterest.com", "/", "_pinterest_sess").unwrap().value(), account);
+        }
+
+        // Directories that aren't valid account names aren't listed
+        std::fs::create_dir_all(data_dir.join("accounts/not valid")).unwrap();
+        std::fs::write(data_dir.join("accounts/stray"), "").unwrap();
+        assert_eq!(list(), ["default", "alice", "bob"]);
+    }
+}
@@ -0,0 +1,284 @@
+//! Content that needs a logged-in session.
+//!
+//! With cookies of a logged-in Pinterest session (see `cookies import`), the
+//! downloader can fetch what Pinterest only shows to that user: their saved pins
+//! (`me/pins`), every board they own or collaborate on, secret and group boards
+//! included (`me/boards`), and secret boards by URL (see
+//! [`crate::resources::fetch_board_info`]). Everything is read from Pinterest's internal
+//! JSON resources, page by page.
+
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use tracing::{debug, info};
+use url::Url;
+
+use crate::error::{Error, Result};
+use crate::request::ResourceRequest;
+use crate::resolve::is_pinterest_host;
+use crate::resources::{collect_media, fetch_board_pins, fetch_feed, fetch_user_boards, resource_id, PinMedia};
+use crate::session::Session;
+
+/// A download source that needs a logged-in session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthSource {
+    /// All pins saved by the logged-in user
+    MyPins,
+    /// Pins of every board of the logged-in user, including secret and group boards
+    MyBoards,
+}
+
+impl AuthSource {
+    /// Recognizes `me/pins` and `me/boards`, also as a Pinterest URL such as
+    /// `https://www.pinterest.com/me/pins/`
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let path = match Url::parse(input) {
+            Ok(url) if url.host_str().is_some_and(is_pinterest_host) => url.path().to_string(),
+            Ok(_) => return None,
+            Err(_) => input.to_string(),
+        };
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        match segments.as_slice() {
+            ["me", "pins"] => Some(AuthSource::MyPins),
+            ["me", "boards"] => Some(AuthSource::MyBoards),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for AuthSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthSource::MyPins => write!(f, "your saved pins"),
+            AuthSource::MyBoards => write!(f, "your boards"),
+        }
+    }
+}
+
+/// Asks Pinterest who the session is logged in as; `None` for anonymous sessions
+pub async fn logged_in_user(session: &Session) -> Result<Option<String>> {
+    if !session.has_session_cookie() {
+        return Ok(None);
+    }
+    let request = ResourceRequest::new("UserSettingsResource").source_url("/settings/");
+    let (status, body) = session.fetch_resource(&request).await?;
+    if !status.is_success() {
+        debug!(status = %status, "Session is not logged in");
+        return Ok(None);
+    }
+    let body: Value = serde_json::from_str(&body)?;
+    let username = body
+        .pointer("/resource_response/data/username")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    Ok(username)
+}
+
+/// Collects the media of an authenticated source, stopping after `limit`
+/// files (0 = no limit)
+pub async fn fetch_images(source: &AuthSource, session: &Session, limit: usize) -> Result<Vec<PinMedia>> {
+    let user = logged_in_user(session).await?;
+    if let Some(user) = &user {
+        info!(user = %user, "Logged in to Pinterest as {}", user);
+    }
+
+    match source {
+        AuthSource::MyPins => {
+            let user = require_login(user)?;
+            let request = ResourceRequest::new("UserPinsResource")
+                .source_url(format!("/{}/pins/", user))
+                .options(json!({ "username": user, "field_set_key": "grid_item" }));
+            let pins = fetch_feed(session, request, limit).await?;
+            Ok(collect_media(&pins, limit))
+        }
+        AuthSource::MyBoards => {
+            let user = require_login(user)?;
+            let boards = fetch_user_boards(session, &user).await?;
+            info!(count = boards.len(), "Found {} boards", boards.len());
+
+            let mut images = Vec::new();
+            let mut seen = HashSet::new();
+            for board in &boards {
+                if limit > 0 && images.len() >= limit {
+                    break;
+                }
+                let (Some(id), Some(url)) = (resource_id(board), board["url"].as_str()) else { continue };
+                debug!(board = %url, privacy = board["privacy"].as_str().unwrap_or("public"), "Fetching board");
+                let remaining = if limit > 0 { limit - images.len() } else { 0 };
+                let pins = fetch_board_pins(session, &id, url, remaining).await?;
+                images.extend(collect_media(&pins, 0).into_iter().filter(|media| seen.insert(media.url.clone())));
+            }
+            images.truncate(if limit > 0 { limit } else { images.len() });
+            Ok(images)
+        }
+    }
+}
+
+fn require_login(user: Option<String>) -> Result<String> {
+    user.ok_or_else(|| {
+        Error::LoginRequired(
+            "This source needs a logged-in Pinterest session. \
+             Import your browser's Pinterest cookies with `cookies import` first."
+                .to_string(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::fetch_board_info;
+    use axum::extract::Query;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::get;
+    use crate::test_support;
+    use axum::{Json, Router};
+    use std::collections::HashMap;
+
+    const SESSION: &str = "_pinterest_sess=secret-session";
+
+    fn logged_in(headers: &HeaderMap) -> bool {
+        headers
+            .get_all("cookie")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| value.split("; ").any(|cookie| cookie == SESSION))
+    }
+
+    fn options(params: &HashMap<String, String>) -> Value {
+        let data: Value = serde_json::from_str(&params["data"]).unwrap();
+        data["options"].clone()
+    }
+
+    fn pin(id: u32) -> Value {
+        json!({ "id": id.to_string(), "images": {
+            "236x": { "width": 236, "url": format!("https://i.pinimg.com/236x/aa/bb/{}.jpg", id) },
+            "orig": { "width": 1200, "url": format!("https://i.pinimg.com/originals/aa/bb/{}.jpg", id) },
+        }})
+    }
+
+    /// A Pinterest stand-in that only answers logged-in requests
+    async fn mock_pinterest() -> Url {
+        async fn settings(headers: HeaderMap) -> Result<Json<Value>, StatusCode> {
+            if !logged_in(&headers) {
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+            Ok(Json(json!({ "resource_response": { "data": { "username": "alice" } } })))
+        }
+        async fn user_pins(headers: HeaderMap, Query(params): Query<HashMap<String, String>>) -> Result<Json<Value>, StatusCode> {
+            if !logged_in(&headers) {
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+            // Two pages, linked by a bookmark
+            let page = if options(&params)["bookmarks"][0] == "page2" {
+                json!({ "data": [pin(3), { "type": "story" }], "bookmark": "-end-" })
+            } else {
+                json!({ "data": [pin(1), pin(2)], "bookmark": "page2" })
+            };
+            Ok(Json(json!({ "resource_response": page })))
+        }
+        async fn boards(headers: HeaderMap) -> Result<Json<Value>, StatusCode> {
+            if !logged_in(&headers) {
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+            Ok(Json(json!({ "resource_response": { "data": [
+                { "id": "10", "url": "/alice/public/", "privacy": "public" },
+                { "id": "11", "url": "/alice/hidden/", "privacy": "secret" },
+            ]}})))
+        }
+        async fn board(headers: HeaderMap) -> Result<Json<Value>, StatusCode> {
+            if !logged_in(&headers) {
+                return Err(StatusCode::NOT_FOUND);
+            }
+            Ok(Json(json!({ "resource_response": { "data": { "id": 11, "url": "/alice/hidden/", "privacy": "secret" } } })))
+        }
+        async fn board_feed(headers: HeaderMap, Query(params): Query<HashMap<String, String>>) -> Result<Json<Value>, StatusCode> {
+            if !logged_in(&headers) {
+                return Err(StatusCode::NOT_FOUND);
+            }
+            let pins = match options(&params)["board_id"].as_str() {
+                Some("10") => vec![pin(1), pin(4)],
+                Some("11") => vec![pin(5)],
+                _ => vec![],
+            };
+            Ok(Json(json!({ "resource_response": { "data": pins } })))
+        }
+
+        let app = Router::new()
+            .route("/resource/UserSettingsResource/get/", get(settings))
+            .route("/resource/UserPinsResource/get/", get(user_pins))
+            .route("/resource/BoardsResource/get/", get(boards))
+            .route("/resource/BoardResource/get/", get(board))
+            .route("/resource/BoardFeedResource/get/", get(board_feed));
+        test_support::serve(app).await
+    }
+
+    fn session(base_url: &Url, cookie: Option<&str>) -> Session {
+        test_support::session(base_url, cookie.as_slice())
+    }
+
+    fn originals(ids: &[u32]) -> Vec<String> {
+        ids.iter().map(|id| format!("https://i.pinimg.com/originals/aa/bb/{}.jpg", id)).collect()
+    }
+
+    fn urls(media: Vec<PinMedia>) -> Vec<String> {
+        media.into_iter().map(|media| media.url).collect()
+    }
+
+    #[test]
+    fn parses_sources() {
+        assert_eq!(AuthSource::parse("me/pins"), Some(AuthSource::MyPins));
+        assert_eq!(AuthSource::parse("me/boards/"), Some(AuthSource::MyBoards));
+        assert_eq!(AuthSource::parse("https://www.pinterest.com/me/pins/"), Some(AuthSource::MyPins));
+        assert_eq!(AuthSource::parse("https://de.pinterest.com/me/boards/"), Some(AuthSource::MyBoards));
+        assert_eq!(AuthSource::parse("https://www.pinterest.com/alice/hidden/"), None);
+        assert_eq!(AuthSource::parse("https://www.pinterest.com/search/pins/"), None);
+        assert_eq!(AuthSource::parse("https://www.pinterest.com/pin/123/"), None);
+        assert_eq!(AuthSource::parse("https://example.com/alice/hidden/"), None);
+        assert_eq!(AuthSource::parse("alice/hidden"), None);
+    }
+
+    #[tokio::test]
+    async fn detects_logged_in_user_from_session_cookie() {
+        let base_url = mock_pinterest().await;
+        assert_eq!(logged_in_user(&session(&base_url, Some(SESSION))).await.unwrap().as_deref(), Some("alice"));
+        assert_eq!(logged_in_user(&session(&base_url, Some("_pinterest_sess=stale"))).await.unwrap(), None);
+        assert_eq!(logged_in_user(&session(&base_url, None)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn fetches_own_pins_across_pages() {
+        let base_url = mock_pinterest().await;
+        let images = fetch_images(&AuthSource::MyPins, &session(&base_url, Some(SESSION)), 0).await.unwrap();
+        assert_eq!(urls(images), originals(&[1, 2, 3]));
+
+        let limited = fetch_images(&AuthSource::MyPins, &session(&base_url, Some(SESSION)), 2).await.unwrap();
+        assert_eq!(urls(limited), originals(&[1, 2]));
+    }
+
+    #[tokio::test]
+    async fn fetches_all_boards_including_secret_ones() {
+        let base_url = mock_pinterest().await;
+        let images = fetch_images(&AuthSource::MyBoards, &session(&base_url, Some(SESSION)), 0).await.unwrap();
+        assert_eq!(urls(images), originals(&[1, 4, 5]));
+    }
+
+    #[tokio::test]
+    async fn fetches_secret_board_by_url_only_when_logged_in() {
+        let base_url = mock_pinterest().await;
+        let session_in = session(&base_url, Some(SESSION));
+        let (id, board) = fetch_board_info(&session_in, "alice", "hidden").await.unwrap();
+        assert_eq!(board["privacy"], "secret");
+        let pins = fetch_board_pins(&session_in, &id, "/alice/hidden/", 0).await.unwrap();
+        assert_eq!(urls(collect_media(&pins, 0)), originals(&[5]));
+
+        assert!(fetch_board_info(&session(&base_url, None), "alice", "hidden").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn own_pins_need_a_login() {
+        let base_url = mock_pinterest().await;
+        let error = fetch_images(&AuthSource::MyPins, &session(&base_url, None), 0).await.unwrap_err();
+        assert!(error.to_string().contains("logged-in"));
+    }
+}
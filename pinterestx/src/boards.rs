@@ -0,0 +1,239 @@
+//! Boards, their sections, and user profiles made of boards.
+//!
+//! A board is read from Pinterest's board resources, and with `--sections` its
+//! sections too, flat or each into its own folder. A profile is downloaded board by
+//! board, narrowed down by name with [`BoardFilters`].
+
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info};
+use url::Url;
+
+use crate::download::DownloadOptions;
+use crate::error::{Error, Result};
+use crate::extract::PinCandidate;
+use crate::resolve;
+use crate::resources::{self, BoardSection};
+use crate::session::Session;
+use crate::source::PinterestSource;
+
+/// Which of a user's boards to download, for user profile URLs
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct BoardFilters {
+    /// Only download boards whose name matches one of these patterns (`*` matches anything)
+    #[cfg_attr(feature = "clap", arg(long = "include-board", value_name = "PATTERN"))]
+    pub include: Vec<String>,
+
+    /// Skip boards whose name matches one of these patterns (`*` matches anything)
+    #[cfg_attr(feature = "clap", arg(long = "exclude-board", value_name = "PATTERN"))]
+    pub exclude: Vec<String>,
+
+    /// Maximum number of images to download per board (0 = unlimited)
+    #[cfg_attr(feature = "clap", arg(long, default_value = "0"))]
+    pub per_board_max: usize,
+}
+
+impl BoardFilters {
+    /// Whether a board, by name or slug, passes the include and exclude patterns
+    pub fn matches(&self, name: &str, slug: &str) -> bool {
+        let matches_any = |patterns: &[String]| {
+            patterns.iter().any(|pattern| glob_match(pattern, name) || glob_match(pattern, slug))
+        };
+        (self.include.is_empty() || matches_any(&self.include)) && !matches_any(&self.exclude)
+    }
+}
+
+/// Case-insensitive match of `text` against a pattern in which `*` matches any run of characters
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// How a board download treats the board's sections
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SectionsMode {
+    /// Also download the pins of every section, into the same folder
+    Flat,
+    /// Also download every section, each into its own subfolder
+    Folders,
+    /// Only list the board's sections, without downloading anything
+    List,
+}
+
+/// Reads a board from Pinterest's board resources, which also covers secret boards
+/// when logged in
+pub fn stream_board<'a>(user: &'a str, slug: &'a str, session: &'a Session, options: &'a DownloadOptions) -> BoxStream<'a, Result<PinCandidate>> {
+    stream::once(resources::fetch_board_info(session, user, slug))
+        .map_ok(move |(board_id, _)| {
+            let board_url = format!("/{}/{}/", user, slug);
+            stream_board_images(session, &board_id, &board_url, None, options.sections, options.max_images)
+        })
+        .try_flatten()
+        .boxed()
+}
+
+/// Reads a user profile board by board, each board into its own folder
+pub fn stream_profile<'a>(user: &'a str, session: &'a Session, options: &'a DownloadOptions) -> BoxStream<'a, Result<PinCandidate>> {
+    // A pin saved to several boards is only downloaded once. It is skipped before the
+    // per-board limit applies, so later boards still give that many new images.
+    let seen = Arc::new(Mutex::new(HashSet::new()));
+    let images = stream::once(resources::fetch_user_boards(session, user))
+        .map_ok(move |boards| {
+            info!(count = boards.len(), "Found {} boards of {}", boards.len(), user);
+            let seen = seen.clone();
+            stream::iter(boards)
+                .filter_map(move |board| {
+                    let (Some(board_id), Some(board_url)) = (resources::resource_id(&board), board["url"].as_str()) else {
+                        return future::ready(None);
+                    };
+                    let slug = board_url.trim_matches('/').rsplit('/').next().unwrap_or_default();
+                    let name = board["name"].as_str().unwrap_or(slug);
+                    if !options.boards.matches(name, slug) {
+                        debug!(board = %board_url, "Skipping board {}", name);
+                        return future::ready(None);
+                    }
+                    info!(board = %board_url, "Fetching board {}", name);
+                    let folder = folder_name(slug);
+                    let seen = seen.clone();
+                    let new_images = stream_board_images(session, &board_id, board_url, Some(&folder), options.sections, 0)
+                        .try_filter(move |image| future::ready(seen.lock().unwrap_or_else(|e| e.into_inner()).insert(image.url.clone())));
+                    future::ready(Some(resources::limited(new_images.boxed(), options.boards.per_board_max)))
+                })
+                .flatten()
+        })
+        .try_flatten();
+    resources::limited(images.boxed(), options.max_images)
+}
+
+/// Streams the images of a board into `folder`, and with `--sections flat` or
+/// `--sections folders` those of its sections after them, up to `limit` images
+/// (0 = no limit). Each page is fetched as the stream gets to it, so nothing past
+/// the limit is.
+pub fn stream_board_images<'a>(
+    session: &'a Session,
+    board_id: &str,
+    board_url: &str,
+    folder: Option<&str>,
+    sections: Option<SectionsMode>,
+    limit: usize,
+) -> BoxStream<'a, Result<PinCandidate>> {
+    let board_folder = folder.map(str::to_string);
+    let pins = resources::board_media(session, board_id, board_url)
+        .map_ok(move |media| PinCandidate { folder: board_folder.clone(), ..media.into() });
+    if !matches!(sections, Some(SectionsMode::Flat | SectionsMode::Folders)) {
+        return resources::limited(pins.boxed(), limit);
+    }
+
+    let (board_id, board_url, folder) = (board_id.to_string(), board_url.to_string(), folder.map(str::to_string));
+    let sections = stream::once(async move { resources::fetch_sections(session, &board_id, &board_url).await.map(|found| (found, board_url)) })
+        .map_ok(move |(found, board_url)| {
+            let folder = folder.clone();
+            stream::iter(found).flat_map(move |section| {
+                let section_url = format!("{}{}/", board_url, section.slug);
+                let section_folder = match (&folder, sections) {
+                    (Some(folder), Some(SectionsMode::Folders)) => Some(format!("{}/{}", folder, folder_name(&section.slug))),
+                    (None, Some(SectionsMode::Folders)) => Some(folder_name(&section.slug)),
+                    (folder, _) => folder.clone(),
+                };
+                debug!(section = %section.slug, "Fetching section");
+                resources::section_media(session, &section, &section_url)
+                    .map_ok(move |media| PinCandidate { folder: section_folder.clone(), ..media.into() })
+            })
+        })
+        .try_flatten();
+    resources::limited(pins.chain(sections).boxed(), limit)
+}
+
+/// Reads a section of a board from Pinterest's section resources
+pub fn stream_section_images<'a>(
+    url: &'a Url,
+    user: &'a str,
+    board: &'a str,
+    slug: &'a str,
+    session: &'a Session,
+    limit: usize,
+) -> BoxStream<'a, Result<PinCandidate>> {
+    let images = stream::once(resources::find_section(session, user, board, slug))
+        .map_ok(move |section| resources::section_media(session, &section, url.path()).map_ok(PinCandidate::from))
+        .try_flatten();
+    resources::limited(images.boxed(), limit)
+}
+
+/// Makes a board or section slug safe to use as a folder name
+pub fn folder_name(slug: &str) -> String {
+    let name: String = slug
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if name.is_empty() { "section".to_string() } else { name }
+}
+
+/// The sections of the board a URL points to (for `--sections list`), with the
+/// user and slug of the board
+pub async fn list_sections(input: &str, session: &Session) -> Result<(String, String, Vec<BoardSection>)> {
+    let url = resolve::resolve(input, session).await?;
+    let (user, board) = match PinterestSource::parse(&url) {
+        Some(PinterestSource::Board { user, slug }) => (user, slug),
+        Some(PinterestSource::Section { user, board, .. }) => (user, board),
+        _ => return Err(Error::InvalidInput(format!("--sections list needs a board URL, got {}", url))),
+    };
+
+    let board_url = format!("/{}/{}/", user, board);
+    let (board_id, _) = resources::fetch_board_info(session, &user, &board).await?;
+    let sections = resources::fetch_sections(session, &board_id, &board_url).await?;
+    Ok((user, board, sections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_boards_by_name_or_slug() {
+        let filters = BoardFilters {
+            include: vec!["*cats*".into(), "Dogs".into()],
+            exclude: vec!["*kittens".into()],
+            per_board_max: 0,
+        };
+        assert!(filters.matches("Big Cats", "big-cats"));
+        assert!(filters.matches("dogs", "my-dogs"));
+        assert!(filters.matches("Puppies", "dogs"));
+        assert!(!filters.matches("Cats and kittens", "cats-and-kittens"));
+        assert!(!filters.matches("Birds", "birds"));
+        assert!(BoardFilters::default().matches("Anything", "anything"));
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(glob_match("A*", "abc"));
+        assert!(!glob_match("a*b", "acb c"));
+        assert!(!glob_match("abc", "abcd"));
+    }
+}
@@ -0,0 +1,40 @@
+//! User configuration, read from `config.json` in the application data directory.
+//!
+//! Every key is optional; a missing file is the same as an empty one.
+
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::consent::ConsentChoice;
+use crate::error::{Error, Result};
+use crate::paths::get_app_data_dir;
+
+/// Settings from `config.json`
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Answer to the cookie consent prompt, for unattended use: "accept" or "decline"
+    #[serde(default)]
+    pub cookie_consent: Option<ConsentChoice>,
+
+    /// Extraction strategies to try first, in this order; the others are tried after them
+    #[serde(default)]
+    pub strategy_order: Vec<String>,
+}
+
+/// Gets the path of the configuration file
+pub fn get_config_path() -> PathBuf {
+    get_app_data_dir().join("config.json")
+}
+
+impl Config {
+    /// Loads the configuration file, if there is one
+    pub fn load() -> Result<Self> {
+        let path = get_config_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&std::fs::read_to_string(&path)?)
+            .map_err(|e| Error::Config { what: "configuration", path: path.clone(), message: e.to_string() })
+    }
+}
@@ -0,0 +1,171 @@
+//! Cookie consent.
+//!
+//! Cookies are only stored once the user has agreed to it, per account. Consent is
+//! recorded with [`grant_consent`], or given up front for unattended runs, in order
+//! of precedence: the `--accept-cookies` / `--no-cookies` flags, the
+//! `PINTERESTX_COOKIE_CONSENT` environment variable, or the `cookie_consent` key of
+//! `config.json` (see [`resolve_choice`]). Without consent, sessions don't read or
+//! store any cookies.
+
+use serde::Deserialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+use tracing::info;
+
+use crate::accounts;
+use crate::config::{get_config_path, Config};
+use crate::error::{Error, Result};
+use crate::paths::create_private_file;
+
+/// Environment variable answering the consent prompt ("accept" or "decline")
+pub const CONSENT_ENV: &str = "PINTERESTX_COOKIE_CONSENT";
+
+/// Set when cookies were declined for this run, whatever consent is stored
+static COOKIES_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// An answer to the consent prompt given ahead of time
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsentChoice {
+    Accept,
+    Decline,
+}
+
+impl std::str::FromStr for ConsentChoice {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "accept" | "yes" | "true" | "1" => Ok(ConsentChoice::Accept),
+            "decline" | "no" | "false" | "0" => Ok(ConsentChoice::Decline),
+            other => Err(Error::InvalidInput(format!("Invalid consent value '{}': expected 'accept' or 'decline'", other))),
+        }
+    }
+}
+
+/// Where a consent choice came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChoiceSource {
+    Flag,
+    Env,
+    Config,
+}
+
+impl ChoiceSource {
+    pub fn describe(self) -> String {
+        match self {
+            ChoiceSource::Flag => "command line flag".to_string(),
+            ChoiceSource::Env => format!("{} environment variable", CONSENT_ENV),
+            ChoiceSource::Config => format!("cookie_consent in {}", get_config_path().display()),
+        }
+    }
+}
+
+/// Finds the consent choice given ahead of time, if any, by order of precedence
+pub fn resolve_choice(flag: Option<ConsentChoice>) -> Result<Option<(ConsentChoice, ChoiceSource)>> {
+    let env = std::env::var(CONSENT_ENV).ok();
+    choose(flag, env.as_deref(), || Ok(Config::load()?.cookie_consent))
+}
+
+/// Picks the first choice given, from the flag, the environment variable's value or
+/// the configuration (only loaded when it's needed). An invalid environment value is
+/// an error rather than being skipped.
+fn choose(
+    flag: Option<ConsentChoice>,
+    env: Option<&str>,
+    config: impl FnOnce() -> Result<Option<ConsentChoice>>,
+) -> Result<Option<(ConsentChoice, ChoiceSource)>> {
+    if let Some(choice) = flag {
+        return Ok(Some((choice, ChoiceSource::Flag)));
+    }
+    if let Some(value) = env.filter(|value| !value.trim().is_empty()) {
+        return Ok(Some((value.parse()?, ChoiceSource::Env)));
+    }
+    Ok(config()?.map(|choice| (choice, ChoiceSource::Config)))
+}
+
+/// Turns cookies off for the rest of the run, whatever consent is stored
+pub fn disable_cookies() {
+    COOKIES_DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Checks if the user has previously given cookie consent for an account
+/// (and hasn't turned cookies off for this run)
+pub fn has_cookie_consent(account: &str) -> bool {
+    if COOKIES_DISABLED.load(Ordering::Relaxed) {
+        return false;
+    }
+    std::fs::metadata(get_cookie_consent_path(account)).is_ok()
+}
+
+/// Gets the path to an account's cookie consent file
+pub fn get_cookie_consent_path(account: &str) -> PathBuf {
+    let mut path = accounts::account_dir(account);
+    path.push("cookie_consent");
+    path
+}
+
+/// Records consent for an account
+pub fn grant_consent(account: &str) -> Result<()> {
+    let consent_path = get_cookie_consent_path(account);
+    let mut file = create_private_file(&consent_path)?;
+    let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    writeln!(file, "Cookie consent granted at: {}", timestamp)?;
+    info!("Created cookie consent file at {:?}", consent_path);
+    Ok(())
+}
+
+/// Withdraws consent for an account
+pub fn revoke_consent(account: &str) -> Result<()> {
+    let consent_path = get_cookie_consent_path(account);
+    if consent_path.exists() {
+        std::fs::remove_file(&consent_path)?;
+    }
+    Ok(())
+}
+
+/// When consent was given for an account, as seconds since the Unix epoch
+pub fn consent_timestamp(account: &str) -> Option<u64> {
+    let content = std::fs::read_to_string(get_cookie_consent_path(account)).ok()?;
+    content.trim().rsplit(' ').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ConsentChoice::{Accept, Decline};
+
+    fn config(choice: Option<ConsentChoice>) -> impl FnOnce() -> Result<Option<ConsentChoice>> {
+        move || Ok(choice)
+    }
+
+    fn unreadable_config() -> Result<Option<ConsentChoice>> {
+        Err(Error::InvalidInput("the configuration shouldn't be read".to_string()))
+    }
+
+    #[test]
+    fn takes_the_flag_then_the_environment_then_the_config() {
+        assert_eq!(choose(Some(Decline), Some("accept"), unreadable_config).unwrap(), Some((Decline, ChoiceSource::Flag)));
+        assert_eq!(choose(None, Some("decline"), unreadable_config).unwrap(), Some((Decline, ChoiceSource::Env)));
+        assert_eq!(choose(None, None, config(Some(Accept))).unwrap(), Some((Accept, ChoiceSource::Config)));
+        // A blank variable counts as unset
+        assert_eq!(choose(None, Some("  "), config(Some(Decline))).unwrap(), Some((Decline, ChoiceSource::Config)));
+        assert_eq!(choose(None, None, config(None)).unwrap(), None);
+        assert!(choose(None, None, unreadable_config).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_environment_values() {
+        for value in ["Accept", " yes ", "TRUE", "1"] {
+            assert_eq!(choose(None, Some(value), config(None)).unwrap(), Some((Accept, ChoiceSource::Env)), "{}", value);
+        }
+        for value in ["decline", "No", "false", "0"] {
+            assert_eq!(choose(None, Some(value), config(None)).unwrap(), Some((Decline, ChoiceSource::Env)), "{}", value);
+        }
+        // Not silently replaced by the config file's choice
+        let error = choose(None, Some("maybe"), config(Some(Accept))).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid consent value 'maybe': expected 'accept' or 'decline'");
+    }
+}
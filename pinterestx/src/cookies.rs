@@ -0,0 +1,467 @@
+//! Persistent Pinterest cookie jar.
+//!
+//! Cookies are kept in a real cookie store with domain, path and expiry semantics,
+//! serialized as JSON and encrypted (see [`crate::encryption`]) in the application
+//! data directory, readable by the owner only. Besides the cookies
+//! Pinterest sets itself, a logged-in session can be imported from a browser with
+//! `cookies import`, either as a Netscape `cookies.txt` file or as the JSON export
+//! of a cookie editor extension.
+
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::accounts::{self, DEFAULT_ACCOUNT};
+use crate::consent::has_cookie_consent;
+use crate::encryption::{self, KeySource};
+use crate::error::{Error, Result};
+use crate::paths::{get_app_data_dir, write_private_file_atomically};
+use crate::resolve::is_pinterest_host;
+
+/// Cookie jar shared by the HTTP clients of a download run
+pub type CookieJar = Arc<CookieStoreMutex>;
+
+/// Name of the plaintext cookie file written by earlier versions
+const LEGACY_COOKIES_FILE: &str = "pinterest_cookies.json";
+
+/// Gets the path to store an account's cookies
+pub fn get_cookies_path(account: &str) -> PathBuf {
+    let mut path = accounts::account_dir(account);
+    path.push("pinterest_cookies.enc");
+    path
+}
+
+/// Gets the path of the plaintext cookie file written by earlier versions
+pub fn get_legacy_cookies_path() -> PathBuf {
+    get_app_data_dir().join(LEGACY_COOKIES_FILE)
+}
+
+/// Loads the stored cookies, or an empty jar if there are none or consent wasn't given.
+/// Fails if the cookies can't be decrypted, rather than silently replacing them.
+pub fn load_jar(account: &str) -> Result<CookieJar> {
+    if !has_cookie_consent(account) {
+        return Ok(Arc::new(CookieStoreMutex::new(CookieStore::default())));
+    }
+
+    let legacy_path = (account == DEFAULT_ACCOUNT).then(get_legacy_cookies_path);
+    let store = load_store(&get_cookies_path(account), legacy_path.as_deref(), encryption::key_source())?;
+    Ok(Arc::new(CookieStoreMutex::new(store)))
+}
+
+/// Reads the encrypted store at `path`, or moves the plaintext file written by earlier
+/// versions at `legacy_path` there, encrypting it
+fn load_store(path: &Path, legacy_path: Option<&Path>, key: &KeySource) -> Result<CookieStore> {
+    if path.exists() {
+        let plaintext = encryption::decrypt_with(key, &std::fs::read(path)?)?;
+        Ok(parse_store(&plaintext).unwrap_or_else(|e| {
+            warn!(error = %e, "Ignoring unreadable cookie store {}", path.display());
+            CookieStore::default()
+        }))
+    } else if let Some(legacy_path) = legacy_path.filter(|legacy_path| legacy_path.exists()) {
+        // Migrate the plaintext file to encrypted storage
+        let mut store = parse_store(&std::fs::read(legacy_path)?).unwrap_or_else(|e| {
+            warn!(error = %e, "Ignoring unreadable cookie file {}", legacy_path.display());
+            CookieStore::default()
+        });
+        let content = serialize_store(&mut store)?;
+        write_private_file_atomically(path, &encryption::encrypt_with(key, &content)?)?;
+        std::fs::remove_file(legacy_path)?;
+        info!("Moved stored cookies to encrypted storage");
+        Ok(store)
+    } else {
+        Ok(CookieStore::default())
+    }
+}
+
+fn parse_store(json: &[u8]) -> Result<CookieStore> {
+    cookie_store::serde::json::load_all(BufReader::new(json)).map_err(|e| Error::Cookies(e.to_string()))
+}
+
+/// Saves the jar to disk, encrypted, if the user has consented to storing cookies
+pub fn save_jar(jar: &CookieJar, account: &str) -> Result<()> {
+    if !has_cookie_consent(account) {
+        return Ok(());
+    }
+
+    let content = serialize_store(&mut jar.lock().unwrap_or_else(|e| e.into_inner()))?;
+    write_private_file_atomically(&get_cookies_path(account), &encryption::encrypt(&content)?)?;
+    debug!("Pinterest cookies saved");
+    Ok(())
+}
+
+/// Prunes expired cookies and serializes the rest as JSON
+fn serialize_store(store: &mut CookieStore) -> Result<Vec<u8>> {
+    prune_expired(store);
+    let mut content = Vec::new();
+    // Session cookies are kept too: a logged-in session imported from a browser
+    // is often made of them, and it should outlive a single run
+    cookie_store::serde::json::save_incl_expired_and_nonpersistent(store, &mut content)
+        .map_err(|e| Error::Cookies(e.to_string()))?;
+    Ok(content)
+}
+
+/// Drops cookies that have expired since they were stored
+fn prune_expired(store: &mut CookieStore) {
+    let unexpired = store.iter_unexpired().cloned().map(Ok::<_, Infallible>);
+    let Ok(pruned) = CookieStore::from_cookies(unexpired, false);
+    *store = pruned;
+}
+
+/// A cookie read from a browser export
+#[derive(Debug, Clone, PartialEq)]
+struct ImportedCookie {
+    domain: String,
+    /// Whether the cookie is only sent to `domain` itself, not its subdomains
+    host_only: bool,
+    path: String,
+    secure: bool,
+    http_only: bool,
+    /// Expiry as seconds since the Unix epoch; `None` for session cookies
+    expires: Option<f64>,
+    name: String,
+    value: String,
+}
+
+/// Outcome of `cookies import`
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub expired: usize,
+    /// Cookies for sites other than Pinterest, which aren't stored
+    pub other_sites: usize,
+}
+
+/// Imports the Pinterest cookies from a Netscape `cookies.txt` file or a JSON
+/// browser export into an account's stored jar
+pub fn import_cookies(file: &Path, account: &str) -> Result<ImportReport> {
+    let content = std::fs::read_to_string(file)
+        .map_err(|e| Error::Cookies(format!("Failed to read {}: {}", file.display(), e)))?;
+    let cookies = parse_cookie_file(&content)?;
+
+    let jar = load_jar(account)?;
+    let mut report = ImportReport::default();
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0);
+    {
+        let mut store = jar.lock().unwrap_or_else(|e| e.into_inner());
+        for cookie in cookies {
+            if !should_import(&cookie, now, &mut report) {
+                continue;
+            }
+
+            let (set_cookie, url) = to_set_cookie(&cookie, now)?;
+            match store.parse(&set_cookie, &url) {
+                Ok(_) => report.imported += 1,
+                Err(e) => warn!(error = %e, "Skipping cookie {} for {}", cookie.name, cookie.domain),
+            }
+        }
+    }
+
+    save_jar(&jar, account)?;
+    Ok(report)
+}
+
+/// Whether an imported cookie is kept: it must be Pinterest's and not have expired.
+/// Cookies that aren't kept are counted in `report`.
+fn should_import(cookie: &ImportedCookie, now: f64, report: &mut ImportReport) -> bool {
+    if !is_pinterest_domain(&cookie.domain) {
+        report.other_sites += 1;
+        false
+    } else if cookie.expires.is_some_and(|expires| expires <= now) {
+        report.expired += 1;
+        false
+    } else {
+        true
+    }
+}
+
+/// Whether a cookie domain is one of Pinterest's sites, and not a lookalike such as
+/// `pinterest.example.com`
+fn is_pinterest_domain(domain: &str) -> bool {
+    let host = domain.trim_start_matches('.');
+    is_pinterest_host(host) || host.eq_ignore_ascii_case("pin.it")
+}
+
+/// Turns an imported cookie into a `Set-Cookie` value and the URL it would have come from
+fn to_set_cookie(cookie: &ImportedCookie, now: f64) -> Result<(String, Url)> {
+    let host = cookie.domain.trim_start_matches('.');
+    let url = Url::parse(&format!("https://{}{}", host, cookie.path))?;
+
+    let mut set_cookie = format!("{}={}; Path={}", cookie.name, cookie.value, cookie.path);
+    if !cookie.host_only {
+        set_cookie.push_str(&format!("; Domain={}", host));
+    }
+    if let Some(expires) = cookie.expires {
+        set_cookie.push_str(&format!("; Max-Age={}", (expires - now).ceil() as i64));
+    }
+    if cookie.secure {
+        set_cookie.push_str("; Secure");
+    }
+    if cookie.http_only {
+        set_cookie.push_str("; HttpOnly");
+    }
+    Ok((set_cookie, url))
+}
+
+/// Detects the export format and parses it
+fn parse_cookie_file(content: &str) -> Result<Vec<ImportedCookie>> {
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        parse_json_export(trimmed)
+    } else {
+        parse_netscape(content)
+    }
+}
+
+/// Parses a Netscape `cookies.txt` file: one tab-separated cookie per line with
+/// domain, include-subdomains flag, path, secure flag, expiry, name and value
+fn parse_netscape(content: &str) -> Result<Vec<ImportedCookie>> {
+    let mut cookies = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        // curl and browser extensions mark HttpOnly cookies with this prefix
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            return Err(Error::Cookies(format!(
+                "Invalid cookies.txt line {}: expected 7 tab-separated fields, found {}",
+                number + 1,
+                fields.len()
+            )));
+        }
+        let expires: i64 = fields[4]
+            .trim()
+            .parse()
+            .map_err(|_| Error::Cookies(format!("Invalid expiry on cookies.txt line {}: {}", number + 1, fields[4])))?;
+
+        cookies.push(ImportedCookie {
+            domain: fields[0].to_string(),
+            host_only: !fields[1].eq_ignore_ascii_case("TRUE"),
+            path: fields[2].to_string(),
+            secure: fields[3].eq_ignore_ascii_case("TRUE"),
+            http_only,
+            // Session cookies are written with an expiry of 0
+            expires: (expires > 0).then_some(expires as f64),
+            name: fields[5].to_string(),
+            value: fields[6].trim_end_matches('\r').to_string(),
+        });
+    }
+    Ok(cookies)
+}
+
+/// A cookie as exported by browser extensions such as Cookie-Editor or EditThisCookie
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct JsonCookie {
+    name: String,
+    value: String,
+    domain: String,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default)]
+    host_only: Option<bool>,
+    #[serde(default)]
+    secure: bool,
+    #[serde(default)]
+    http_only: bool,
+    #[serde(default)]
+    session: bool,
+    #[serde(default, alias = "expires", alias = "expiry")]
+    expiration_date: Option<f64>,
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+/// Some exporters wrap the cookie list in an object
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonExport {
+    List(Vec<JsonCookie>),
+    Wrapped { cookies: Vec<JsonCookie> },
+}
+
+fn parse_json_export(content: &str) -> Result<Vec<ImportedCookie>> {
+    let export: JsonExport = serde_json::from_str(content)
+        .map_err(|e| Error::Cookies(format!("Unrecognized JSON cookie export: {}", e)))?;
+    let cookies = match export {
+        JsonExport::List(cookies) | JsonExport::Wrapped { cookies } => cookies,
+    };
+
+    Ok(cookies
+        .into_iter()
+        .map(|c| ImportedCookie {
+            // Without an explicit flag, a leading dot marks a domain cookie
+            host_only: c.host_only.unwrap_or(!c.domain.starts_with('.')),
+            domain: c.domain,
+            path: c.path,
+            secure: c.secure,
+            http_only: c.http_only,
+            // Puppeteer-style exports use -1 for session cookies
+            expires: c.expiration_date.filter(|&e| !c.session && e > 0.0),
+            name: c.name,
+            value: c.value,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_a_legacy_plaintext_jar() {
+        let dir = std::env::temp_dir().join(format!("pinterestx-cookies-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path, legacy_path) = (dir.join("pinterest_cookies.enc"), dir.join(LEGACY_COOKIES_FILE));
+        let key = KeySource::KeyFile(dir.join("cookies.key"));
+
+        let mut store = CookieStore::default();
+        store.parse("_pinterest_sess=secret; Max-Age=3600", &Url::parse("https://www.pinterest.com/").unwrap()).unwrap();
+        std::fs::write(&legacy_path, serialize_store(&mut store).unwrap()).unwrap();
+
+        let migrated = load_store(&path, Some(&legacy_path), &key).unwrap();
+        assert_eq!(migrated.get("www.pinterest.com", "/", "_pinterest_sess").unwrap().value(), "secret");
+        assert!(!legacy_path.exists());
+        assert!(!String::from_utf8_lossy(&std::fs::read(&path).unwrap()).contains("secret"));
+
+        // From then on the encrypted store is read, with or without the plaintext file
+        let reloaded = load_store(&path, None, &key).unwrap();
+        assert_eq!(reloaded.get("www.pinterest.com", "/", "_pinterest_sess").unwrap().value(), "secret");
+        let error = load_store(&path, None, &KeySource::Passphrase("guess".to_string())).unwrap_err();
+        assert!(matches!(error, Error::Cookies(_)), "{:?}", error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn accepts_only_pinterest_domains() {
+        for domain in [".pinterest.com", "www.pinterest.com", "pinterest.co.uk", ".de.pinterest.com", "pin.it"] {
+            assert!(is_pinterest_domain(domain), "{}", domain);
+        }
+        for domain in ["pinterest.evil.com", "login.pinterest.attacker.net", ".example.com", "notpinterest.com"] {
+            assert!(!is_pinterest_domain(domain), "{}", domain);
+        }
+    }
+
+    #[test]
+    fn parses_netscape_cookie_files() {
+        let content = "# Netscape HTTP Cookie File\n\
+            # https://curl.se/docs/http-cookies.html\n\
+            \n\
+            .pinterest.com\tTRUE\t/\tTRUE\t1900000000\tcsrftoken\tabc\r\n\
+            #HttpOnly_.pinterest.com\tTRUE\t/\tTRUE\t0\t_pinterest_sess\tsecret\n\
+            www.pinterest.com\tFALSE\t/ideas/\tFALSE\t1000\told\tgone\n";
+        let cookies = parse_cookie_file(content).unwrap();
+        assert_eq!(cookies, [
+            ImportedCookie {
+                domain: ".pinterest.com".to_string(),
+                host_only: false,
+                path: "/".to_string(),
+                secure: true,
+                http_only: false,
+                expires: Some(1_900_000_000.0),
+                name: "csrftoken".to_string(),
+                value: "abc".to_string(),
+            },
+            ImportedCookie {
+                domain: ".pinterest.com".to_string(),
+                host_only: false,
+                path: "/".to_string(),
+                secure: true,
+                http_only: true,
+                expires: None,
+                name: "_pinterest_sess".to_string(),
+                value: "secret".to_string(),
+            },
+            ImportedCookie {
+                domain: "www.pinterest.com".to_string(),
+                host_only: true,
+                path: "/ideas/".to_string(),
+                secure: false,
+                http_only: false,
+                expires: Some(1000.0),
+                name: "old".to_string(),
+                value: "gone".to_string(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn rejects_malformed_netscape_lines() {
+        let error = parse_cookie_file("# comment\n.pinterest.com\tTRUE\t/\n").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid cookies.txt line 2: expected 7 tab-separated fields, found 3");
+        let error = parse_cookie_file(".pinterest.com\tTRUE\t/\tTRUE\tsoon\ta\tb\n").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid expiry on cookies.txt line 1: soon");
+    }
+
+    #[test]
+    fn parses_json_exports_as_a_list_or_wrapped() {
+        let list = r#"[
+            { "name": "csrftoken", "value": "abc", "domain": ".pinterest.com", "secure": true, "expirationDate": 1900000000.5 },
+            { "name": "_pinterest_sess", "value": "secret", "domain": "www.pinterest.com", "path": "/", "httpOnly": true, "session": true, "expirationDate": 1900000000 }
+        ]"#;
+        let cookies = parse_cookie_file(list).unwrap();
+        assert_eq!(cookies.len(), 2);
+        assert!(!cookies[0].host_only);
+        assert_eq!(cookies[0].path, "/");
+        assert_eq!(cookies[0].expires, Some(1_900_000_000.5));
+        assert!(cookies[1].host_only);
+        assert!(cookies[1].http_only);
+        assert_eq!(cookies[1].expires, None);
+
+        // Puppeteer-style, wrapped in an object with -1 for session cookies
+        let wrapped = r#"{ "cookies": [{ "name": "a", "value": "b", "domain": ".pinterest.com", "hostOnly": true, "expires": -1 }] }"#;
+        let cookies = parse_cookie_file(wrapped).unwrap();
+        assert_eq!(cookies.len(), 1);
+        assert!(cookies[0].host_only);
+        assert_eq!(cookies[0].expires, None);
+    }
+
+    #[test]
+    fn rejects_malformed_json_exports() {
+        for content in [r#"{ "cookies": 3 }"#, r#"[{ "name": "a" }]"#, "[1, 2"] {
+            let error = parse_cookie_file(content).unwrap_err();
+            assert!(error.to_string().starts_with("Unrecognized JSON cookie export"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn skips_expired_and_foreign_cookies() {
+        let cookie = |domain: &str, expires: Option<f64>| ImportedCookie {
+            domain: domain.to_string(),
+            host_only: false,
+            path: "/".to_string(),
+            secure: true,
+            http_only: false,
+            expires,
+            name: "a".to_string(),
+            value: "b".to_string(),
+        };
+        let mut report = ImportReport::default();
+        assert!(should_import(&cookie(".pinterest.com", Some(2000.0)), 1000.0, &mut report));
+        assert!(should_import(&cookie(".pinterest.com", None), 1000.0, &mut report));
+        assert!(!should_import(&cookie(".pinterest.com", Some(1000.0)), 1000.0, &mut report));
+        assert!(!should_import(&cookie("pinterest.evil.com", Some(2000.0)), 1000.0, &mut report));
+        assert_eq!((report.expired, report.other_sites), (1, 1));
+
+        let (set_cookie, url) = to_set_cookie(&cookie(".pinterest.com", Some(2000.0)), 1000.0).unwrap();
+        assert_eq!(set_cookie, "a=b; Path=/; Domain=pinterest.com; Max-Age=1000; Secure");
+        assert_eq!(url.as_str(), "https://pinterest.com/");
+    }
+}
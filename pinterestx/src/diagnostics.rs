@@ -0,0 +1,331 @@
+//! Diagnostics for when extraction stops finding pins because Pinterest changed its
+//! pages.
+//!
+//! With a [`DebugDump`] set on the session (`--debug-dump <dir>`), every page fetched
+//! is saved in full, with its response headers, the JSON blobs it embeds and a
+//! [`PageReport`] of how often each selector and pattern the scrapers rely on
+//! matched. Responses of Pinterest's internal resources are saved too, with their
+//! headers. [`compare_strategies`] runs every extraction strategy on the same page
+//! (`diagnose <url>`), up to a limited number of images each, to see which still work
+//! and what each finds.
+
+use futures::{StreamExt, TryStreamExt};
+use reqwest::header::{HeaderMap, SET_COOKIE};
+use reqwest::StatusCode;
+use scraper::{Html, Selector};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use url::Url;
+
+use crate::error::Result;
+use crate::extract::{merge_key, PinCandidate, Registry, Target};
+use crate::metrics::Outcome;
+use crate::pages::{self, DATA_SCRIPTS};
+use crate::scrape::{initial_state, CONTAINER_SELECTORS, MEDIA_URL_PREFIXES};
+use crate::session::Session;
+
+/// Longest part of a dump's file names made from the page's path
+const MAX_NAME_LEN: usize = 60;
+
+/// Text patterns the scrapers look for in a page, besides the media URL prefixes
+const PATTERNS: &[&str] = &["\"initial_state\"", "\"original\"", "\"images\"", "\"orig\"", "\"image_url\"", "\"url\":"];
+
+/// A directory that fetched pages and resource responses are saved to, numbered in
+/// the order they're fetched
+#[derive(Debug)]
+pub struct DebugDump {
+    dir: PathBuf,
+    next: AtomicUsize,
+}
+
+impl DebugDump {
+    /// Saves into `dir`, which is created if needed
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, next: AtomicUsize::new(1) })
+    }
+
+    /// The directory pages are saved to
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Saves a fetched page as `<n>-<path>.html`, and next to it its headers, each
+    /// JSON blob it embeds and its [`PageReport`]. Returns the path of the report.
+    pub fn save_page(&self, url: &Url, status: StatusCode, headers: &HeaderMap, html: &str) -> Result<PathBuf> {
+        let stem = format!("{:03}-{}", self.next.fetch_add(1, Ordering::Relaxed), file_name(url));
+        let path = |suffix: &str| self.dir.join(format!("{}{}", stem, suffix));
+
+        std::fs::write(path(".html"), html)?;
+        std::fs::write(path(".headers.txt"), render_headers(url, status, headers))?;
+        for (name, blob) in json_blobs(html) {
+            std::fs::write(path(&format!(".{}.json", name)), blob)?;
+        }
+        let report_path = path(".report.txt");
+        std::fs::write(&report_path, PageReport::new(url, status, html).render())?;
+        Ok(report_path)
+    }
+
+    /// Saves the response of an internal resource such as `BoardFeedResource` as
+    /// `<n>-<resource>.json`, pretty-printed when it parses, with its headers next to
+    /// it. Returns the path of the response.
+    pub fn save_resource(&self, resource: &str, url: &Url, status: StatusCode, headers: &HeaderMap, body: &str) -> Result<PathBuf> {
+        let name: String = resource.chars().filter(|c| c.is_alphanumeric()).take(MAX_NAME_LEN).collect();
+        let stem = format!("{:03}-{}", self.next.fetch_add(1, Ordering::Relaxed), name);
+        let path = |suffix: &str| self.dir.join(format!("{}{}", stem, suffix));
+
+        let body = match serde_json::from_str::<Value>(body) {
+            Ok(value) => serde_json::to_string_pretty(&value).unwrap_or_else(|_| body.to_string()),
+            Err(_) => body.to_string(),
+        };
+        let body_path = path(".json");
+        std::fs::write(&body_path, body)?;
+        std::fs::write(path(".headers.txt"), render_headers(url, status, headers))?;
+        Ok(body_path)
+    }
+}
+
+/// Makes a page's path safe to use in a file name, e.g. `alice_cats` for `/alice/cats/`
+fn file_name(url: &Url) -> String {
+    let name: String = url
+        .path()
+        .trim_matches('/')
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .take(MAX_NAME_LEN)
+        .collect();
+    if name.is_empty() { "home".to_string() } else { name }
+}
+
+/// The request line, status and response headers of a page. Cookie values are left
+/// out, since they may hold a logged-in session and dumps are meant to be shared.
+fn render_headers(url: &Url, status: StatusCode, headers: &HeaderMap) -> String {
+    let mut out = format!("GET {}\n{}\n", url, status);
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        let value = match value.split_once('=') {
+            Some((cookie, _)) if name == SET_COOKIE => format!("{}=<hidden>", cookie),
+            _ => value.into_owned(),
+        };
+        out.push_str(&format!("{}: {}\n", name, value));
+    }
+    out
+}
+
+/// The JSON blobs a page embeds, by name: its data scripts and its `initial_state`
+/// object, pretty-printed when they parse
+pub fn json_blobs(html: &str) -> Vec<(String, String)> {
+    let pretty = |text: &str| match serde_json::from_str::<Value>(text) {
+        Ok(value) => serde_json::to_string_pretty(&value).unwrap_or_else(|_| text.to_string()),
+        Err(_) => text.to_string(),
+    };
+
+    let document = Html::parse_document(html);
+    let mut blobs = Vec::new();
+    for script_id in DATA_SCRIPTS {
+        let Ok(selector) = Selector::parse(&format!("script#{}", script_id)) else { continue };
+        for script in document.select(&selector) {
+            blobs.push((script_id.to_string(), pretty(&script.inner_html())));
+        }
+    }
+    if let Some(state) = initial_state(html) {
+        blobs.push(("initial_state".to_string(), pretty(state)));
+    }
+    blobs
+}
+
+/// What the scrapers can still find in a page: how often each selector and pattern
+/// they rely on matched
+#[derive(Debug, Clone)]
+pub struct PageReport {
+    pub url: String,
+    pub status: StatusCode,
+    pub bytes: usize,
+    /// Pins found in the data the page embeds
+    pub embedded_pins: usize,
+    /// Elements matched by each selector
+    pub selectors: Vec<(String, usize)>,
+    /// Occurrences of each text pattern and media URL prefix
+    pub patterns: Vec<(String, usize)>,
+}
+
+impl PageReport {
+    pub fn new(url: &Url, status: StatusCode, html: &str) -> Self {
+        let document = Html::parse_document(html);
+        let data_scripts = DATA_SCRIPTS.iter().map(|id| format!("script#{}", id));
+        let others = [r#"meta[property="og:image"]"#, "script", "img"].into_iter().map(str::to_string);
+        let containers = CONTAINER_SELECTORS.iter().map(|selector| selector.to_string());
+        let selectors = data_scripts
+            .chain(others)
+            .chain(containers)
+            .map(|selector| {
+                let count = Selector::parse(&selector).map(|parsed| document.select(&parsed).count()).unwrap_or(0);
+                (selector, count)
+            })
+            .collect();
+        let patterns = PATTERNS
+            .iter()
+            .chain(MEDIA_URL_PREFIXES)
+            .map(|pattern| (pattern.to_string(), html.matches(pattern).count()))
+            .collect();
+
+        Self {
+            url: url.to_string(),
+            status,
+            bytes: html.len(),
+            embedded_pins: pages::embedded_pins(html, None).len(),
+            selectors,
+            patterns,
+        }
+    }
+
+    /// Renders the report as a table of match counts
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Page:          {}\nStatus:        {}\nSize:          {} bytes\nEmbedded pins: {}\n\n",
+            self.url, self.status, self.bytes, self.embedded_pins
+        );
+        out.push_str(&format!("  {:>7}  {}\n", "MATCHES", "SELECTOR"));
+        for (selector, count) in &self.selectors {
+            out.push_str(&format!("  {:>7}  {}\n", count, selector));
+        }
+        out.push_str(&format!("\n  {:>7}  {}\n", "MATCHES", "PATTERN"));
+        for (pattern, count) in &self.patterns {
+            out.push_str(&format!("  {:>7}  {}\n", count, pattern));
+        }
+        out
+    }
+}
+
+/// Number of images each strategy is stopped at by `diagnose` unless told otherwise,
+/// so a large board or profile isn't paged through to its end once per strategy
+pub const DEFAULT_DIAGNOSE_LIMIT: usize = 50;
+
+/// How one strategy did on its own, for `diagnose`
+#[derive(Debug, Clone)]
+pub struct StrategyReport {
+    pub name: &'static str,
+    /// How the run went; `None` if the strategy doesn't apply to this kind of source
+    pub outcome: Option<Outcome>,
+    pub elapsed: Duration,
+    /// URLs of the images found, in the order found
+    pub urls: Vec<String>,
+    /// Whether the strategy was stopped at the limit, and might have found more
+    pub capped: bool,
+}
+
+/// Runs every strategy of `registry` that can handle the target, one after the other,
+/// and returns what each found on its own, stopping each at `limit` images. Unlike a
+/// download, nothing is recorded in the strategy metrics.
+pub async fn compare_strategies(registry: &Registry, target: &Target<'_>, session: &Session, limit: usize) -> Vec<StrategyReport> {
+    let mut reports = Vec::new();
+    for extractor in registry.extractors() {
+        let mut report =
+            StrategyReport { name: extractor.name(), outcome: None, elapsed: Duration::ZERO, urls: Vec::new(), capped: false };
+        if extractor.can_handle(target.source) {
+            let start = Instant::now();
+            let found: Result<Vec<PinCandidate>> = extractor.extract(target, session).take(limit).try_collect().await;
+            report.elapsed = start.elapsed();
+            report.outcome = Some(match found {
+                Ok(found) if found.is_empty() => Outcome::Empty,
+                Ok(found) => {
+                    report.capped = found.len() >= limit;
+                    report.urls = found.into_iter().map(|candidate| candidate.url).collect();
+                    Outcome::Found(report.urls.len())
+                }
+                Err(e) => Outcome::Failed(e.to_string()),
+            });
+        }
+        reports.push(report);
+    }
+    reports
+}
+
+/// Renders a comparison of strategies as a table: what each found, how long it took,
+/// and how many of its images no other strategy found. A `+` marks a strategy that was
+/// stopped at the limit.
+pub fn render_comparison(reports: &[StrategyReport]) -> String {
+    // Which strategies found each image, counting the sizes of an image as one
+    let mut finders: HashMap<String, usize> = HashMap::new();
+    for report in reports {
+        let mut keys: Vec<String> = report.urls.iter().map(|url| merge_key(url)).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            *finders.entry(key).or_default() += 1;
+        }
+    }
+
+    let mut out = format!("  {:<14} {:<12} {:>8} {:>10}\n", "STRATEGY", "RESULT", "TIME", "ONLY HERE");
+    for report in reports {
+        let result = match &report.outcome {
+            None => {
+                out.push_str(&format!("  {:<14} {}\n", report.name, "n/a"));
+                continue;
+            }
+            Some(Outcome::Found(count)) if report.capped => format!("found {}+", count),
+            Some(Outcome::Found(count)) => format!("found {}", count),
+            Some(Outcome::Empty) => "empty".to_string(),
+            Some(Outcome::Failed(_)) => "failed".to_string(),
+        };
+        let mut only_here: Vec<String> = report.urls.iter().map(|url| merge_key(url)).filter(|key| finders[key] == 1).collect();
+        only_here.sort();
+        only_here.dedup();
+        out.push_str(&format!(
+            "  {:<14} {:<12} {:>6}ms {:>10}\n",
+            report.name,
+            result,
+            report.elapsed.as_millis(),
+            only_here.len()
+        ));
+        match (&report.outcome, report.urls.first()) {
+            (Some(Outcome::Failed(error)), _) => out.push_str(&format!("  {:<14} error: {}\n", "", error)),
+            (_, Some(first)) => out.push_str(&format!("  {:<14} first: {}\n", "", first)),
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_selector_and_pattern_matches() {
+        let html = r#"<html><head><meta property="og:image" content="https://i.pinimg.com/736x/aa/bb/one.jpg"></head>
+            <body><div data-test-id="pin"><img src="https://i.pinimg.com/236x/aa/bb/two.jpg"></div>
+            <script id="__PWS_DATA__">{"initial_state":{"pins":{"1":{"id":"1","images":{"orig":{"url":"https://i.pinimg.com/originals/aa/bb/one.jpg"}}}}}}</script>
+            </body></html>"#;
+        let report = PageReport::new(&Url::parse("https://www.pinterest.com/alice/cats/").unwrap(), StatusCode::OK, html);
+        let count = |list: &[(String, usize)], key: &str| list.iter().find(|(name, _)| name == key).map(|(_, count)| *count);
+
+        assert_eq!(report.embedded_pins, 1);
+        assert_eq!(count(&report.selectors, "script#__PWS_DATA__"), Some(1));
+        assert_eq!(count(&report.selectors, "script#__PWS_INITIAL_PROPS__"), Some(0));
+        assert_eq!(count(&report.selectors, "div[data-test-id=\"pin\"] img"), Some(1));
+        assert_eq!(count(&report.patterns, "\"initial_state\""), Some(1));
+        assert_eq!(count(&report.patterns, "https://i.pinimg.com/originals/"), Some(1));
+        assert_eq!(count(&report.patterns, "https://i.pinimg.com/736x/"), Some(1));
+
+        let blobs = json_blobs(html);
+        let names: Vec<&str> = blobs.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["__PWS_DATA__", "initial_state"]);
+        assert!(blobs[1].1.starts_with("{\n  \"pins\""));
+    }
+
+    #[test]
+    fn hides_cookie_values_in_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "text/html".parse().unwrap());
+        headers.insert(SET_COOKIE, "_pinterest_sess=secret; Path=/".parse().unwrap());
+        let rendered = render_headers(&Url::parse("https://www.pinterest.com/").unwrap(), StatusCode::OK, &headers);
+        assert!(rendered.contains("content-type: text/html\n"));
+        assert!(rendered.contains("set-cookie: _pinterest_sess=<hidden>\n"));
+        assert!(!rendered.contains("secret"));
+    }
+}
@@ -0,0 +1,509 @@
+//! Downloading: finding the media of a source and saving it, in parallel.
+//!
+//! [`download`] resolves the URL, runs the extraction pipeline (or reads a `me/...`
+//! source with the logged-in session), filters and limits what was found, then has a
+//! few workers save each file into the collection folder, skipping duplicates by
+//! content and writing a JSON sidecar of metadata next to every file.
+
+use fnv::FnvHasher;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex as StdMutex;
+use std::time::SystemTime;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, info, info_span, instrument, warn, Instrument};
+use url::Url;
+
+use crate::accounts::DEFAULT_ACCOUNT;
+use crate::authenticated::{self, AuthSource};
+use crate::boards::{BoardFilters, SectionsMode};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::extract::{PinCandidate, Registry, Target};
+use crate::formats;
+use crate::media::{self, MediaFilter, MediaKind};
+use crate::metrics;
+use crate::pages::PinPage;
+use crate::paths::get_output_root;
+use crate::progress::{DownloadProgress, DownloadStats, ProgressEvent};
+use crate::resolve;
+use crate::resources;
+use crate::search::SearchArgs;
+use crate::session::Session;
+use crate::source::{PinterestSource, SearchScope};
+
+/// What to download, and how: the arguments of the `download` command
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct DownloadOptions {
+    /// The Pinterest URL (e.g., a board or pin) to download images from.
+    #[cfg_attr(feature = "clap", arg(short, long))]
+    pub url: String,
+
+    /// The genre to classify the downloaded images under (optional).
+    #[cfg_attr(feature = "clap", arg(short, long))]
+    pub genre: Option<String>,
+
+    /// The query name to classify the downloaded images under (optional).
+    #[cfg_attr(feature = "clap", arg(short, long))]
+    pub query: Option<String>,
+
+    /// Image quality to download (original, 736x, 474x, 236x)
+    #[cfg_attr(feature = "clap", arg(long, default_value = "original"))]
+    pub quality: String,
+
+    /// Maximum number of images to download (0 = unlimited)
+    #[cfg_attr(feature = "clap", arg(short, long, default_value = "0"))]
+    pub max_images: usize,
+
+    /// Number of images to download in parallel
+    #[cfg_attr(feature = "clap", arg(short, long, default_value_t = DEFAULT_WORKERS))]
+    pub workers: usize,
+
+    /// What to do with a board's sections (by default only pins outside sections are downloaded)
+    #[cfg_attr(feature = "clap", arg(long, value_enum))]
+    pub sections: Option<SectionsMode>,
+
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub boards: BoardFilters,
+
+    /// For a pin URL, also download this many related ("more like this") pins
+    #[cfg_attr(feature = "clap", arg(long, value_name = "N", default_value = "0"))]
+    pub related: usize,
+
+    /// Which kinds of media to download
+    #[cfg_attr(feature = "clap", arg(long, value_enum, default_value_t))]
+    pub media: MediaFilter,
+
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub search: SearchArgs,
+
+    /// Extraction strategy to use instead of trying them all (repeatable, tried in the
+    /// order given; `strategies` lists them)
+    #[cfg_attr(feature = "clap", arg(long = "strategy", value_name = "NAME", value_parser = crate::extract::parse_strategy))]
+    pub strategies: Vec<String>,
+
+    /// Run every strategy that applies and merge what they find, instead of stopping
+    /// at the first that finds something
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub merge: bool,
+
+    /// Account whose session to download with (set from the global --account flag)
+    #[cfg_attr(feature = "clap", arg(skip = DEFAULT_ACCOUNT.to_string()))]
+    pub account: String,
+}
+
+
+impl Default for DownloadOptions {
+    /// Options as the `download` command defaults them, for an empty URL
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            genre: None,
+            query: None,
+            quality: "original".to_string(),
+            max_images: 0,
+            workers: DEFAULT_WORKERS,
+            sections: None,
+            boards: BoardFilters::default(),
+            related: 0,
+            media: MediaFilter::All,
+            search: SearchArgs::default(),
+            strategies: Vec::new(),
+            merge: false,
+            account: DEFAULT_ACCOUNT.to_string(),
+        }
+    }
+}
+
+/// Number of parallel downloads unless configured otherwise
+pub const DEFAULT_WORKERS: usize = 4;
+
+/// What a download run produced
+#[derive(Debug, Clone)]
+pub struct DownloadSummary {
+    /// Directory the images were saved to
+    pub output_dir: PathBuf,
+    /// File names of the images saved by this run
+    pub files: Vec<String>,
+    /// Counters for the end-of-run summary
+    pub stats: DownloadStats,
+}
+
+/// Downloads what `options.url` points to into `<genre>/<query>` under the output
+/// root, passing what its workers are doing on to `on_progress`
+#[instrument(name = "download", skip_all, fields(url = %options.url))]
+pub async fn download(
+    options: &DownloadOptions,
+    session: &Session,
+    on_progress: impl Fn(ProgressEvent<'_>) + Sync,
+) -> Result<DownloadSummary> {
+    info!(quality = %options.quality, "Attempting to download images from {}", options.url);
+    
+    // `me/...` sources aren't URLs; anything else is resolved to a canonical Pinterest URL first
+    let auth_source = AuthSource::parse(&options.url);
+    let (url, source) = match &auth_source {
+        Some(_) => (None, None),
+        None => {
+            let url = resolve::resolve(&options.url, session).await?;
+            let source = PinterestSource::parse(&url);
+            match &source {
+                Some(source) => info!(source = %source, "Resolved {} to {} ({})", options.url, url, source),
+                None => debug!("Resolved {} to {}, which isn't a known kind of Pinterest page", options.url, url),
+            }
+            (Some(url), source)
+        }
+    };
+
+    // Get the genre/query if provided, otherwise use defaults.
+    // A user's boards are saved under the user's name, one folder per board.
+    let genre = options.genre.as_deref().unwrap_or("Uncategorized");
+    let query = match (&options.query, &source) {
+        (Some(query), _) => query.as_str(),
+        (None, Some(PinterestSource::User { name })) => name.as_str(),
+        (None, _) => "Pinterest",
+    };
+    
+    info!(genre, query, "Classifying under genre '{}', query '{}'", genre, query);
+    
+    // Print max images limit if set
+    if options.max_images > 0 {
+        info!(max_images = options.max_images, "Will download at most {} images", options.max_images);
+    }
+
+    let mut image_urls = if let Some(auth_source) = &auth_source {
+        info!(strategy = "authenticated", "Fetching {} with the logged-in session...", auth_source);
+        let urls = authenticated::fetch_images(auth_source, session, options.max_images).await?;
+        urls.into_iter().map(PinCandidate::from).collect()
+    } else if let Some(url) = &url {
+        extract_images(source.as_ref(), url, session, options).await?
+    } else {
+        Vec::new()
+    };
+    
+    info!(count = image_urls.len(), "Found {} unique image URLs", image_urls.len());
+
+    // Keep the session cookies Pinterest handed out for the next run
+    if let Err(e) = session.save() {
+        warn!(error = %e, "Failed to save cookies");
+    }
+
+    // Construct base output path
+    let base_output_dir = get_output_root()?.join(genre).join(query);
+    let mut summary = DownloadSummary {
+        output_dir: base_output_dir.clone(),
+        files: Vec::new(),
+        stats: DownloadStats::default(),
+    };
+
+    if image_urls.is_empty() {
+        warn!("No images found to download");
+        return Ok(summary);
+    }
+    
+    // Keep only the kinds of media asked for
+    let found = image_urls.len();
+    image_urls.retain(|image| options.media.includes(MediaKind::of(&image.url)));
+    let mut filtered = found - image_urls.len();
+    if filtered > 0 {
+        info!(filtered, "Skipping {} files that aren't {:?}", filtered, options.media);
+    }
+    if image_urls.is_empty() {
+        warn!("No media of the requested kind found to download");
+        summary.stats.filtered = filtered;
+        return Ok(summary);
+    }
+
+    // Apply max_images limit if set
+    if options.max_images > 0 && image_urls.len() > options.max_images {
+        info!("Limiting to {} images as requested", options.max_images);
+        filtered += image_urls.len() - options.max_images;
+        image_urls.truncate(options.max_images);
+    }
+
+    // Create directories if they don't exist
+    fs::create_dir_all(&base_output_dir).await?;
+    info!(output_dir = %base_output_dir.display(), "Saving images to {}", base_output_dir.display());
+
+    // Images are numbered per folder; pages of multi-page pins are named after their pin instead
+    let mut folder_counts: HashMap<Option<&str>, usize> = HashMap::new();
+    let mut numbers = Vec::with_capacity(image_urls.len());
+    for image in &image_urls {
+        let count = folder_counts.entry(image.folder.as_deref()).or_default();
+        if image.page.is_none() {
+            *count += 1;
+        }
+        numbers.push(*count);
+    }
+    for folder in folder_counts.keys().flatten() {
+        fs::create_dir_all(base_output_dir.join(folder)).await?;
+    }
+
+    let client = session.client();
+
+    // Track which images we've downloaded already using a hash of the file content
+    let downloaded_hashes = StdMutex::new(HashSet::new());
+    let saved_files = StdMutex::new(Vec::new());
+    let next_index = AtomicUsize::new(0);
+
+    let total = image_urls.len();
+    let workers = options.workers.clamp(1, total);
+    let progress = DownloadProgress::new(&on_progress, total, workers, filtered);
+
+    // Each worker takes the next image off the list until none are left
+    let worker_tasks = (0..workers).map(|worker| {
+        let (image_urls, numbers, progress) = (&image_urls, &numbers, &progress);
+        let (downloaded_hashes, saved_files, next_index) = (&downloaded_hashes, &saved_files, &next_index);
+        let base_output_dir = &base_output_dir;
+        async move {
+            loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let Some(image) = image_urls.get(index) else { break };
+                let img_url_str = &image.url;
+
+                let img_url = match Url::parse(img_url_str) {
+                    Ok(img_url) => img_url,
+                    Err(e) => {
+                        progress.failed(worker, index + 1, img_url_str, &e.into());
+                        continue;
+                    }
+                };
+
+                let image_span = info_span!("image", index = index + 1, total, worker = worker + 1, url = %img_url);
+                async {
+                    // Generate filename (e.g., image_001.jpg, or 123456_p01.jpg for a page of a pin)
+                    let extension = Path::new(img_url.path())
+                        .extension()
+                        .and_then(|os_str| os_str.to_str())
+                        .unwrap_or("jpg"); // Default to jpg if no extension
+                    let filename = match (&image.pin_id, &image.page) {
+                        (Some(pin_id), Some(page)) => format!("{}_p{:02}.{}", pin_id, page.number, extension),
+                        _ => format!("image_{:03}.{}", numbers[index], extension),
+                    };
+                    let filename = match &image.folder {
+                        Some(folder) => format!("{}/{}", folder, filename),
+                        None => filename,
+                    };
+                    let dest_path = base_output_dir.join(&filename);
+
+                    debug!("Downloading {} to {}", img_url, dest_path.display());
+                    progress.start_file(worker, &filename);
+                    let on_chunk = |file_bytes, chunk| progress.add_bytes(worker, &filename, file_bytes, chunk);
+                    let fetch_url = session.media_url(&img_url);
+                    match download_image_with_deduplication(client, &fetch_url, &dest_path, downloaded_hashes, on_chunk).await {
+                        Ok(Some((saved_path, size))) => {
+                            // Files are saved with the extension of their format, which for streams is their container's
+                            let filename = match saved_path.strip_prefix(base_output_dir) {
+                                Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+                                Err(_) => filename.clone(),
+                            };
+                            let dest_path = saved_path;
+                            let metadata = ImageMetadata {
+                                source_url: img_url.to_string(),
+                                page_url: options.url.clone(),
+                                genre: genre.to_string(),
+                                query: query.to_string(),
+                                quality: options.quality.clone(),
+                                related_to: image.related_to.clone(),
+                                pin_id: image.pin_id.clone(),
+                                page: image.page.clone(),
+                                downloaded_at: SystemTime::now()
+                                    .duration_since(SystemTime::UNIX_EPOCH)
+                                    .map(|d| d.as_secs())
+                                    .unwrap_or(0),
+                            };
+                            if let Err(e) = write_sidecar(&dest_path, &metadata).await {
+                                warn!(error = %e, "Failed to write metadata for {}", filename);
+                            }
+                            saved_files.lock().unwrap_or_else(|e| e.into_inner()).push(filename.clone());
+                            progress.downloaded(worker, index + 1, &filename, size);
+                        },
+                        Ok(None) => progress.duplicate(worker, index + 1),
+                        Err(e) => progress.failed(worker, index + 1, img_url.as_str(), &e),
+                    }
+
+                    // Add a small delay between downloads to be polite to the server
+                    if index + 1 < total {
+                        tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+                    }
+                }
+                .instrument(image_span)
+                .await;
+            }
+        }
+    });
+    futures::future::join_all(worker_tasks).await;
+
+    summary.stats = progress.finish();
+    summary.files = saved_files.into_inner().unwrap_or_else(|e| e.into_inner());
+    summary.files.sort();
+
+    info!(
+        downloaded = summary.stats.downloaded,
+        duplicates = summary.stats.duplicates,
+        failed = summary.stats.failed,
+        filtered = summary.stats.filtered,
+        bytes = summary.stats.bytes,
+        "Finished downloading {} unique images",
+        summary.stats.downloaded
+    );
+
+    Ok(summary)
+}
+
+/// Finds the images of a source with the extraction strategies that can handle it
+pub async fn extract_images(
+    source: Option<&PinterestSource>,
+    url: &Url,
+    session: &Session,
+    options: &DownloadOptions,
+) -> Result<Vec<PinCandidate>> {
+    if options.search.is_set() && !matches!(source, Some(PinterestSource::Search { .. })) {
+        warn!("Search options only apply to search URLs; ignoring them for {}", url);
+    }
+    let target = Target::new(url, source, options);
+    if let Some(search) = &target.search {
+        info!(scope = search.scope.as_str(), terms = %search.terms(), "Searching {} for '{}'", search.scope.as_str(), search.terms());
+        if search.scope == SearchScope::Users {
+            return Err(Error::Unsupported(
+                "User search results have no pins to download; download the profile URL of a user instead".to_string(),
+            ));
+        }
+    }
+
+    let config = Config::load()?;
+    let registry = Registry::configured(&config.strategy_order, &options.strategies)?;
+    let extraction = registry.run(&target, session, options.merge).await?;
+    metrics::record_runs(target.kind(), &extraction.runs);
+    let mut images = extraction.candidates;
+
+    if let Some(PinterestSource::Pin { id }) = source {
+        if images.is_empty() {
+            return Err(Error::NotFound(format!("Couldn't find the image of pin {}", id)));
+        }
+        if options.related > 0 {
+            images.extend(fetch_related_images(id, &images, session, options.related).await);
+        }
+    }
+    Ok(images)
+}
+
+/// Up to `count` of the pins Pinterest shows as related to pin `id`, other than `images`
+pub async fn fetch_related_images(id: &str, images: &[PinCandidate], session: &Session, count: usize) -> Vec<PinCandidate> {
+    info!(strategy = "related", "Fetching pins related to {}...", id);
+    match resources::fetch_related(session, id, count + 1).await {
+        Ok(related) => related
+            .into_iter()
+            .filter(|media| !images.iter().any(|image| image.url == media.url))
+            .take(count)
+            .map(|media| PinCandidate { related_to: Some(id.to_string()), ..media.into() })
+            .collect(),
+        Err(e) => {
+            warn!(strategy = "related", error = %e, "Couldn't fetch related pins");
+            Vec::new()
+        }
+    }
+}
+
+/// Downloads an image (or video) from a URL, checks for duplicates, and saves it to a destination path.
+/// Responses that aren't images or videos, such as HTML error pages, are rejected, and the file is
+/// saved with the extension of the format it really is, e.g. `.webp` for a WebP served at a `.jpg` URL.
+/// `on_chunk` is called with the bytes received so far and the size of each new chunk.
+/// Returns Ok(Some((path, size))) if the file was saved, Ok(None) if it was a duplicate, or an Error.
+pub async fn download_image_with_deduplication(
+    client: &Client, 
+    url: &Url, 
+    dest_path: &Path,
+    downloaded_hashes: &StdMutex<HashSet<u64>>,
+    mut on_chunk: impl FnMut(u64, u64),
+) -> Result<Option<(PathBuf, u64)>> {
+    let mut dest_path = dest_path.to_path_buf();
+    let mut content = Vec::new();
+    let mut stream_format = None;
+    if media::is_playlist(url.as_str()) {
+        let (joined, format) = media::download_hls(client, url, on_chunk).await?;
+        content = joined;
+        stream_format = Some(format);
+    } else {
+        let mut response = client.get(url.clone()).send().await?.error_for_status()?;
+        // Don't bother reading a page served instead of the file
+        let content_type = response.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok());
+        if let Err(reason) = formats::check_content_type(content_type) {
+            return Err(Error::NotMedia { url: url.to_string(), reason });
+        }
+        while let Some(chunk) = response.chunk().await? {
+            content.extend_from_slice(&chunk);
+            on_chunk(content.len() as u64, chunk.len() as u64);
+        }
+    }
+
+    // Make sure it's really an image or video, and name it after what it is. The
+    // segments of an HLS stream have to be what its playlist says they are.
+    let format = formats::validate(url, &content)?;
+    if let Some(expected) = stream_format.filter(|expected| *expected != format) {
+        return Err(Error::NotMedia {
+            url: url.to_string(),
+            reason: format!("the stream's segments are {} rather than {}", format.extension(), expected.extension()),
+        });
+    }
+    dest_path.set_extension(format.extension());
+    
+    // Compute a simple hash of the image content to detect duplicates
+    // We use a simple FNV hash here, but you could use a more sophisticated image hash
+    let mut hasher = FnvHasher::default();
+    hasher.write(&content);
+    let content_hash = hasher.finish();
+    
+    // If we've already downloaded this image (by content), skip it.
+    // Otherwise record its hash before saving so parallel workers don't save it twice.
+    if !downloaded_hashes.lock().unwrap_or_else(|e| e.into_inner()).insert(content_hash) {
+        return Ok(None);
+    }
+    
+    let mut file = File::create(&dest_path).await?;
+    file.write_all(&content).await?;
+    // The write may still be in flight until the file is flushed
+    file.flush().await?;
+
+    Ok(Some((dest_path, content.len() as u64)))
+}
+
+/// Metadata saved next to each downloaded image as `<image>.json`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageMetadata {
+    pub source_url: String,
+    pub page_url: String,
+    pub genre: String,
+    pub query: String,
+    pub quality: String,
+    /// Id of the pin this image is related to, for pins found with `--related`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_to: Option<String>,
+    /// Id of the pin the image belongs to, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_id: Option<String>,
+    /// Page number, text overlays and caption, for a page of an Idea or carousel pin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<PinPage>,
+    pub downloaded_at: u64,
+}
+
+/// Path of the JSON sidecar that holds metadata for an image
+pub fn sidecar_path(image_path: &Path) -> PathBuf {
+    let mut name = image_path.as_os_str().to_owned();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+/// Writes the metadata sidecar for a downloaded image
+pub async fn write_sidecar(image_path: &Path, metadata: &ImageMetadata) -> Result<()> {
+    let content = serde_json::to_vec_pretty(metadata)?;
+    fs::write(sidecar_path(image_path), content).await?;
+    Ok(())
+}
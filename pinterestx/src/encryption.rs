@@ -0,0 +1,278 @@
+//! Encryption of credentials stored on disk.
+//!
+//! Session cookies are encrypted with ChaCha20-Poly1305. The key is derived with
+//! Argon2id from the passphrase in `PINTERESTX_COOKIE_PASSPHRASE` when that is set,
+//! and otherwise read from a key file (by default `cookies.key` in the application
+//! data directory, generated on first use). Encrypted data is stored as a small JSON
+//! envelope that records how the key was obtained.
+
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::paths::{get_app_data_dir, write_private_file_atomically};
+
+/// Environment variable holding the passphrase cookies are encrypted with
+pub const PASSPHRASE_ENV: &str = "PINTERESTX_COOKIE_PASSPHRASE";
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Where the encryption key comes from
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    Passphrase(String),
+    KeyFile(PathBuf),
+}
+
+impl KeySource {
+    /// Human-readable description, without revealing the passphrase
+    pub fn describe(&self) -> String {
+        match self {
+            KeySource::Passphrase(_) => format!("passphrase (from {})", PASSPHRASE_ENV),
+            KeySource::KeyFile(path) => format!("key file {}", path.display()),
+        }
+    }
+}
+
+static KEY_SOURCE: OnceCell<KeySource> = OnceCell::new();
+
+/// Chooses the key source for this process: the passphrase environment variable if
+/// set, otherwise `key_file`, otherwise the default key file
+pub fn configure(key_file: Option<PathBuf>) {
+    let source = match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) if !passphrase.is_empty() => KeySource::Passphrase(passphrase),
+        _ => KeySource::KeyFile(key_file.unwrap_or_else(default_key_file_path)),
+    };
+    let _ = KEY_SOURCE.set(source);
+}
+
+/// The configured key source
+pub fn key_source() -> &'static KeySource {
+    KEY_SOURCE.get_or_init(|| KeySource::KeyFile(default_key_file_path()))
+}
+
+fn default_key_file_path() -> PathBuf {
+    get_app_data_dir().join("cookies.key")
+}
+
+/// How the key of an envelope was obtained
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Kdf {
+    Argon2id,
+    KeyFile,
+}
+
+/// Encrypted data as stored on disk
+#[derive(Serialize, Deserialize, Debug)]
+struct Envelope {
+    version: u32,
+    kdf: Kdf,
+    /// Argon2 salt (passphrase keys only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Encrypts `plaintext` with the configured key, returning the serialized envelope
+pub fn encrypt(plaintext: &[u8]) -> Result<Vec<u8>> {
+    encrypt_with(key_source(), plaintext)
+}
+
+/// Encrypts `plaintext` with the key from `source`, returning the serialized envelope
+pub fn encrypt_with(source: &KeySource, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let (kdf, salt, key) = match source {
+        KeySource::Passphrase(passphrase) => {
+            let mut salt = [0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            (Kdf::Argon2id, Some(BASE64.encode(salt)), derive_key(passphrase, &salt)?)
+        }
+        KeySource::KeyFile(path) => (Kdf::KeyFile, None, load_or_create_key_file(path)?),
+    };
+
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| Error::Cookies("Failed to encrypt cookies".to_string()))?;
+
+    let envelope = Envelope {
+        version: 1,
+        kdf,
+        salt,
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+    Ok(serde_json::to_vec_pretty(&envelope)?)
+}
+
+/// Decrypts an envelope produced by [`encrypt`]
+pub fn decrypt(data: &[u8]) -> Result<Vec<u8>> {
+    decrypt_with(key_source(), data)
+}
+
+/// Decrypts an envelope produced by [`encrypt_with`] with the key from `source`
+pub fn decrypt_with(source: &KeySource, data: &[u8]) -> Result<Vec<u8>> {
+    let envelope: Envelope = serde_json::from_slice(data)?;
+    if envelope.version != 1 {
+        return Err(Error::Cookies(format!("Unsupported encrypted file version {}", envelope.version)));
+    }
+
+    let key = match (envelope.kdf, source) {
+        (Kdf::Argon2id, KeySource::Passphrase(passphrase)) => {
+            let salt = decode(envelope.salt.as_deref().unwrap_or_default())?;
+            derive_key(passphrase, &salt)?
+        }
+        (Kdf::Argon2id, KeySource::KeyFile(_)) => {
+            return Err(Error::Cookies(format!("The cookies are protected by a passphrase; set {} to unlock them", PASSPHRASE_ENV)));
+        }
+        (Kdf::KeyFile, KeySource::KeyFile(path)) => {
+            if !path.exists() {
+                return Err(Error::Cookies(format!("The cookie key file {} is missing", path.display())));
+            }
+            load_or_create_key_file(path)?
+        }
+        (Kdf::KeyFile, KeySource::Passphrase(_)) => {
+            return Err(Error::Cookies(format!("The cookies are protected by a key file; unset {} to use it", PASSPHRASE_ENV)));
+        }
+    };
+
+    let nonce = decode(&envelope.nonce)?;
+    if nonce.len() != NONCE_LEN {
+        return Err(Error::Cookies("Corrupt encrypted file: invalid nonce".to_string()));
+    }
+    let ciphertext = decode(&envelope.ciphertext)?;
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| Error::Cookies("Failed to decrypt cookies: wrong passphrase or key file".to_string()))
+}
+
+fn decode(encoded: &str) -> Result<Vec<u8>> {
+    BASE64
+        .decode(encoded)
+        .map_err(|e| Error::Cookies(format!("Corrupt encrypted file: {}", e)))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LEN]> {
+    let mut key = [0u8; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::Cookies(format!("Failed to derive key from passphrase: {}", e)))?;
+    Ok(key)
+}
+
+/// Reads a base64-encoded key file, generating a new random key if it doesn't exist
+fn load_or_create_key_file(path: &Path) -> Result<[u8; KEY_LEN]> {
+    if path.exists() {
+        let encoded = std::fs::read_to_string(path)?;
+        let bytes = decode(encoded.trim())?;
+        return bytes
+            .try_into()
+            .map_err(|_| Error::Cookies(format!("Invalid key file {}: expected a {}-byte key", path.display(), KEY_LEN)));
+    }
+
+    let mut key = [0u8; KEY_LEN];
+    OsRng.fill_bytes(&mut key);
+    write_private_file_atomically(path, BASE64.encode(key).as_bytes())?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A key file path of its own for each test, in a fresh directory
+    fn key_file(name: &str) -> KeySource {
+        let dir = std::env::temp_dir().join(format!("pinterestx-encryption-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let _ = std::fs::remove_file(&path);
+        KeySource::KeyFile(path)
+    }
+
+    fn passphrase(passphrase: &str) -> KeySource {
+        KeySource::Passphrase(passphrase.to_string())
+    }
+
+    fn envelope(data: &[u8]) -> Envelope {
+        serde_json::from_slice(data).unwrap()
+    }
+
+    #[test]
+    fn round_trips_with_a_passphrase() {
+        let source = passphrase("correct horse");
+        let data = encrypt_with(&source, b"secret cookies").unwrap();
+        assert_eq!(envelope(&data).kdf, Kdf::Argon2id);
+        assert!(!String::from_utf8_lossy(&data).contains("secret"));
+        assert_eq!(decrypt_with(&source, &data).unwrap(), b"secret cookies");
+
+        let error = decrypt_with(&passphrase("wrong horse"), &data).unwrap_err();
+        assert!(matches!(error, Error::Cookies(ref message) if message.contains("wrong passphrase")), "{:?}", error);
+        let error = decrypt_with(&key_file("unused.key"), &data).unwrap_err();
+        assert!(error.to_string().contains(PASSPHRASE_ENV), "{}", error);
+    }
+
+    #[test]
+    fn round_trips_with_a_generated_key_file() {
+        let source = key_file("round-trip.key");
+        let data = encrypt_with(&source, b"secret cookies").unwrap();
+        assert_eq!(envelope(&data).kdf, Kdf::KeyFile);
+        assert_eq!(decrypt_with(&source, &data).unwrap(), b"secret cookies");
+
+        // Another key file holds another key
+        let other = key_file("other.key");
+        encrypt_with(&other, b"").unwrap();
+        let error = decrypt_with(&other, &data).unwrap_err();
+        assert!(matches!(error, Error::Cookies(ref message) if message.contains("wrong passphrase or key file")), "{:?}", error);
+        let error = decrypt_with(&passphrase("correct horse"), &data).unwrap_err();
+        assert!(matches!(error, Error::Cookies(_)), "{:?}", error);
+
+        let KeySource::KeyFile(path) = &source else { unreachable!() };
+        std::fs::remove_file(path).unwrap();
+        let error = decrypt_with(&source, &data).unwrap_err();
+        assert!(error.to_string().contains("is missing"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_truncated_or_corrupted_files() {
+        let source = key_file("corrupt.key");
+        let data = encrypt_with(&source, b"secret cookies").unwrap();
+
+        let truncated = decrypt_with(&source, &data[..data.len() / 2]).unwrap_err();
+        assert!(matches!(truncated, Error::Json(_)), "{:?}", truncated);
+
+        // A flipped bit fails authentication
+        let mut tampered = envelope(&data);
+        let mut ciphertext = BASE64.decode(&tampered.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        tampered.ciphertext = BASE64.encode(ciphertext);
+        let error = decrypt_with(&source, &serde_json::to_vec(&tampered).unwrap()).unwrap_err();
+        assert!(matches!(error, Error::Cookies(_)), "{:?}", error);
+
+        let mut bad_nonce = envelope(&data);
+        bad_nonce.nonce = BASE64.encode([0u8; 4]);
+        let error = decrypt_with(&source, &serde_json::to_vec(&bad_nonce).unwrap()).unwrap_err();
+        assert_eq!(error.to_string(), "Corrupt encrypted file: invalid nonce");
+
+        let mut not_base64 = envelope(&data);
+        not_base64.ciphertext = "%%%".to_string();
+        let error = decrypt_with(&source, &serde_json::to_vec(&not_base64).unwrap()).unwrap_err();
+        assert!(error.to_string().starts_with("Corrupt encrypted file"), "{}", error);
+
+        let mut future = envelope(&data);
+        future.version = 2;
+        let error = decrypt_with(&source, &serde_json::to_vec(&future).unwrap()).unwrap_err();
+        assert_eq!(error.to_string(), "Unsupported encrypted file version 2");
+    }
+}
@@ -0,0 +1,64 @@
+//! The error type of the library.
+
+use reqwest::StatusCode;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Result of the library's fallible operations
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong finding and downloading pins
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A request couldn't be sent, or its response couldn't be read
+    #[error("Network request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    /// Pinterest answered a request with an error status
+    #[error("{what} request failed: {status}")]
+    Http { what: String, status: StatusCode },
+    #[error("Filesystem error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Failed to parse URL: {0}")]
+    Url(#[from] url::ParseError),
+    /// A board, section or pin that doesn't exist, or isn't visible to the session
+    #[error("{0}")]
+    NotFound(String),
+    /// Pinterest answered with something else than expected
+    #[error("{0}")]
+    UnexpectedResponse(String),
+    /// A source or option that can't be downloaded, or not in this combination
+    #[error("{0}")]
+    Unsupported(String),
+    /// An invalid argument, e.g. an account name or strategy
+    #[error("{0}")]
+    InvalidInput(String),
+    /// A `me/...` source downloaded without being logged in
+    #[error("{0}")]
+    LoginRequired(String),
+    /// An unreadable configuration or data file
+    #[error("Invalid {what} file {}: {message}", path.display())]
+    Config { what: &'static str, path: PathBuf, message: String },
+    /// Cookies that couldn't be imported, encrypted or decrypted
+    #[error("{0}")]
+    Cookies(String),
+    /// A video stream that can't be downloaded
+    #[error("{0}")]
+    Media(String),
+    /// A download that isn't an image or video, e.g. an HTML login wall or error page
+    #[error("{url} is not an image or video: {reason}")]
+    NotMedia { url: String, reason: String },
+}
+
+impl Error {
+    /// The HTTP status of a failed request, if it got a response
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Request(e) => e.status(),
+            Error::Http { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,574 @@
+//! Extraction strategies and the pipeline that runs them.
+//!
+//! Each way of finding the pins of a page is an [`Extractor`]: Pinterest's internal
+//! resources, the search resource, the data embedded in a page, or scanning its
+//! HTML. The [`Registry`] holds them in the order they're tried, which `config.json`
+//! can change and `--strategy` can narrow down to a few. The pipeline runs the
+//! extractors that can handle a source, in order, until one finds something, or
+//! with `--merge` runs them all; what they find is merged, without duplicates.
+//! Extractors stream what they find, so feeds are read a page at a time.
+
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::future::Future;
+use tracing::{info, info_span, warn, Instrument};
+use url::Url;
+
+use crate::boards::{stream_board, stream_profile, stream_section_images};
+use crate::download::DownloadOptions;
+use crate::error::{Error, Result};
+use crate::metrics::Outcome;
+use crate::pages::PinPage;
+use crate::resources::{self, PinMedia};
+use crate::scrape::{scrape_html, scrape_pin, try_direct_pinterest_extraction, try_fetch_from_modern_search};
+use crate::search::{self, Search};
+use crate::session::Session;
+use crate::source::PinterestSource;
+
+/// An image (or video) found by an extractor
+#[derive(Debug, Clone, Default)]
+pub struct PinCandidate {
+    pub url: String,
+    /// Subfolder of the collection the image goes in, e.g. for a board section
+    pub folder: Option<String>,
+    /// The pin this image was found as related to, with `--related`
+    pub related_to: Option<String>,
+    /// Id of the pin the image belongs to, when known
+    pub pin_id: Option<String>,
+    /// Which page of its pin the image is, for Idea and carousel pins
+    pub page: Option<PinPage>,
+}
+
+impl From<String> for PinCandidate {
+    fn from(url: String) -> Self {
+        Self { url, ..Default::default() }
+    }
+}
+
+impl From<PinMedia> for PinCandidate {
+    fn from(media: PinMedia) -> Self {
+        Self { url: media.url, pin_id: media.pin_id, page: media.page, ..Default::default() }
+    }
+}
+
+/// What to extract: a resolved URL, what kind of page it is, and the download's options
+pub struct Target<'a> {
+    pub url: &'a Url,
+    pub source: Option<&'a PinterestSource>,
+    pub options: &'a DownloadOptions,
+    /// The search to run, for search URLs, with the search options applied
+    pub search: Option<Search>,
+}
+
+impl<'a> Target<'a> {
+    pub fn new(url: &'a Url, source: Option<&'a PinterestSource>, options: &'a DownloadOptions) -> Self {
+        let search = match source {
+            Some(PinterestSource::Search { query, scope, filters }) => Some(Search::new(query, *scope, filters, &options.search)),
+            _ => None,
+        };
+        Self { url, source, options, search }
+    }
+
+    /// The page to scrape: the search page with the search options applied for a
+    /// search, or else the URL itself
+    pub fn page_url(&self) -> Result<Url> {
+        match &self.search {
+            Some(search) => search.page_url(self.url),
+            None => Ok(self.url.clone()),
+        }
+    }
+
+    /// Short name of the kind of page, for metrics
+    pub fn kind(&self) -> &'static str {
+        self.source.map(PinterestSource::kind).unwrap_or("page")
+    }
+}
+
+/// A way of finding the pins of a page
+pub trait Extractor: Send + Sync {
+    /// Name of the strategy, as used by `--strategy`, `config.json`, logs and metrics
+    fn name(&self) -> &'static str;
+
+    /// What the strategy does, for `strategies`
+    fn description(&self) -> &'static str;
+
+    /// Whether the strategy can extract from this kind of source (`None` for pages
+    /// that aren't a known kind of Pinterest source)
+    fn can_handle(&self, source: Option<&PinterestSource>) -> bool;
+
+    /// Finds the pins of `target`, yielding each as soon as it's found, e.g. a page of
+    /// a feed at a time
+    fn extract<'a>(&'a self, target: &'a Target<'a>, session: &'a Session) -> BoxStream<'a, Result<PinCandidate>>;
+}
+
+/// Streams the result of an extraction that finds everything at once, like scraping a
+/// single page
+fn all_at_once<'a, T: Into<PinCandidate> + Send + 'a>(
+    extraction: impl Future<Output = Result<Vec<T>>> + Send + 'a,
+) -> BoxStream<'a, Result<PinCandidate>> {
+    stream::once(extraction)
+        .map_ok(|found| stream::iter(found.into_iter().map(|candidate| Ok(candidate.into()))))
+        .try_flatten()
+        .boxed()
+}
+
+/// Pinterest's internal resources: board feeds (with sections), section feeds, the
+/// pin resource and a profile's boards
+struct ResourceExtractor;
+
+impl Extractor for ResourceExtractor {
+    fn name(&self) -> &'static str {
+        "resource"
+    }
+
+    fn description(&self) -> &'static str {
+        "Pinterest's internal resources, for boards, sections, pins and profiles"
+    }
+
+    fn can_handle(&self, source: Option<&PinterestSource>) -> bool {
+        matches!(
+            source,
+            Some(PinterestSource::Board { .. } | PinterestSource::Section { .. } | PinterestSource::Pin { .. } | PinterestSource::User { .. })
+        )
+    }
+
+    fn extract<'a>(&'a self, target: &'a Target<'a>, session: &'a Session) -> BoxStream<'a, Result<PinCandidate>> {
+        let options = target.options;
+        match target.source {
+            Some(PinterestSource::Board { user, slug }) => stream_board(user, slug, session, options),
+            Some(PinterestSource::Section { user, board, section }) => {
+                stream_section_images(target.url, user, board, section, session, options.max_images)
+            }
+            Some(PinterestSource::Pin { id }) => all_at_once(resources::fetch_pin(session, id)),
+            Some(PinterestSource::User { name }) => stream_profile(name, session, options),
+            _ => stream::empty().boxed(),
+        }
+    }
+}
+
+/// Pinterest's search resource
+struct SearchApiExtractor;
+
+impl Extractor for SearchApiExtractor {
+    fn name(&self) -> &'static str {
+        "api"
+    }
+
+    fn description(&self) -> &'static str {
+        "Pinterest's search resource, with the scope, refinements and filters asked for"
+    }
+
+    fn can_handle(&self, source: Option<&PinterestSource>) -> bool {
+        matches!(source, Some(PinterestSource::Search { .. }))
+    }
+
+    fn extract<'a>(&'a self, target: &'a Target<'a>, session: &'a Session) -> BoxStream<'a, Result<PinCandidate>> {
+        match &target.search {
+            Some(search) => search::stream_images(session, search, target.options.max_images),
+            None => stream::once(async { Err(Error::InvalidInput("Not a search".to_string())) }).boxed(),
+        }
+    }
+}
+
+/// Search pages opened from a module of Pinterest's web app, which carry its id
+struct ModernSearchExtractor;
+
+impl Extractor for ModernSearchExtractor {
+    fn name(&self) -> &'static str {
+        "modern_search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Image URLs in search pages that carry a source_module_id"
+    }
+
+    fn can_handle(&self, source: Option<&PinterestSource>) -> bool {
+        match source {
+            Some(PinterestSource::Search { filters, .. }) => filters.iter().any(|(key, _)| key == "source_module_id"),
+            _ => false,
+        }
+    }
+
+    fn extract<'a>(&'a self, target: &'a Target<'a>, session: &'a Session) -> BoxStream<'a, Result<PinCandidate>> {
+        all_at_once(async move { try_fetch_from_modern_search(&target.page_url()?, session).await })
+    }
+}
+
+/// A pin's own page: the pin data it embeds, or its Open Graph image
+struct PinPageExtractor;
+
+impl Extractor for PinPageExtractor {
+    fn name(&self) -> &'static str {
+        "pin_page"
+    }
+
+    fn description(&self) -> &'static str {
+        "The pin data embedded in a pin's page, or its Open Graph image"
+    }
+
+    fn can_handle(&self, source: Option<&PinterestSource>) -> bool {
+        matches!(source, Some(PinterestSource::Pin { .. }))
+    }
+
+    fn extract<'a>(&'a self, target: &'a Target<'a>, session: &'a Session) -> BoxStream<'a, Result<PinCandidate>> {
+        all_at_once(async move {
+            match target.source {
+                Some(PinterestSource::Pin { id }) => scrape_pin(target.url, id, session).await,
+                _ => Ok(Vec::new()),
+            }
+        })
+    }
+}
+
+/// Image URLs in the data a page embeds. Pin pages are left to `pin_page`, since
+/// the rest of a pin's page shows other pins.
+struct DirectExtractor;
+
+impl Extractor for DirectExtractor {
+    fn name(&self) -> &'static str {
+        "direct"
+    }
+
+    fn description(&self) -> &'static str {
+        "Image URLs in the data embedded in a page"
+    }
+
+    fn can_handle(&self, source: Option<&PinterestSource>) -> bool {
+        !matches!(source, Some(PinterestSource::Pin { .. }))
+    }
+
+    fn extract<'a>(&'a self, target: &'a Target<'a>, session: &'a Session) -> BoxStream<'a, Result<PinCandidate>> {
+        all_at_once(async move { try_direct_pinterest_extraction(&target.page_url()?, session).await })
+    }
+}
+
+/// Last resort: image URLs anywhere in a page's HTML
+struct HtmlExtractor;
+
+impl Extractor for HtmlExtractor {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn description(&self) -> &'static str {
+        "Image URLs in a page's scripts, image tags and raw HTML"
+    }
+
+    fn can_handle(&self, source: Option<&PinterestSource>) -> bool {
+        !matches!(source, Some(PinterestSource::Pin { .. }))
+    }
+
+    fn extract<'a>(&'a self, target: &'a Target<'a>, session: &'a Session) -> BoxStream<'a, Result<PinCandidate>> {
+        all_at_once(async move { scrape_html(&target.page_url()?, session, target.search.is_some()).await })
+    }
+}
+
+/// The extractors, in the order they're tried
+pub struct Registry {
+    extractors: Vec<Box<dyn Extractor>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self {
+            extractors: vec![
+                Box::new(ResourceExtractor),
+                Box::new(SearchApiExtractor),
+                Box::new(ModernSearchExtractor),
+                Box::new(PinPageExtractor),
+                Box::new(DirectExtractor),
+                Box::new(HtmlExtractor),
+            ],
+        }
+    }
+}
+
+/// Checks that a strategy name is one of the built-in extractors, for `--strategy`
+pub fn parse_strategy(name: &str) -> Result<String, String> {
+    let registry = Registry::default();
+    match registry.position(name) {
+        Ok(_) => Ok(name.to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+impl Registry {
+    /// The built-in extractors in the order from `config.json`, narrowed down to
+    /// `only` if that's not empty
+    pub fn configured(order: &[String], only: &[String]) -> Result<Self> {
+        let registry = Self::default().prefer(order)?;
+        if only.is_empty() {
+            Ok(registry)
+        } else {
+            registry.only(only)
+        }
+    }
+
+    /// The names of the extractors, in order
+    pub fn names(&self) -> Vec<&'static str> {
+        self.extractors.iter().map(|extractor| extractor.name()).collect()
+    }
+
+    /// The extractors, in order
+    pub fn extractors(&self) -> impl Iterator<Item = &dyn Extractor> {
+        self.extractors.iter().map(Box::as_ref)
+    }
+
+    fn position(&self, name: &str) -> Result<usize> {
+        self.extractors.iter().position(|extractor| extractor.name() == name).ok_or_else(|| {
+            Error::InvalidInput(format!("Unknown strategy '{}': expected one of {}", name, self.names().join(", ")))
+        })
+    }
+
+    /// Moves the named extractors to the front, in the order given
+    pub fn prefer(mut self, names: &[String]) -> Result<Self> {
+        for name in names.iter().rev() {
+            let index = self.position(name)?;
+            let extractor = self.extractors.remove(index);
+            self.extractors.insert(0, extractor);
+        }
+        Ok(self)
+    }
+
+    /// Keeps only the named extractors, in the order given
+    pub fn only(self, names: &[String]) -> Result<Self> {
+        let mut names = names.to_vec();
+        let mut seen = std::collections::HashSet::new();
+        names.retain(|name| seen.insert(name.clone()));
+        let mut registry = self.prefer(&names)?;
+        registry.extractors.truncate(names.len());
+        Ok(registry)
+    }
+
+    /// Runs the extractors that can handle the target, in order, until one finds
+    /// something, or all of them when `merge` is set. Returns what they found,
+    /// merged, and how each run went.
+    pub async fn run(&self, target: &Target<'_>, session: &Session, merge: bool) -> Result<Extraction> {
+        let extractors: Vec<&dyn Extractor> = self.extractors().filter(|extractor| extractor.can_handle(target.source)).collect();
+        if extractors.is_empty() {
+            return Err(Error::Unsupported(format!(
+                "None of the strategies used ({}) can extract from {}",
+                self.names().join(", "),
+                target.source.map(ToString::to_string).unwrap_or_else(|| target.url.to_string())
+            )));
+        }
+
+        let mut extraction = Extraction::default();
+        for (index, extractor) in extractors.iter().enumerate() {
+            let name = extractor.name();
+            let outcome = async {
+                info!(strategy = name, "Attempting {} extraction...", name);
+                let mut found = 0;
+                let mut candidates = extractor.extract(target, session);
+                while let Some(candidate) = candidates.next().await {
+                    match candidate {
+                        Ok(candidate) => {
+                            found += 1;
+                            extraction.add(candidate);
+                        }
+                        Err(e) => {
+                            let more = if index + 1 < extractors.len() { ", trying other methods" } else { "" };
+                            warn!(strategy = name, error = %e, "{} extraction failed{}", name, more);
+                            return Outcome::Failed(e.to_string());
+                        }
+                    }
+                }
+                info!(strategy = name, count = found, "Extracted {} images", found);
+                if found > 0 {
+                    Outcome::Found(found)
+                } else {
+                    Outcome::Empty
+                }
+            }
+            .instrument(info_span!("strategy", strategy = name))
+            .await;
+            extraction.runs.push((name, outcome));
+
+            if !merge && !extraction.candidates.is_empty() {
+                break;
+            }
+        }
+        Ok(extraction)
+    }
+}
+
+/// What the extractors of a source found
+#[derive(Default)]
+pub struct Extraction {
+    /// The images found, in the order they were found, without duplicates
+    pub candidates: Vec<PinCandidate>,
+    /// Each extractor that ran, with how it went
+    pub runs: Vec<(&'static str, Outcome)>,
+    seen: HashMap<String, usize>,
+}
+
+impl Extraction {
+    /// Adds a candidate, or if it was already found, keeps the larger of the two sizes
+    /// of the image and fills in what the earlier find didn't know about it
+    fn add(&mut self, candidate: PinCandidate) {
+        let key = merge_key(&candidate.url);
+        match self.seen.get(&key) {
+            Some(&index) => {
+                let existing = &mut self.candidates[index];
+                if size_rank(&candidate.url) > size_rank(&existing.url) {
+                    existing.url = candidate.url;
+                }
+                existing.pin_id = existing.pin_id.take().or(candidate.pin_id);
+                existing.page = existing.page.take().or(candidate.page);
+                existing.folder = existing.folder.take().or(candidate.folder);
+                existing.related_to = existing.related_to.take().or(candidate.related_to);
+            }
+            None => {
+                self.seen.insert(key, self.candidates.len());
+                self.candidates.push(candidate);
+            }
+        }
+    }
+}
+
+/// What makes two found URLs the same image: Pinterest serves each image at several
+/// sizes, `/originals/aa/bb/cc.jpg` and `/736x/aa/bb/cc.jpg`, which are one image
+pub(crate) fn merge_key(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) if parsed.host_str().is_some_and(|host| host.ends_with("pinimg.com")) => {
+            let mut segments = parsed.path_segments().into_iter().flatten();
+            let size = segments.next().unwrap_or_default();
+            let rest: Vec<&str> = segments.collect();
+            // Videos keep their whole path: the size segment is deeper, and the sizes differ in format
+            if size == "videos" || rest.is_empty() {
+                parsed.path().to_string()
+            } else {
+                rest.join("/")
+            }
+        }
+        _ => url.to_string(),
+    }
+}
+
+/// How large the image at a `pinimg.com` URL is: originals first, then by
+/// the width in the size segment, e.g. `/736x/`
+fn size_rank(url: &str) -> u32 {
+    let size = Url::parse(url).ok().and_then(|parsed| Some(parsed.path_segments()?.next()?.to_string()));
+    match size.as_deref() {
+        Some("originals") => u32::MAX,
+        Some(size) => {
+            let width: String = size.chars().take_while(char::is_ascii_digit).collect();
+            width.parse().unwrap_or(0)
+        }
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Finds a fixed list of URLs, or fails
+    struct Fixed(&'static str, Result<Vec<&'static str>, &'static str>);
+
+    impl Extractor for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn description(&self) -> &'static str {
+            "test"
+        }
+
+        fn can_handle(&self, source: Option<&PinterestSource>) -> bool {
+            source.is_none()
+        }
+
+        fn extract<'a>(&'a self, _: &'a Target<'a>, _: &'a Session) -> BoxStream<'a, Result<PinCandidate>> {
+            let result = self.1.clone().map(|urls| urls.into_iter().map(String::from).collect::<Vec<_>>());
+            all_at_once(async move { result.map_err(|e| Error::UnexpectedResponse(e.to_string())) })
+        }
+    }
+
+    fn registry(extractors: Vec<Fixed>) -> Registry {
+        Registry { extractors: extractors.into_iter().map(|extractor| Box::new(extractor) as Box<dyn Extractor>).collect() }
+    }
+
+    fn session() -> Session {
+        use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+        use std::sync::Arc;
+        Session::with_jar(Arc::new(CookieStoreMutex::new(CookieStore::default())), "test").unwrap()
+    }
+
+    #[tokio::test]
+    async fn falls_back_and_merges() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let options = DownloadOptions { url: url.to_string(), ..Default::default() };
+        let target = Target::new(&url, None, &options);
+        let session = session();
+        let extractors = || {
+            vec![
+                Fixed("broken", Err("HTTP 403")),
+                Fixed("empty", Ok(vec![])),
+                Fixed("first", Ok(vec!["https://i.pinimg.com/736x/aa/bb/one.jpg", "https://example.com/a.png"])),
+                Fixed("second", Ok(vec!["https://i.pinimg.com/originals/aa/bb/one.jpg", "https://i.pinimg.com/originals/aa/bb/two.jpg"])),
+            ]
+        };
+
+        let extraction = registry(extractors()).run(&target, &session, false).await.unwrap();
+        assert_eq!(extraction.candidates.len(), 2);
+        assert_eq!(
+            extraction.runs,
+            [("broken", Outcome::Failed("HTTP 403".into())), ("empty", Outcome::Empty), ("first", Outcome::Found(2))]
+        );
+
+        let extraction = registry(extractors()).run(&target, &session, true).await.unwrap();
+        let urls: Vec<&str> = extraction.candidates.iter().map(|candidate| candidate.url.as_str()).collect();
+        // The original found by a later strategy replaces the smaller size found first
+        assert_eq!(urls, [
+            "https://i.pinimg.com/originals/aa/bb/one.jpg",
+            "https://example.com/a.png",
+            "https://i.pinimg.com/originals/aa/bb/two.jpg",
+        ]);
+        assert_eq!(extraction.runs.len(), 4);
+    }
+
+    #[test]
+    fn orders_and_narrows_down_strategies() {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        let registry = Registry::configured(&names(&["html", "direct"]), &[]).unwrap();
+        assert_eq!(registry.names(), ["html", "direct", "resource", "api", "modern_search", "pin_page"]);
+
+        let registry = Registry::configured(&names(&["html"]), &names(&["direct", "resource", "direct"])).unwrap();
+        assert_eq!(registry.names(), ["direct", "resource"]);
+
+        assert!(Registry::configured(&names(&["nope"]), &[]).is_err());
+        assert!(parse_strategy("pin_page").is_ok());
+        assert!(parse_strategy("scrape").unwrap_err().contains("expected one of resource, api"));
+    }
+
+    #[test]
+    fn strategies_handle_their_sources() {
+        let pin = PinterestSource::Pin { id: "1".into() };
+        let board = PinterestSource::Board { user: "a".into(), slug: "b".into() };
+        let handling = |source: Option<&PinterestSource>| -> Vec<&str> {
+            Registry::default().extractors().filter(|e| e.can_handle(source)).map(|e| e.name()).collect()
+        };
+        assert_eq!(handling(Some(&pin)), ["resource", "pin_page"]);
+        assert_eq!(handling(Some(&board)), ["resource", "direct", "html"]);
+        assert_eq!(handling(None), ["direct", "html"]);
+    }
+
+    #[test]
+    fn merges_sizes_of_one_image() {
+        assert_eq!(merge_key("https://i.pinimg.com/originals/aa/bb/cc.jpg"), merge_key("https://i.pinimg.com/236x/aa/bb/cc.jpg"));
+        assert_ne!(merge_key("https://i.pinimg.com/originals/aa/bb/cc.jpg"), merge_key("https://i.pinimg.com/originals/aa/bb/dd.jpg"));
+        assert_ne!(
+            merge_key("https://v1.pinimg.com/videos/mc/720p/aa/bb/cc.mp4"),
+            merge_key("https://v1.pinimg.com/videos/mc/hls/aa/bb/cc.m3u8")
+        );
+        assert_eq!(merge_key("https://example.com/a.png"), "https://example.com/a.png");
+
+        let mut extraction = Extraction::default();
+        for url in ["236x", "736x", "474x", "75x75_RS", "originals", "1200x"] {
+            extraction.add(format!("https://i.pinimg.com/{}/aa/bb/cc.jpg", url).into());
+        }
+        let urls: Vec<&str> = extraction.candidates.iter().map(|candidate| candidate.url.as_str()).collect();
+        assert_eq!(urls, ["https://i.pinimg.com/originals/aa/bb/cc.jpg"]);
+    }
+}
@@ -0,0 +1,264 @@
+//! File formats of downloaded media.
+//!
+//! What a URL's extension says isn't always what comes back: Pinterest may serve a
+//! WebP at a `.jpg` URL, and a request that runs into a login wall or an error page
+//! gets HTML, sometimes with status 200. So before a download is saved, its
+//! `Content-Type` is checked and its format is sniffed from its first bytes, and it's
+//! saved with the extension of the format it really is. With the `verify-images`
+//! feature, still images are also decoded in full, which catches truncated files.
+
+use url::Url;
+
+use crate::error::{Error, Result};
+
+/// The image and video formats Pinterest serves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaFormat {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Avif,
+    Mp4,
+    Mov,
+    Webm,
+    /// MPEG transport stream, the segments of most HLS videos
+    MpegTs,
+}
+
+/// Size of an MPEG transport stream packet, each of which starts with a sync byte
+const TS_PACKET_LEN: usize = 188;
+
+/// Major brands of the `ftyp` box of MP4 and M4V videos
+const MP4_BRANDS: &[&[u8]] = &[
+    b"isom", b"iso2", b"iso4", b"iso5", b"iso6", b"mp41", b"mp42", b"avc1", b"dash",
+    b"M4V ", b"M4VH", b"M4VP", b"mmp4",
+];
+
+impl MediaFormat {
+    /// Every format, in the order they're declared
+    pub const ALL: [MediaFormat; 9] = [
+        MediaFormat::Jpeg,
+        MediaFormat::Png,
+        MediaFormat::Gif,
+        MediaFormat::Webp,
+        MediaFormat::Avif,
+        MediaFormat::Mp4,
+        MediaFormat::Mov,
+        MediaFormat::Webm,
+        MediaFormat::MpegTs,
+    ];
+
+    /// Recognizes a format by the signature at the start of a file
+    pub fn sniff(content: &[u8]) -> Option<Self> {
+        match content {
+            [0xFF, 0xD8, 0xFF, ..] => Some(MediaFormat::Jpeg),
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n', ..] => Some(MediaFormat::Png),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(MediaFormat::Gif),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(MediaFormat::Webp),
+            [_, _, _, _, b'f', b't', b'y', b'p', brand @ ..] => match brand.get(..4)? {
+                b"avif" | b"avis" => Some(MediaFormat::Avif),
+                b"qt  " => Some(MediaFormat::Mov),
+                brand if MP4_BRANDS.contains(&brand) => Some(MediaFormat::Mp4),
+                // HEIF images (`heic`, `mif1`, `msf1`...) and other ISO media files
+                // aren't among the formats Pinterest serves
+                _ => None,
+            },
+            [0x1A, 0x45, 0xDF, 0xA3, ..] => Some(MediaFormat::Webm),
+            // A lone 'G' says little, so a transport stream needs the sync bytes of two packets
+            [0x47, ..] if content.get(TS_PACKET_LEN) == Some(&0x47) => Some(MediaFormat::MpegTs),
+            _ => None,
+        }
+    }
+
+    /// Extension files of this format are saved with
+    pub fn extension(self) -> &'static str {
+        match self {
+            MediaFormat::Jpeg => "jpg",
+            MediaFormat::Png => "png",
+            MediaFormat::Gif => "gif",
+            MediaFormat::Webp => "webp",
+            MediaFormat::Avif => "avif",
+            MediaFormat::Mp4 => "mp4",
+            MediaFormat::Mov => "mov",
+            MediaFormat::Webm => "webm",
+            MediaFormat::MpegTs => "ts",
+        }
+    }
+
+    /// MIME type files of this format are served with
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            MediaFormat::Jpeg => "image/jpeg",
+            MediaFormat::Png => "image/png",
+            MediaFormat::Gif => "image/gif",
+            MediaFormat::Webp => "image/webp",
+            MediaFormat::Avif => "image/avif",
+            MediaFormat::Mp4 => "video/mp4",
+            MediaFormat::Mov => "video/quicktime",
+            MediaFormat::Webm => "video/webm",
+            MediaFormat::MpegTs => "video/mp2t",
+        }
+    }
+
+    /// Whether this is a video format; animated GIFs count as images
+    pub fn is_video(self) -> bool {
+        matches!(self, MediaFormat::Mp4 | MediaFormat::Mov | MediaFormat::Webm | MediaFormat::MpegTs)
+    }
+
+    /// The format of a file saved with `extension`, if it's one the downloader writes
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.extension().eq_ignore_ascii_case(extension))
+    }
+}
+
+/// Checks the `Content-Type` of a media response before its body is read: images,
+/// videos and untyped binary data pass, anything else (HTML, JSON, text) is the
+/// reason it's rejected
+pub fn check_content_type(content_type: Option<&str>) -> Result<(), String> {
+    let Some(content_type) = content_type else { return Ok(()) };
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    let is_media = mime.starts_with("image/")
+        || mime.starts_with("video/")
+        || matches!(mime.as_str(), "" | "application/octet-stream" | "binary/octet-stream" | "application/mp4");
+    if is_media {
+        Ok(())
+    } else {
+        Err(format!("served as {}", mime))
+    }
+}
+
+/// Checks that a download is an image or video, returning its format
+pub fn validate(url: &Url, content: &[u8]) -> Result<MediaFormat> {
+    let format = MediaFormat::sniff(content)
+        .ok_or_else(|| Error::NotMedia { url: url.to_string(), reason: describe_payload(content) })?;
+    #[cfg(feature = "verify-images")]
+    decode(url, format, content)?;
+    Ok(format)
+}
+
+/// Decodes a still image in full; other formats aren't checked beyond their signature
+#[cfg(feature = "verify-images")]
+fn decode(url: &Url, format: MediaFormat, content: &[u8]) -> Result<()> {
+    let image_format = match format {
+        MediaFormat::Jpeg => image::ImageFormat::Jpeg,
+        MediaFormat::Png => image::ImageFormat::Png,
+        MediaFormat::Gif => image::ImageFormat::Gif,
+        MediaFormat::Webp => image::ImageFormat::WebP,
+        _ => return Ok(()),
+    };
+    image::load_from_memory_with_format(content, image_format).map_err(|e| Error::NotMedia {
+        url: url.to_string(),
+        reason: format!("{} data doesn't decode: {}", format.extension().to_uppercase(), e),
+    })?;
+    Ok(())
+}
+
+/// Says what a payload that isn't media looks like, for the error
+fn describe_payload(content: &[u8]) -> String {
+    let start = String::from_utf8_lossy(&content[..content.len().min(64)]).trim_start().to_ascii_lowercase();
+    if content.is_empty() {
+        "the response is empty".to_string()
+    } else if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        "got an HTML page instead, probably a login wall or an error page".to_string()
+    } else if start.starts_with('{') || start.starts_with('[') {
+        "got JSON instead".to_string()
+    } else {
+        let head: Vec<String> = content.iter().take(8).map(|byte| format!("{:02x}", byte)).collect();
+        format!("unknown format starting with {}", head.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_formats_from_signatures() {
+        let table: &[(&[u8], Option<MediaFormat>)] = &[
+            (b"\xff\xd8\xff\xe0\0\x10JFIF", Some(MediaFormat::Jpeg)),
+            (b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", Some(MediaFormat::Png)),
+            (b"GIF89a\x01\0", Some(MediaFormat::Gif)),
+            (b"GIF87a\x01\0", Some(MediaFormat::Gif)),
+            (b"RIFF\x24\0\0\0WEBPVP8 ", Some(MediaFormat::Webp)),
+            (b"RIFF\x24\0\0\0WAVEfmt ", None),
+            (b"\0\0\0\x1cftypavif\0\0\0\0", Some(MediaFormat::Avif)),
+            (b"\0\0\0\x18ftypmp42\0\0\0\0", Some(MediaFormat::Mp4)),
+            (b"\0\0\0\x18ftypiso6\0\0\0\0", Some(MediaFormat::Mp4)),
+            (b"\0\0\0\x14ftypqt  \0\0\0\0", Some(MediaFormat::Mov)),
+            (b"\0\0\0\x18ftypisom\0\0\x02\0", Some(MediaFormat::Mp4)),
+            (b"\0\0\0\x1cftypM4V \0\0\0\x01", Some(MediaFormat::Mp4)),
+            (b"\0\0\0\x18ftypheic\0\0\0\0", None),
+            (b"\0\0\0\x18ftypmif1\0\0\0\0", None),
+            (b"\0\0\0\x18ftypmsf1\0\0\0\0", None),
+            (b"\0\0\0\x18ftypM4A \0\0\0\0", None),
+            (b"\0\0\0\x08ftyp", None),
+            (b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81", Some(MediaFormat::Webm)),
+            (b"<!DOCTYPE html><html>", None),
+            (b"Gone", None),
+            (b"GET / HTTP/1.1\r\n", None),
+            (b"", None),
+        ];
+        for (content, expected) in table {
+            assert_eq!(MediaFormat::sniff(content), *expected, "{:?}", String::from_utf8_lossy(content));
+        }
+
+        let mut ts = vec![0u8; TS_PACKET_LEN * 2];
+        ts[0] = 0x47;
+        ts[TS_PACKET_LEN] = 0x47;
+        assert_eq!(MediaFormat::sniff(&ts), Some(MediaFormat::MpegTs));
+        ts[TS_PACKET_LEN] = 0;
+        assert_eq!(MediaFormat::sniff(&ts), None);
+        // Nor is a body too short to hold a second packet
+        assert_eq!(MediaFormat::sniff(&ts[..TS_PACKET_LEN]), None);
+    }
+
+    #[test]
+    fn maps_extensions_back_to_formats() {
+        for format in MediaFormat::ALL {
+            assert_eq!(MediaFormat::from_extension(format.extension()), Some(format));
+        }
+        assert_eq!(MediaFormat::from_extension("JPG"), Some(MediaFormat::Jpeg));
+        assert_eq!(MediaFormat::from_extension("part"), None);
+        assert_eq!(MediaFormat::from_extension("json"), None);
+        for format in MediaFormat::ALL {
+            let kind = if format.is_video() { "video/" } else { "image/" };
+            assert!(format.mime_type().starts_with(kind), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn accepts_only_media_content_types() {
+        for content_type in [None, Some("image/jpeg"), Some("image/webp"), Some("video/mp4"), Some("application/octet-stream")] {
+            assert_eq!(check_content_type(content_type), Ok(()), "{:?}", content_type);
+        }
+        assert_eq!(check_content_type(Some("text/html; charset=utf-8")), Err("served as text/html".to_string()));
+        assert_eq!(check_content_type(Some("Application/JSON")), Err("served as application/json".to_string()));
+    }
+
+    #[test]
+    fn explains_what_was_served_instead() {
+        let url = Url::parse("https://i.pinimg.com/originals/aa/bb/cc.jpg").unwrap();
+        let reason = |content: &[u8]| match validate(&url, content) {
+            Err(Error::NotMedia { reason, .. }) => reason,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(reason(b"\n  <!doctype html><title>Log in</title>"), "got an HTML page instead, probably a login wall or an error page");
+        assert_eq!(reason(br#"{"error":"not found"}"#), "got JSON instead");
+        assert_eq!(reason(b""), "the response is empty");
+        assert_eq!(reason(b"\x01\x02\x03"), "unknown format starting with 01 02 03");
+    }
+
+    #[cfg(feature = "verify-images")]
+    #[test]
+    fn rejects_truncated_images() {
+        let url = Url::parse("https://i.pinimg.com/originals/aa/bb/cc.png").unwrap();
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(4, 4).write_to(&mut png, image::ImageFormat::Png).unwrap();
+        let png = png.into_inner();
+        assert_eq!(validate(&url, &png).unwrap(), MediaFormat::Png);
+
+        let error = validate(&url, &png[..png.len() / 2]).unwrap_err();
+        assert!(error.to_string().contains("PNG data doesn't decode"), "{}", error);
+    }
+}
@@ -0,0 +1,77 @@
+//! Download images, videos and Idea pins from Pinterest.
+//!
+//! `pinterestx` finds the media of a Pinterest board, section, pin, user profile or
+//! search and saves it to disk. It's the library behind the `pinterest_downloader`
+//! command-line tool and web interface, and can be embedded in other programs.
+//!
+//! The main pieces:
+//!
+//! - [`Session`]: the HTTP client and cookie jar of a Pinterest account, which every
+//!   request goes through.
+//! - [`Registry`]: the extraction strategies ([`Extractor`]s) and the pipeline that
+//!   runs them on a [`Target`], producing [`PinCandidate`]s.
+//! - [`download`]: the whole run, from URL to files on disk, configured with
+//!   [`DownloadOptions`] and summed up in a [`DownloadSummary`].
+//!
+//! Every fallible operation returns the typed [`Error`].
+//!
+//! ```no_run
+//! use pinterestx::progress::ProgressEvent;
+//! use pinterestx::{download, DownloadOptions, Session};
+//!
+//! # async fn run() -> pinterestx::Result<()> {
+//! let session = Session::load("default")?;
+//! let options = DownloadOptions {
+//!     url: "https://www.pinterest.com/pinterest/official-news/".to_string(),
+//!     genre: Some("News".to_string()),
+//!     max_images: 20,
+//!     ..Default::default()
+//! };
+//! let summary = download(&options, &session, |event| {
+//!     if let ProgressEvent::Saved { filename, .. } = event {
+//!         println!("Saved {}", filename);
+//!     }
+//! })
+//! .await?;
+//! println!("Saved {} files to {}", summary.files.len(), summary.output_dir.display());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With the `clap` feature, the option types derive `clap` argument parsing, so a
+//! command-line front end can flatten them into its own commands. With the
+//! `verify-images` feature, downloaded images are decoded in full before they're
+//! saved (see [`formats`]).
+
+pub mod accounts;
+pub mod authenticated;
+pub mod boards;
+pub mod config;
+pub mod consent;
+pub mod cookies;
+pub mod diagnostics;
+pub mod download;
+pub mod encryption;
+pub mod error;
+pub mod extract;
+pub mod formats;
+pub mod media;
+pub mod metrics;
+pub mod pages;
+pub mod paths;
+pub mod progress;
+pub mod request;
+pub mod resolve;
+pub mod resources;
+pub mod scrape;
+pub mod search;
+pub mod session;
+pub mod source;
+#[cfg(test)]
+pub(crate) mod test_support;
+
+pub use download::{download, DownloadOptions, DownloadSummary};
+pub use error::{Error, Result};
+pub use extract::{Extractor, PinCandidate, Registry, Target};
+pub use session::Session;
+pub use source::PinterestSource;
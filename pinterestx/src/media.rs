@@ -0,0 +1,270 @@
+//! Videos and animated GIFs.
+//!
+//! Video pins carry a list of encodings: progressive MP4s at several sizes and HLS
+//! playlists (`.m3u8` on `v.pinimg.com`). The largest MP4 is preferred; when a pin
+//! only has a playlist, its segments are fetched and joined into one file as they
+//! are, without remuxing them into another container. GIF pins
+//! show a static JPEG thumbnail in their image renditions, so the animated original
+//! is read from the pin's embed instead.
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use tracing::debug;
+use url::Url;
+
+use crate::error::{Error, Result};
+use crate::formats::MediaFormat;
+
+/// What kind of media a URL points to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Gif,
+    Video,
+}
+
+impl MediaKind {
+    /// Guesses the kind of media from the extension of a URL's path
+    pub fn of(url: &str) -> Self {
+        let path = Url::parse(url).map(|url| url.path().to_string()).unwrap_or_else(|_| url.to_string());
+        match Path::new(&path).extension().and_then(|ext| ext.to_str()).map(str::to_lowercase).as_deref() {
+            Some("mp4" | "m3u8" | "mov" | "webm") => MediaKind::Video,
+            Some("gif") => MediaKind::Gif,
+            _ => MediaKind::Image,
+        }
+    }
+}
+
+/// Which kinds of media a download keeps
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum MediaFilter {
+    /// Still images and animated GIFs
+    Images,
+    /// Videos only
+    Videos,
+    /// Everything
+    #[default]
+    All,
+}
+
+impl MediaFilter {
+    pub fn includes(self, kind: MediaKind) -> bool {
+        match self {
+            MediaFilter::Images => kind != MediaKind::Video,
+            MediaFilter::Videos => kind == MediaKind::Video,
+            MediaFilter::All => true,
+        }
+    }
+}
+
+/// The best video of a pin: its largest MP4, or else its HLS playlist
+pub fn best_video(pin: &Value) -> Option<String> {
+    let list = pin
+        .pointer("/videos/video_list")
+        .or_else(|| pin.pointer("/story_pin_data/pages/0/blocks/0/video/video_list"))?;
+    best_encoding(list)
+}
+
+/// The best entry of a video's list of encodings
+pub fn best_encoding(video_list: &Value) -> Option<String> {
+    let list = video_list.as_object()?;
+    let variants = || list.values().filter_map(|video| Some((video["width"].as_u64().unwrap_or(0), video["url"].as_str()?)));
+
+    variants()
+        .filter(|(_, url)| MediaKind::of(url) == MediaKind::Video && !is_playlist(url))
+        .max_by_key(|(width, _)| *width)
+        .or_else(|| variants().filter(|(_, url)| is_playlist(url)).max_by_key(|(width, _)| *width))
+        .map(|(_, url)| url.to_string())
+}
+
+/// The animated original of a GIF pin, whose image renditions are static thumbnails
+pub fn animated_gif(pin: &Value) -> Option<String> {
+    let embed = &pin["embed"];
+    let src = embed["src"].as_str()?;
+    (embed["type"].as_str() == Some("gif") || MediaKind::of(src) == MediaKind::Gif).then(|| src.to_string())
+}
+
+/// Whether a URL is an HLS playlist
+pub fn is_playlist(url: &str) -> bool {
+    Url::parse(url).map(|url| url.path().ends_with(".m3u8")).unwrap_or(false)
+}
+
+/// Downloads the stream of an HLS playlist as a single file, returning its content
+/// and its format. A master playlist is resolved to its highest-bandwidth variant
+/// first. The segments are joined, not remuxed: MPEG-TS segments make an MPEG
+/// transport stream (`.ts`), which players like VLC and mpv play but most browsers
+/// don't, and fMP4 segments after their initialization segment a fragmented `.mp4`.
+pub async fn download_hls(client: &Client, url: &Url, mut on_chunk: impl FnMut(u64, u64)) -> Result<(Vec<u8>, MediaFormat)> {
+    let mut playlist_url = url.clone();
+    let mut playlist = fetch_text(client, &playlist_url).await?;
+    if let Some(variant) = best_variant(&playlist) {
+        playlist_url = playlist_url.join(&variant)?;
+        debug!(variant = %playlist_url, "Picked stream variant");
+        playlist = fetch_text(client, &playlist_url).await?;
+    }
+
+    let segments = media_segments(&playlist)?;
+    if segments.is_empty() {
+        return Err(Error::Media(format!("Playlist {} has no segments", playlist_url)));
+    }
+    let fragmented = segments.first().is_some_and(|(_, init)| *init);
+    debug!(count = segments.len(), "Fetching video segments");
+
+    let mut content = Vec::new();
+    for (segment, _) in &segments {
+        let mut response = client.get(playlist_url.join(segment)?).send().await?.error_for_status()?;
+        while let Some(chunk) = response.chunk().await? {
+            content.extend_from_slice(&chunk);
+            on_chunk(content.len() as u64, chunk.len() as u64);
+        }
+    }
+    Ok((content, if fragmented { MediaFormat::Mp4 } else { MediaFormat::MpegTs }))
+}
+
+async fn fetch_text(client: &Client, url: &Url) -> Result<String> {
+    let text = client.get(url.clone()).send().await?.error_for_status()?.text().await?;
+    if !text.trim_start().starts_with("#EXTM3U") {
+        return Err(Error::Media(format!("{} is not an HLS playlist", url)));
+    }
+    Ok(text)
+}
+
+/// The URI of the highest-bandwidth stream of a master playlist; `None` for a media playlist
+fn best_variant(playlist: &str) -> Option<String> {
+    let mut lines = playlist.lines().map(str::trim);
+    let mut best: Option<(u64, String)> = None;
+    while let Some(line) = lines.next() {
+        let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") else { continue };
+        let bandwidth = attribute(attributes, "BANDWIDTH").and_then(|b| b.parse().ok()).unwrap_or(0);
+        let Some(uri) = lines.find(|line| !line.is_empty() && !line.starts_with('#')) else { break };
+        if best.as_ref().is_none_or(|(max, _)| bandwidth > *max) {
+            best = Some((bandwidth, uri.to_string()));
+        }
+    }
+    best.map(|(_, uri)| uri)
+}
+
+/// The segment URIs of a media playlist, in order, each flagged if it's an
+/// initialization segment
+fn media_segments(playlist: &str) -> Result<Vec<(String, bool)>> {
+    let mut segments = Vec::new();
+    for line in playlist.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+            if attribute(attributes, "METHOD").is_some_and(|method| method != "NONE") {
+                return Err(Error::Media("Encrypted video streams aren't supported".to_string()));
+            }
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-MAP:") {
+            if let Some(uri) = attribute(attributes, "URI") {
+                segments.push((uri.to_string(), true));
+            }
+        } else if !line.starts_with('#') {
+            segments.push((line.to_string(), false));
+        }
+    }
+    Ok(segments)
+}
+
+/// The value of an attribute in an HLS tag's attribute list, without quotes
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes;
+    while !rest.is_empty() {
+        let (key, value) = rest.split_once('=')?;
+        let (value, next) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], quoted[end + 1..].trim_start_matches(','))
+            }
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        if key.trim() == name {
+            return Some(value);
+        }
+        rest = next;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn classifies_urls() {
+        assert_eq!(MediaKind::of("https://v1.pinimg.com/videos/mc/720p/aa/bb/cc.mp4"), MediaKind::Video);
+        assert_eq!(MediaKind::of("https://v.pinimg.com/videos/mc/hls/aa/bb/cc.m3u8?x=1"), MediaKind::Video);
+        assert_eq!(MediaKind::of("https://i.pinimg.com/originals/aa/bb/cc.gif"), MediaKind::Gif);
+        assert_eq!(MediaKind::of("https://i.pinimg.com/originals/aa/bb/cc.jpg"), MediaKind::Image);
+        assert!(MediaFilter::Images.includes(MediaKind::Gif));
+        assert!(!MediaFilter::Images.includes(MediaKind::Video));
+        assert!(!MediaFilter::Videos.includes(MediaKind::Image));
+    }
+
+    #[test]
+    fn picks_largest_mp4_before_playlists() {
+        let pin = json!({ "videos": { "video_list": {
+            "V_HLSV4": { "width": 1080, "url": "https://v.pinimg.com/videos/hls/a.m3u8" },
+            "V_720P": { "width": 720, "url": "https://v.pinimg.com/videos/720p/a.mp4" },
+            "V_EXP4": { "width": 480, "url": "https://v.pinimg.com/videos/exp4/a.mp4" },
+        }}});
+        assert_eq!(best_video(&pin).as_deref(), Some("https://v.pinimg.com/videos/720p/a.mp4"));
+
+        let hls_only = json!({ "videos": { "video_list": {
+            "V_HLSV3_MOBILE": { "width": 360, "url": "https://v.pinimg.com/videos/hls/b.m3u8" },
+        }}});
+        assert_eq!(best_video(&hls_only).as_deref(), Some("https://v.pinimg.com/videos/hls/b.m3u8"));
+        assert_eq!(best_video(&json!({ "images": {} })), None);
+    }
+
+    #[test]
+    fn finds_animated_gifs() {
+        let pin = json!({ "embed": { "type": "gif", "src": "https://i.pinimg.com/originals/aa/bb/cc.gif" } });
+        assert_eq!(animated_gif(&pin).as_deref(), Some("https://i.pinimg.com/originals/aa/bb/cc.gif"));
+        assert_eq!(animated_gif(&json!({ "embed": null })), None);
+    }
+
+    #[test]
+    fn parses_playlists() {
+        let master = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,CODECS=\"avc1.4d401e,mp4a.40.2\"\n\
+            low/index.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2400000,RESOLUTION=1280x720\n\
+            high/index.m3u8\n";
+        assert_eq!(best_variant(master).as_deref(), Some("high/index.m3u8"));
+
+        let media = "#EXTM3U\n#EXT-X-MAP:URI=\"init.mp4\"\n#EXTINF:4.0,\nseg1.m4s\n#EXTINF:4.0,\nseg2.m4s\n#EXT-X-ENDLIST\n";
+        assert_eq!(best_variant(media), None);
+        let segments = media_segments(media).unwrap();
+        assert_eq!(segments, [("init.mp4".to_string(), true), ("seg1.m4s".to_string(), false), ("seg2.m4s".to_string(), false)]);
+
+        assert!(media_segments("#EXTM3U\n#EXT-X-KEY:METHOD=AES-128,URI=\"k\"\nseg.ts\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn joins_hls_segments() {
+        use crate::test_support;
+        use axum::routing::get;
+        use axum::Router;
+
+        let app = Router::new()
+            .route("/master.m3u8", get(|| async { "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1000\nhls/720.m3u8\n" }))
+            .route("/hls/720.m3u8", get(|| async { "#EXTM3U\n#EXTINF:4.0,\na.ts\n#EXTINF:4.0,\nb.ts\n#EXT-X-ENDLIST\n" }))
+            .route("/hls/a.ts", get(|| async { "first" }))
+            .route("/hls/b.ts", get(|| async { "second" }))
+            .route("/fmp4.m3u8", get(|| async { "#EXTM3U\n#EXT-X-MAP:URI=\"init.mp4\"\n#EXTINF:4.0,\nhls/a.ts\n" }))
+            .route("/init.mp4", get(|| async { "init" }));
+        let base = test_support::serve(app).await;
+
+        let (content, format) = download_hls(&Client::new(), &base.join("master.m3u8").unwrap(), |_, _| {}).await.unwrap();
+        assert_eq!(content, b"firstsecond");
+        assert_eq!(format, MediaFormat::MpegTs);
+
+        let (content, format) = download_hls(&Client::new(), &base.join("fmp4.m3u8").unwrap(), |_, _| {}).await.unwrap();
+        assert_eq!(content, b"initfirst");
+        assert_eq!(format, MediaFormat::Mp4);
+    }
+}
@@ -0,0 +1,197 @@
+//! How well each extraction strategy works.
+//!
+//! Every run of a strategy is counted in `strategy_stats.json` in the application
+//! data directory, per kind of source: how often it found pins, found nothing or
+//! failed, and when it last worked. When Pinterest changes its pages, `strategies`
+//! shows which approaches stopped working, and for which kind of page.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+use crate::error::{Error, Result};
+use crate::paths::{get_app_data_dir, write_file_atomically};
+
+/// Serializes updates of the metrics file by concurrent downloads
+static METRICS_LOCK: Mutex<()> = Mutex::new(());
+
+/// How one run of a strategy went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Found this many pins
+    Found(usize),
+    /// Ran without error but found nothing
+    Empty,
+    /// Failed with this error
+    Failed(String),
+}
+
+/// Counters of one strategy on one kind of source
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct StrategyStats {
+    pub runs: u64,
+    pub found: u64,
+    pub empty: u64,
+    pub failed: u64,
+    /// Pins found over all runs
+    pub pins: u64,
+    /// Unix time of the last run that found pins
+    pub last_success: Option<u64>,
+    /// Unix time and error of the last failed run
+    pub last_failure: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+/// Counters of every strategy, by strategy name and then kind of source
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct StrategyMetrics {
+    pub strategies: BTreeMap<String, BTreeMap<String, StrategyStats>>,
+}
+
+fn get_metrics_path() -> PathBuf {
+    get_app_data_dir().join("strategy_stats.json")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl StrategyMetrics {
+    /// Loads the recorded metrics; none if there's no file yet
+    pub fn load() -> Result<Self> {
+        let path = get_metrics_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(&std::fs::read_to_string(&path)?)
+            .map_err(|e| Error::Config { what: "strategy metrics", path: path.clone(), message: e.to_string() })
+    }
+
+    fn save(&self) -> Result<()> {
+        write_file_atomically(&get_metrics_path(), &serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Counts one run of `strategy` on a `kind` of source, made at unix time `at`
+    pub fn record(&mut self, strategy: &str, kind: &str, outcome: &Outcome, at: u64) {
+        let stats = self.strategies.entry(strategy.to_string()).or_default().entry(kind.to_string()).or_default();
+        stats.runs += 1;
+        match outcome {
+            Outcome::Found(count) => {
+                stats.found += 1;
+                stats.pins += *count as u64;
+                stats.last_success = Some(at);
+            }
+            Outcome::Empty => stats.empty += 1,
+            Outcome::Failed(error) => {
+                stats.failed += 1;
+                stats.last_failure = Some(at);
+                stats.last_error = Some(error.clone());
+            }
+        }
+    }
+
+    /// Renders the counters as a table, strategies in the given order
+    pub fn render(&self, order: &[&str], at: u64) -> String {
+        let ago = |time: Option<u64>| match time {
+            Some(time) => format!("{} ago", human_duration(Duration::from_secs(at.saturating_sub(time)))),
+            None => "never".to_string(),
+        };
+        let mut out = format!(
+            "  {:<14} {:<8} {:>6} {:>6} {:>6} {:>6} {:>8}  {}\n",
+            "STRATEGY", "SOURCE", "RUNS", "FOUND", "EMPTY", "FAILED", "PINS", "LAST WORKED"
+        );
+        for name in order {
+            for (kind, stats) in self.strategies.get(*name).into_iter().flatten() {
+                out.push_str(&format!(
+                    "  {:<14} {:<8} {:>6} {:>6} {:>6} {:>6} {:>8}  {}\n",
+                    name,
+                    kind,
+                    stats.runs,
+                    stats.found,
+                    stats.empty,
+                    stats.failed,
+                    stats.pins,
+                    ago(stats.last_success)
+                ));
+                if let (Some(error), true) = (&stats.last_error, stats.last_failure > stats.last_success) {
+                    out.push_str(&format!("  {:<14} last error: {}\n", "", error));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A duration in its largest whole unit, e.g. "3 minutes"
+fn human_duration(duration: Duration) -> String {
+    const UNITS: [(&str, u64); 6] =
+        [("year", 365 * 86400), ("week", 7 * 86400), ("day", 86400), ("hour", 3600), ("minute", 60), ("second", 1)];
+    let seconds = duration.as_secs();
+    let (unit, size) = UNITS.iter().copied().find(|(_, size)| seconds >= *size).unwrap_or(("second", 1));
+    let count = seconds / size;
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// Adds the runs of an extraction to the metrics file; failing to is only logged,
+/// since the download itself went fine
+pub fn record_runs(kind: &str, runs: &[(&str, Outcome)]) {
+    if runs.is_empty() {
+        return;
+    }
+    let _guard = METRICS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let result = StrategyMetrics::load().and_then(|mut metrics| {
+        let at = now();
+        for (strategy, outcome) in runs {
+            metrics.record(strategy, kind, outcome, at);
+        }
+        metrics.save()
+    });
+    if let Err(e) = result {
+        warn!(error = %e, "Failed to record strategy metrics");
+    }
+}
+
+/// Forgets every recorded run
+pub fn reset() -> Result<()> {
+    let _guard = METRICS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = get_metrics_path();
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_runs_per_strategy_and_source() {
+        let mut metrics = StrategyMetrics::default();
+        metrics.record("resource", "board", &Outcome::Found(40), 100);
+        metrics.record("resource", "board", &Outcome::Failed("HTTP 403".into()), 200);
+        metrics.record("resource", "pin", &Outcome::Found(1), 150);
+        metrics.record("html", "board", &Outcome::Empty, 200);
+
+        let board = &metrics.strategies["resource"]["board"];
+        assert_eq!((board.runs, board.found, board.failed, board.pins), (2, 1, 1, 40));
+        assert_eq!(board.last_success, Some(100));
+        assert_eq!(board.last_error.as_deref(), Some("HTTP 403"));
+        assert_eq!(metrics.strategies["html"]["board"].empty, 1);
+
+        let table = metrics.render(&["resource", "html"], 300);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("  resource       board"));
+        assert!(lines[2].contains("last error: HTTP 403"));
+        assert!(lines[3].starts_with("  resource       pin"));
+        assert!(lines[1].trim_end().ends_with("3 minutes ago"));
+        assert!(lines[4].trim_end().ends_with("never"));
+    }
+}
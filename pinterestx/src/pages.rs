@@ -0,0 +1,200 @@
+//! Pins with several pages: Idea (story) pins and carousels.
+//!
+//! An Idea pin is a sequence of pages, each made of blocks: an image or video plus
+//! text overlays. A carousel pin is a row of slots, each an image with an optional
+//! title and description. Both are read from the pin's JSON, as returned by the
+//! pin resources or embedded in a pin page, rather than by scanning for image URLs,
+//! which would lose the page order and mix up pages of different pins.
+
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::media;
+use crate::resources::{best_image, resource_id};
+
+/// Ids of the scripts pin pages embed their data in
+pub const DATA_SCRIPTS: &[&str] = &["__PWS_INITIAL_PROPS__", "__PWS_DATA__"];
+
+/// One page of a multi-page pin
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PinPage {
+    /// Position of the page in the pin, from 1
+    pub number: usize,
+    /// The page's image or video
+    #[serde(skip)]
+    pub url: String,
+    /// Text shown over or next to the page, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub text: Vec<String>,
+    /// The page's caption
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+}
+
+/// The pages of an Idea or carousel pin, in order; empty for single-image pins
+pub fn pin_pages(pin: &Value) -> Vec<PinPage> {
+    if let Some(pages) = pin.pointer("/story_pin_data/pages").and_then(Value::as_array) {
+        return numbered(pages.iter().filter_map(story_page));
+    }
+    if let Some(slots) = pin.pointer("/carousel_data/carousel_slots").and_then(Value::as_array) {
+        return numbered(slots.iter().filter_map(carousel_slot));
+    }
+    Vec::new()
+}
+
+fn numbered(pages: impl Iterator<Item = PinPage>) -> Vec<PinPage> {
+    pages
+        .enumerate()
+        .map(|(index, page)| PinPage { number: index + 1, ..page })
+        .collect()
+}
+
+/// A page of an Idea pin: its first image or video block, and the text of its other blocks
+fn story_page(page: &Value) -> Option<PinPage> {
+    let blocks = page["blocks"].as_array()?;
+    let (block, url) = blocks.iter().find_map(|block| {
+        let url = match block["type"].as_str() {
+            Some("story_pin_image_block") => best_image(&block["image"]),
+            Some("story_pin_video_block") => media::best_encoding(&block["video"]["video_list"]),
+            _ => None,
+        };
+        Some((block, url?))
+    })?;
+    let text = blocks
+        .iter()
+        .filter(|block| matches!(block["type"].as_str(), Some("story_pin_paragraph_block" | "story_pin_heading_block")))
+        .filter_map(|block| non_empty(&block["text"]))
+        .collect();
+    let caption = non_empty(&block["caption"])
+        .or_else(|| non_empty(&block["image"]["caption"]))
+        .or_else(|| non_empty(&page["title"]));
+    Some(PinPage { number: 0, url, text, caption })
+}
+
+/// A slot of a carousel pin, captioned with its title and description
+fn carousel_slot(slot: &Value) -> Option<PinPage> {
+    let url = best_image(slot)?;
+    let parts: Vec<String> = [&slot["title"], &slot["details"], &slot["description"]]
+        .into_iter()
+        .filter_map(non_empty)
+        .collect();
+    let caption = (!parts.is_empty()).then(|| parts.join(" - "));
+    Some(PinPage { number: 0, url, text: Vec::new(), caption })
+}
+
+fn non_empty(value: &Value) -> Option<String> {
+    value.as_str().map(str::trim).filter(|text| !text.is_empty()).map(str::to_string)
+}
+
+/// Finds the pins in the data a Pinterest page embeds in its scripts, with the pin
+/// whose id is `id` first if there is one
+pub fn embedded_pins(html: &str, id: Option<&str>) -> Vec<Value> {
+    let document = Html::parse_document(html);
+    let mut pins = Vec::new();
+    for script_id in DATA_SCRIPTS {
+        let Ok(selector) = Selector::parse(&format!("script#{}", script_id)) else { continue };
+        for script in document.select(&selector) {
+            if let Ok(data) = serde_json::from_str::<Value>(&script.inner_html()) {
+                find_pins(&data, &mut pins);
+            }
+        }
+    }
+    if let Some(id) = id {
+        if let Some(index) = pins.iter().position(|pin| resource_id(pin).as_deref() == Some(id)) {
+            let pin = pins.remove(index);
+            pins.insert(0, pin);
+        }
+    }
+    pins
+}
+
+/// Collects every object that looks like a pin, without descending into pins
+fn find_pins(value: &Value, pins: &mut Vec<Value>) {
+    match value {
+        Value::Object(object) => {
+            let is_pin = object.contains_key("id")
+                && ["images", "story_pin_data", "carousel_data", "videos"].iter().any(|key| object.get(*key).is_some_and(Value::is_object));
+            if is_pin {
+                pins.push(value.clone());
+            } else {
+                object.values().for_each(|value| find_pins(value, pins));
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|value| find_pins(value, pins)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn image_block(name: &str) -> Value {
+        json!({ "type": "story_pin_image_block", "image": { "images": {
+            "736x": { "width": 736, "url": format!("https://i.pinimg.com/736x/aa/bb/{}.jpg", name) },
+            "originals": { "width": 1080, "url": format!("https://i.pinimg.com/originals/aa/bb/{}.jpg", name) },
+        }}})
+    }
+
+    #[test]
+    fn reads_idea_pin_pages_in_order() {
+        let pin = json!({ "id": "42", "story_pin_data": { "pages": [
+            { "blocks": [image_block("one"), { "type": "story_pin_heading_block", "text": "Step 1" },
+                { "type": "story_pin_paragraph_block", "text": "Mix the paint" }] },
+            { "blocks": [{ "type": "story_pin_paragraph_block", "text": "No image here" }] },
+            { "blocks": [{ "type": "story_pin_video_block", "video": { "video_list": {
+                "V_720P": { "width": 720, "url": "https://v.pinimg.com/videos/720p/two.mp4" } } } }],
+              "title": "Done" },
+        ]}});
+        let pages = pin_pages(&pin);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].number, 1);
+        assert_eq!(pages[0].url, "https://i.pinimg.com/originals/aa/bb/one.jpg");
+        assert_eq!(pages[0].text, ["Step 1", "Mix the paint"]);
+        assert_eq!(pages[1].number, 2);
+        assert_eq!(pages[1].url, "https://v.pinimg.com/videos/720p/two.mp4");
+        assert_eq!(pages[1].caption.as_deref(), Some("Done"));
+    }
+
+    #[test]
+    fn reads_carousel_slots() {
+        let pin = json!({ "id": "7", "carousel_data": { "carousel_slots": [
+            { "images": { "orig": { "url": "https://i.pinimg.com/originals/aa/bb/a.jpg" } }, "title": "Red", "details": "Small" },
+            { "images": { "orig": { "url": "https://i.pinimg.com/originals/aa/bb/b.jpg" } } },
+        ]}});
+        let pages = pin_pages(&pin);
+        assert_eq!(pages.iter().map(|page| page.url.as_str()).collect::<Vec<_>>(), [
+            "https://i.pinimg.com/originals/aa/bb/a.jpg",
+            "https://i.pinimg.com/originals/aa/bb/b.jpg",
+        ]);
+        assert_eq!(pages[0].caption.as_deref(), Some("Red - Small"));
+        assert_eq!(pages[1].caption, None);
+        assert!(pin_pages(&json!({ "id": "1", "images": {} })).is_empty());
+    }
+
+    #[test]
+    fn finds_pins_embedded_in_pages() {
+        let data = json!({ "props": { "initialReduxState": { "pins": {
+            "1": { "id": "1", "images": { "orig": { "url": "https://i.pinimg.com/originals/1.jpg" } } },
+            "2": { "id": "2", "story_pin_data": { "pages": [] }, "images": {} },
+        }}}});
+        let html = format!(r#"<html><script id="__PWS_INITIAL_PROPS__" type="application/json">{}</script></html>"#, data);
+        let pins = embedded_pins(&html, Some("2"));
+        assert_eq!(pins.len(), 2);
+        assert_eq!(pins[0]["id"], "2");
+    }
+
+    #[test]
+    fn skips_objects_with_an_id_but_no_media() {
+        let data = json!({ "props": { "user": { "id": "9", "username": "alice" }, "pins": [
+            { "id": "3", "images": { "orig": { "url": "https://i.pinimg.com/originals/3.jpg" } } },
+            { "id": "4", "images": "none" },
+        ]}});
+        let html = format!(r#"<html><script id="__PWS_DATA__" type="application/json">{}</script></html>"#, data);
+        let pins = embedded_pins(&html, None);
+        assert_eq!(pins.len(), 1);
+        assert_eq!(pins[0]["id"], "3");
+    }
+}
@@ -0,0 +1,190 @@
+//! Where the downloader keeps its data and saves downloads, and safe file writes.
+//!
+//! Settings, cookies and history live in the application data directory
+//! (`PinterestX` in the platform's data directory); downloaded collections are saved
+//! under `Pinterestx` in the user's Pictures directory. Either can be moved with an
+//! environment variable ([`DATA_DIR_ENV`], [`OUTPUT_DIR_ENV`]), or by a program
+//! embedding the library with [`set_app_data_dir`] and [`set_output_root`].
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::warn;
+
+use crate::error::Result;
+
+/// Environment variable naming the directory to keep data in instead of the platform's
+pub const DATA_DIR_ENV: &str = "PINTERESTX_DATA_DIR";
+
+/// Environment variable naming the directory to save downloads under instead of Pictures
+pub const OUTPUT_DIR_ENV: &str = "PINTERESTX_OUTPUT_DIR";
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+static OUTPUT_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keeps data in `dir` for the rest of the process, ahead of [`DATA_DIR_ENV`]. Call it
+/// before anything is loaded; it can be set once, and `Err` has the directory in use.
+pub fn set_app_data_dir(dir: impl Into<PathBuf>) -> std::result::Result<(), PathBuf> {
+    DATA_DIR.set(dir.into())
+}
+
+/// Saves downloads under `dir` for the rest of the process, ahead of [`OUTPUT_DIR_ENV`].
+/// It can be set once, and `Err` has the directory in use.
+pub fn set_output_root(dir: impl Into<PathBuf>) -> std::result::Result<(), PathBuf> {
+    OUTPUT_ROOT.set(dir.into())
+}
+
+/// A directory named by an environment variable, if it's set and not empty
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from)
+}
+
+/// Keeps the data of the crate's unit tests in a fresh directory of their own
+#[cfg(test)]
+pub(crate) fn isolate_test_data() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pinterestx-unit-{}", std::process::id()));
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        let _ = std::fs::remove_dir_all(&dir);
+        set_app_data_dir(dir.clone()).expect("the data directory is used before the tests isolate it");
+    });
+    get_app_data_dir()
+}
+
+/// Gets the application data directory
+pub fn get_app_data_dir() -> PathBuf {
+    let path = DATA_DIR.get_or_init(|| {
+        env_dir(DATA_DIR_ENV).unwrap_or_else(|| {
+            let mut path = dirs::data_dir().unwrap_or_else(|| {
+                PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| {
+                    warn!("Could not determine home directory. Using current directory for data storage.");
+                    ".".to_string()
+                }))
+            });
+            path.push("PinterestX");
+            path
+        })
+    }).clone();
+    
+    // Create directory if it doesn't exist
+    if !path.exists() {
+        let _ = std::fs::create_dir_all(&path);
+    }
+    
+    path
+}
+
+/// Writes a file by writing a temporary sibling first and renaming it into place,
+/// so readers never observe a partially written file
+pub fn write_file_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Creates (or truncates) a file that only the current user can read and write
+pub fn create_private_file(path: &Path) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    open_private(path, &mut options)
+}
+
+/// Opens a file with `options`, readable and writable by the current user only. The
+/// mode given at open only applies to a file it creates, so the permissions of a file
+/// that was already there are set too.
+fn open_private(path: &Path, options: &mut std::fs::OpenOptions) -> std::io::Result<std::fs::File> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(file)
+}
+
+/// Like `write_file_atomically`, for files holding credentials: the result is
+/// readable and writable by the current user only
+pub fn write_private_file_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    // A temporary file left behind by a crash or an older version may be readable by
+    // others, or a link to somewhere else; start from a new one
+    match std::fs::remove_file(&tmp_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut file = open_private(&tmp_path, std::fs::OpenOptions::new().write(true).create_new(true))?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Gets the root directory that downloaded collections are saved under
+pub fn get_output_root() -> Result<PathBuf> {
+    if let Some(root) = OUTPUT_ROOT.get().cloned().or_else(|| env_dir(OUTPUT_DIR_ENV)) {
+        return Ok(root);
+    }
+    match dirs::picture_dir() {
+        Some(pictures_dir) => Ok(pictures_dir.join("Pinterestx")),
+        None => {
+            // Fallback if picture_dir is not available
+            let home = std::env::var("HOME")
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::NotFound, "Could not find a Pictures or home directory"))?;
+            Ok(PathBuf::from(home)
+                .join("Pictures")
+                .join("Pinterestx"))
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn mode(path: &Path) -> u32 {
+        std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn writes_private_files_over_stale_temporary_files() {
+        let dir = isolate_test_data().join("private");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cookies.json");
+        let tmp_path = dir.join("cookies.json.tmp");
+        std::fs::write(&tmp_path, "left over from a crash").unwrap();
+        std::fs::set_permissions(&tmp_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private_file_atomically(&path, b"secret").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"secret");
+        assert_eq!(mode(&path), 0o600);
+        assert!(!tmp_path.exists());
+    }
+
+    #[test]
+    fn makes_existing_files_private() {
+        let dir = isolate_test_data().join("private");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("consent.json");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        create_private_file(&path).unwrap().write_all(b"new").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        assert_eq!(mode(&path), 0o600);
+    }
+}
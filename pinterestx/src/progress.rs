@@ -0,0 +1,140 @@
+//! Progress reporting for download runs.
+//!
+//! [`download`](crate::download()) tells its caller what its workers are doing
+//! through a callback taking [`ProgressEvent`]s, so a front end can show it however
+//! it likes: the command line draws progress bars from them, the web server logs
+//! them. Either way the run ends with a [`DownloadStats`] summary.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::error::Error;
+
+/// Something that happened during a download run. Workers are numbered from 0, and
+/// files by their position in the run, from 1.
+#[derive(Debug)]
+pub enum ProgressEvent<'a> {
+    /// The run is starting on `total` files, spread over `workers` workers;
+    /// `filtered` files that were found are left out
+    Started { total: usize, workers: usize, filtered: usize },
+    /// A worker has started on a file
+    FileStarted { worker: usize, filename: &'a str },
+    /// A worker received `chunk` more bytes of its current file, `file_bytes` so far
+    Bytes { worker: usize, filename: &'a str, file_bytes: u64, chunk: u64 },
+    /// A file was saved
+    Saved { worker: usize, index: usize, filename: &'a str, size: u64 },
+    /// A file was skipped as a copy of one already downloaded in this run
+    Duplicate { worker: usize, index: usize },
+    /// A file couldn't be downloaded; `kind` is what [`error_kind`] makes of the error
+    Failed { worker: usize, index: usize, url: &'a str, kind: &'a str, error: &'a Error },
+}
+
+/// Counters for a download run
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct DownloadStats {
+    pub downloaded: usize,
+    pub duplicates: usize,
+    pub failed: usize,
+    /// Images found but not downloaded (e.g. beyond `--max-images`)
+    pub filtered: usize,
+    pub bytes: u64,
+    pub elapsed: Duration,
+    /// Failed URLs grouped by error kind
+    pub failures: BTreeMap<String, Vec<String>>,
+}
+
+/// Classifies a download error into a short, human-readable kind for grouping
+pub fn error_kind(error: &Error) -> String {
+    match error {
+        Error::Request(e) => {
+            if let Some(status) = e.status() {
+                format!("HTTP {}", status.as_u16())
+            } else if e.is_timeout() {
+                "timeout".to_string()
+            } else if e.is_connect() {
+                "connection error".to_string()
+            } else if e.is_body() || e.is_decode() {
+                "interrupted transfer".to_string()
+            } else {
+                "request error".to_string()
+            }
+        }
+        Error::Http { status, .. } => format!("HTTP {}", status.as_u16()),
+        Error::Url(_) => "invalid URL".to_string(),
+        Error::Io(_) => "file system error".to_string(),
+        Error::NotMedia { .. } => "not an image or video".to_string(),
+        _ => "other".to_string(),
+    }
+}
+
+/// Progress of one download run, shared by all of its workers: keeps the counters
+/// and passes every event on to the caller's callback
+pub(crate) struct DownloadProgress<'a> {
+    total: usize,
+    started: Instant,
+    stats: Mutex<DownloadStats>,
+    on_progress: &'a (dyn Fn(ProgressEvent<'_>) + Sync),
+}
+
+impl<'a> DownloadProgress<'a> {
+    /// Starts tracking a run of `total` images spread over `workers` workers
+    pub fn new(on_progress: &'a (dyn Fn(ProgressEvent<'_>) + Sync), total: usize, workers: usize, filtered: usize) -> Self {
+        on_progress(ProgressEvent::Started { total, workers, filtered });
+        Self {
+            total,
+            started: Instant::now(),
+            stats: Mutex::new(DownloadStats { filtered, ..Default::default() }),
+            on_progress,
+        }
+    }
+
+    /// Reports the file a worker has started on
+    pub fn start_file(&self, worker: usize, filename: &str) {
+        (self.on_progress)(ProgressEvent::FileStarted { worker, filename });
+    }
+
+    /// Reports bytes received by a worker for its current file
+    pub fn add_bytes(&self, worker: usize, filename: &str, file_bytes: u64, chunk: u64) {
+        (self.on_progress)(ProgressEvent::Bytes { worker, filename, file_bytes, chunk });
+    }
+
+    /// Records a saved image
+    pub fn downloaded(&self, worker: usize, index: usize, filename: &str, size: u64) {
+        self.update(|stats| {
+            stats.downloaded += 1;
+            stats.bytes += size;
+        });
+        (self.on_progress)(ProgressEvent::Saved { worker, index, filename, size });
+    }
+
+    /// Records an image that had already been downloaded in this run
+    pub fn duplicate(&self, worker: usize, index: usize) {
+        self.update(|stats| stats.duplicates += 1);
+        (self.on_progress)(ProgressEvent::Duplicate { worker, index });
+    }
+
+    /// Records a failed image
+    pub fn failed(&self, worker: usize, index: usize, url: &str, error: &Error) {
+        let kind = error_kind(error);
+        warn!(error = %error, kind = %kind, "[{}/{}] Download failed", index, self.total);
+        self.update(|stats| {
+            stats.failed += 1;
+            stats.failures.entry(kind.clone()).or_default().push(url.to_string());
+        });
+        (self.on_progress)(ProgressEvent::Failed { worker, index, url, kind: &kind, error });
+    }
+
+    fn update(&self, f: impl FnOnce(&mut DownloadStats)) {
+        f(&mut self.stats.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    /// Returns the final counters
+    pub fn finish(self) -> DownloadStats {
+        let mut stats = self.stats.into_inner().unwrap_or_else(|e| e.into_inner());
+        stats.elapsed = self.started.elapsed();
+        stats
+    }
+}
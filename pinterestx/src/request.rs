@@ -0,0 +1,208 @@
+//! Requests for Pinterest's internal resources.
+//!
+//! Every resource is read with a GET of `/resource/<Name>/get/`, whose `source_url`
+//! parameter is the page the request is made from and whose `data` parameter is a
+//! JSON object of options and context. [`ResourceRequest`] builds that URL from typed
+//! parts, serializing the JSON with `serde_json` and encoding the query with `url`, so
+//! that no value ever needs escaping by hand. It also names the page's handler for
+//! the `X-Pinterest-PWS-Handler` header the web app sends with resource requests.
+
+use serde_json::{json, Map, Value};
+use url::Url;
+
+use crate::error::Result;
+use crate::source::PinterestSource;
+
+/// Handler of pages that aren't a known kind of source
+const DEFAULT_HANDLER: &str = "www/index.js";
+
+/// A request for one of Pinterest's internal resources, such as `BoardFeedResource`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceRequest {
+    name: String,
+    source_url: String,
+    options: Map<String, Value>,
+}
+
+impl ResourceRequest {
+    /// A request for a resource, made from the home page until [`Self::source_url`] says otherwise
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), source_url: "/".to_string(), options: Map::new() }
+    }
+
+    /// Sets the path (and query) of the page the request is made from, e.g. `/alice/cats/`
+    pub fn source_url(mut self, source_url: impl Into<String>) -> Self {
+        self.source_url = source_url.into();
+        self
+    }
+
+    /// Sets an option of the request
+    pub fn option(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.options.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Sets every field of a JSON object as an option; other values are ignored
+    pub fn options(mut self, options: Value) -> Self {
+        if let Value::Object(options) = options {
+            self.options.extend(options);
+        }
+        self
+    }
+
+    /// Asks for the page of a paginated resource that follows `bookmark`
+    pub fn bookmark(self, bookmark: &str) -> Self {
+        self.option("bookmarks", json!([bookmark]))
+    }
+
+    /// Name of the resource
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Path of the page the request is made from
+    pub fn page(&self) -> &str {
+        &self.source_url
+    }
+
+    /// The `data` parameter: the options and an empty context, as JSON
+    pub fn data(&self) -> String {
+        json!({ "options": self.options, "context": {} }).to_string()
+    }
+
+    /// The request's URL on a Pinterest host
+    pub fn url(&self, base_url: &Url) -> Result<Url> {
+        let mut url = base_url.join(&format!("resource/{}/get/", self.name))?;
+        url.query_pairs_mut()
+            .append_pair("source_url", &self.source_url)
+            .append_pair("data", &self.data());
+        Ok(url)
+    }
+
+    /// The web app's handler of the page the request is made from, for `X-Pinterest-PWS-Handler`
+    pub fn pws_handler(&self) -> &'static str {
+        let source = Url::parse("https://www.pinterest.com/")
+            .and_then(|base| base.join(&self.source_url))
+            .ok()
+            .and_then(|url| PinterestSource::parse(&url));
+        match source {
+            Some(PinterestSource::Pin { .. }) => "www/pin/[id].js",
+            Some(PinterestSource::Board { .. }) => "www/[username]/[slug].js",
+            Some(PinterestSource::Section { .. }) => "www/[username]/[slug]/[section_slug].js",
+            Some(PinterestSource::User { .. }) => "www/[username].js",
+            Some(PinterestSource::Search { .. }) => "www/search/[scope].js",
+            Some(PinterestSource::Topic { .. }) => "www/ideas/[interest]/[id].js",
+            Some(PinterestSource::Ideas) => "www/ideas.js",
+            None => DEFAULT_HANDLER,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://www.pinterest.com/").unwrap()
+    }
+
+    #[test]
+    fn builds_board_requests() {
+        let request = ResourceRequest::new("BoardResource")
+            .source_url("/alice/cats/")
+            .options(json!({ "username": "alice", "slug": "cats", "field_set_key": "detailed" }));
+        assert_eq!(
+            request.url(&base()).unwrap().as_str(),
+            "https://www.pinterest.com/resource/BoardResource/get/?source_url=%2Falice%2Fcats%2F\
+             &data=%7B%22context%22%3A%7B%7D%2C%22options%22%3A%7B%22field_set_key%22%3A%22detailed%22%2C\
+             %22slug%22%3A%22cats%22%2C%22username%22%3A%22alice%22%7D%7D"
+        );
+        assert_eq!(request.pws_handler(), "www/[username]/[slug].js");
+    }
+
+    #[test]
+    fn builds_paginated_requests() {
+        let request = ResourceRequest::new("BoardFeedResource")
+            .source_url("/alice/cats/")
+            .option("board_id", "10")
+            .option("page_size", 25)
+            .bookmark("Y2JVSG81V2sxcmNHRlpWM1J5VFVaU1YxVnJaR=");
+        assert_eq!(
+            request.url(&base()).unwrap().as_str(),
+            "https://www.pinterest.com/resource/BoardFeedResource/get/?source_url=%2Falice%2Fcats%2F\
+             &data=%7B%22context%22%3A%7B%7D%2C%22options%22%3A%7B%22board_id%22%3A%2210%22%2C\
+             %22bookmarks%22%3A%5B%22Y2JVSG81V2sxcmNHRlpWM1J5VFVaU1YxVnJaR%3D%22%5D%2C%22page_size%22%3A25%7D%7D"
+        );
+    }
+
+    #[test]
+    fn builds_search_requests_with_any_query() {
+        let query = r#"tom & jerry "1940" café/ümlaut?#"#;
+        let mut source_url = base().join("/search/pins/").unwrap();
+        source_url.query_pairs_mut().append_pair("q", query).append_pair("rs", "typed");
+        let source_url = format!("{}?{}", source_url.path(), source_url.query().unwrap());
+
+        let request = ResourceRequest::new("BaseSearchResource")
+            .source_url(source_url.clone())
+            .option("query", query)
+            .option("scope", "pins");
+        let url = request.url(&base()).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://www.pinterest.com/resource/BaseSearchResource/get/\
+             ?source_url=%2Fsearch%2Fpins%2F%3Fq%3Dtom%2B%2526%2Bjerry%2B%25221940%2522%2Bcaf%25C3%25A9%252F%25C3%25BCmlaut%253F%2523%26rs%3Dtyped\
+             &data=%7B%22context%22%3A%7B%7D%2C%22options%22%3A%7B%22query%22%3A%22tom+%26+jerry+%5C%221940%5C%22+caf%C3%A9%2F%C3%BCmlaut%3F%23%22%2C\
+             %22scope%22%3A%22pins%22%7D%7D"
+        );
+        assert_eq!(request.pws_handler(), "www/search/[scope].js");
+
+        // Decoding gives back exactly what was put in
+        let params: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert_eq!(params[0], ("source_url".to_string(), source_url));
+        let data: Value = serde_json::from_str(&params[1].1).unwrap();
+        assert_eq!(data["options"]["query"], query);
+    }
+
+    #[test]
+    fn builds_requests_against_other_hosts() {
+        let request = ResourceRequest::new("PinResource").source_url("/pin/42/").option("id", "42");
+        assert_eq!(
+            request.url(&Url::parse("http://127.0.0.1:8080/").unwrap()).unwrap().as_str(),
+            "http://127.0.0.1:8080/resource/PinResource/get/?source_url=%2Fpin%2F42%2F\
+             &data=%7B%22context%22%3A%7B%7D%2C%22options%22%3A%7B%22id%22%3A%2242%22%7D%7D"
+        );
+        assert_eq!(request.pws_handler(), "www/pin/[id].js");
+        assert_eq!(ResourceRequest::new("UserSettingsResource").source_url("/settings/").pws_handler(), "www/index.js");
+    }
+
+    #[tokio::test]
+    async fn sends_the_headers_of_the_web_app() {
+        use crate::test_support;
+        use axum::http::HeaderMap;
+        use axum::routing::get;
+        use axum::{Json, Router};
+
+        async fn board(headers: HeaderMap) -> Json<Value> {
+            let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).unwrap_or_default().to_string();
+            Json(json!({
+                "handler": header("x-pinterest-pws-handler"),
+                "source_url": header("x-pinterest-source-url"),
+                "csrf": header("x-csrftoken"),
+                "requested_with": header("x-requested-with"),
+                "referer": header("referer"),
+            }))
+        }
+
+        let app = Router::new().route("/resource/BoardResource/get/", get(board));
+        let base_url = test_support::serve(app).await;
+        let session = test_support::session(&base_url, &["csrftoken=token123"]);
+
+        let request = ResourceRequest::new("BoardResource").source_url("/alice/cats/");
+        let headers: Value = session.resource(&request).unwrap().send().await.unwrap().json().await.unwrap();
+        assert_eq!(headers["handler"], "www/[username]/[slug].js");
+        assert_eq!(headers["source_url"], "/alice/cats/");
+        assert_eq!(headers["csrf"], "token123");
+        assert_eq!(headers["requested_with"], "XMLHttpRequest");
+        assert_eq!(headers["referer"], format!("{}alice/cats/", base_url));
+    }
+}
@@ -0,0 +1,214 @@
+//! Resolution and normalization of the URLs users paste.
+//!
+//! `pin.it` short links are followed to the page they point to, regional hosts
+//! (`se.pinterest.com`, `pinterest.de`, `br.pinterest.com`, ...) are mapped to
+//! `www.pinterest.com`, and share/tracking parameters are dropped, so the rest of
+//! the downloader only ever sees canonical Pinterest URLs.
+
+use tracing::debug;
+use url::Url;
+
+use crate::error::{Error, Result};
+use crate::session::Session;
+
+/// Redirects followed at most when resolving a short link
+const MAX_REDIRECTS: usize = 5;
+
+/// Host every Pinterest URL is normalized to
+const CANONICAL_HOST: &str = "www.pinterest.com";
+
+/// Hosts of Pinterest's link shortener
+const SHORT_LINK_HOSTS: &[&str] = &["pin.it", "www.pin.it"];
+
+/// Query parameters added by share buttons and campaigns, which don't change the page
+const TRACKING_PARAMS: &[&str] = &[
+    "invite_code", "sender", "sfo", "mweb_unauth_id", "epik", "_t", "_r", "nic", "nic_v2",
+    "amp_client_id", "fbclid", "gclid", "msclkid",
+];
+
+/// Resolves a URL as given by the user: follows short links, then normalizes it
+pub async fn resolve(input: &str, session: &Session) -> Result<Url> {
+    let mut url = Url::parse(input.trim())?;
+
+    let mut hops = 0;
+    while is_short_link(&url) {
+        if hops == MAX_REDIRECTS {
+            return Err(Error::UnexpectedResponse(format!("Too many redirects resolving {} (more than {})", input, MAX_REDIRECTS)));
+        }
+        hops += 1;
+
+        let response = session.get_without_redirects(&url).send().await?;
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .filter(|_| response.status().is_redirection())
+            .ok_or_else(|| Error::UnexpectedResponse(format!("Short link {} didn't redirect ({})", url, response.status())))?;
+        let next = url.join(location)?;
+        debug!(from = %url, to = %next, "Followed redirect");
+        url = next;
+    }
+
+    Ok(normalize(&url))
+}
+
+/// Whether a URL is a short link that redirects elsewhere: a `pin.it` link, or the
+/// `/url_shortener/` page it redirects to, which is served from `api.pinterest.com`
+fn is_short_link(url: &Url) -> bool {
+    let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+    let pinterest = is_pinterest_host(&host) || host.ends_with(".pinterest.com");
+    is_short_link_host(&host) || (pinterest && url.path().starts_with("/url_shortener/"))
+}
+
+/// Whether a host is Pinterest's link shortener
+pub fn is_short_link_host(host: &str) -> bool {
+    SHORT_LINK_HOSTS.contains(&host.to_ascii_lowercase().as_str())
+}
+
+/// Whether a host is one of Pinterest's regional sites, e.g. `pinterest.com`,
+/// `se.pinterest.com`, `www.pinterest.de` or `pinterest.co.uk`
+pub fn is_pinterest_host(host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    let labels: Vec<&str> = host.split('.').collect();
+    let Some(index) = labels.iter().position(|label| *label == "pinterest") else { return false };
+    let prefix_ok = match index {
+        0 => true,
+        1 => labels[0] == "www" || labels[0].len() == 2,
+        _ => false,
+    };
+    let suffix_ok = match &labels[index + 1..] {
+        [tld] => *tld == "com" || tld.len() == 2,
+        [second, country] => matches!(*second, "co" | "com") && country.len() == 2,
+        _ => false,
+    };
+    prefix_ok && suffix_ok
+}
+
+/// Maps a Pinterest URL to its canonical form: https, `www.pinterest.com`, a trailing
+/// slash and no tracking parameters or fragment. Other URLs are returned unchanged.
+pub fn normalize(url: &Url) -> Url {
+    let Some(host) = url.host_str().filter(|host| is_pinterest_host(host)) else {
+        return url.clone();
+    };
+
+    let mut normalized = url.clone();
+    if host != CANONICAL_HOST {
+        let _ = normalized.set_host(Some(CANONICAL_HOST));
+    }
+    let _ = normalized.set_scheme("https");
+    let _ = normalized.set_port(None);
+    normalized.set_fragment(None);
+    if !normalized.path().ends_with('/') {
+        let path = format!("{}/", normalized.path());
+        normalized.set_path(&path);
+    }
+
+    let params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !is_tracking_param(key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if params.is_empty() {
+        normalized.set_query(None);
+    } else {
+        normalized.query_pairs_mut().clear().extend_pairs(params);
+    }
+    normalized
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(input: &str) -> String {
+        normalize(&Url::parse(input).unwrap()).to_string()
+    }
+
+    #[test]
+    fn recognizes_regional_hosts() {
+        for host in ["pinterest.com", "www.pinterest.com", "se.pinterest.com", "br.pinterest.com", "pinterest.de",
+            "www.pinterest.co.uk", "pinterest.com.au", "in.pinterest.com"]
+        {
+            assert!(is_pinterest_host(host), "{}", host);
+        }
+        for host in ["pin.it", "i.pinimg.com", "pinterest.example.com", "notpinterest.com", "a.b.pinterest.com"] {
+            assert!(!is_pinterest_host(host), "{}", host);
+        }
+    }
+
+    #[test]
+    fn normalizes_urls() {
+        assert_eq!(normalized("https://se.pinterest.com/alice/cats"), "https://www.pinterest.com/alice/cats/");
+        assert_eq!(normalized("http://pinterest.de/pin/123/#comments"), "https://www.pinterest.com/pin/123/");
+        assert_eq!(
+            normalized("https://br.pinterest.com/pin/123/?utm_source=x&invite_code=abc&sender=1"),
+            "https://www.pinterest.com/pin/123/"
+        );
+        assert_eq!(
+            normalized("https://www.pinterest.co.uk/search/pins/?q=dark%20art&rs=typed&utm_medium=y"),
+            "https://www.pinterest.com/search/pins/?q=dark+art&rs=typed"
+        );
+        assert_eq!(normalized("http://127.0.0.1:8765/a/b?utm_source=x"), "http://127.0.0.1:8765/a/b?utm_source=x");
+    }
+
+    #[test]
+    fn recognizes_short_links() {
+        for url in ["https://pin.it/1a2b3c", "https://api.pinterest.com/url_shortener/1a2b3c/redirect/",
+            "https://www.pinterest.com/url_shortener/1a2b3c/redirect/"]
+        {
+            assert!(is_short_link(&Url::parse(url).unwrap()), "{}", url);
+        }
+        for url in ["https://api.pinterest.com/v3/pins/", "https://www.pinterest.com/pin/123/",
+            "https://pinterest.example.com/url_shortener/1a2b3c/"]
+        {
+            assert!(!is_short_link(&Url::parse(url).unwrap()), "{}", url);
+        }
+    }
+
+    /// A link shortener that redirects `pin.it/<id>` through the API host to a pin,
+    /// the way Pinterest's does, and `pin.it/loop` back to itself forever
+    async fn mock_shortener() -> Session {
+        use crate::test_support;
+        use axum::extract::Path;
+        use axum::http::{header, StatusCode};
+        use axum::response::{IntoResponse, Response};
+        use axum::routing::get;
+        use axum::Router;
+
+        fn redirect(location: String) -> Response {
+            (StatusCode::FOUND, [(header::LOCATION, location)]).into_response()
+        }
+        async fn short_link(Path(id): Path<String>) -> Response {
+            redirect(format!("https://api.pinterest.com/url_shortener/{}/redirect/", id))
+        }
+        async fn api_redirect(Path(id): Path<String>) -> Response {
+            match id.as_str() {
+                "loop" => redirect("https://pin.it/loop".to_string()),
+                _ => redirect("https://se.pinterest.com/pin/123/?invite_code=abc".to_string()),
+            }
+        }
+
+        let app = Router::new()
+            .route("/:id", get(short_link))
+            .route("/url_shortener/:id/redirect/", get(api_redirect));
+        test_support::session(&test_support::serve(app).await, &[])
+    }
+
+    #[tokio::test]
+    async fn follows_short_links_through_the_api_host() {
+        let session = mock_shortener().await;
+        let url = resolve("https://pin.it/1a2b3c", &session).await.unwrap();
+        assert_eq!(url.as_str(), "https://www.pinterest.com/pin/123/");
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_too_many_redirects() {
+        let session = mock_shortener().await;
+        let error = resolve("https://pin.it/loop", &session).await.unwrap_err();
+        assert!(matches!(error, Error::UnexpectedResponse(ref message) if message.starts_with("Too many redirects")), "{:?}", error);
+    }
+}
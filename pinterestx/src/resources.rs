@@ -0,0 +1,427 @@
+//! Pinterest's internal JSON resources.
+//!
+//! The web app loads boards, feeds and searches from `/resource/<Name>/get/`
+//! endpoints, a page at a time, each page carrying a bookmark for the next one. These
+//! helpers fetch such feeds, whole or streamed page by page, and pick the best image
+//! of each pin in them.
+
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use tracing::{debug, info, warn};
+
+use crate::error::{Error, Result};
+use crate::media;
+use crate::pages::{pin_pages, PinPage};
+use crate::request::ResourceRequest;
+use crate::scrape::improve_image_quality;
+use crate::session::Session;
+
+/// Pins requested per resource page
+const PAGE_SIZE: usize = 25;
+
+/// Upper bound on pages fetched from one feed, in case a bookmark never ends
+const MAX_PAGES: usize = 400;
+
+/// Bookmark Pinterest returns on a feed's last page
+const END_BOOKMARK: &str = "-end-";
+
+/// A section of a board
+#[derive(Debug, Clone)]
+pub struct BoardSection {
+    pub id: String,
+    pub slug: String,
+    pub title: String,
+    pub pin_count: u64,
+}
+
+/// A file to download for a pin: its image or video, or one page of a multi-page pin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinMedia {
+    pub url: String,
+    /// Id of the pin, when known
+    pub pin_id: Option<String>,
+    /// Which page of the pin this is, for Idea and carousel pins
+    pub page: Option<PinPage>,
+}
+
+/// Looks up a board (public, or secret with a logged-in session), returning its id
+/// and the resource data describing it
+pub async fn fetch_board_info(session: &Session, user: &str, slug: &str) -> Result<(String, Value)> {
+    let board_url = format!("/{}/{}/", user, slug);
+    let request = ResourceRequest::new("BoardResource")
+        .source_url(board_url.clone())
+        .options(json!({ "username": user, "slug": slug, "field_set_key": "detailed" }));
+    let (status, body) = session.fetch_resource(&request).await?;
+    if !status.is_success() {
+        return Err(Error::NotFound(format!("Board {} not found: {}", board_url, status)));
+    }
+    let mut body: Value = serde_json::from_str(&body)?;
+    let board = body["resource_response"]["data"].take();
+    let id = resource_id(&board).ok_or_else(|| Error::NotFound(format!("Board {} not found", board_url)))?;
+    if board["privacy"].as_str() == Some("secret") {
+        info!(board = %board_url, "Board is secret");
+    }
+    Ok((id, board))
+}
+
+/// The media of a single pin: its best image or video, or every page of a multi-page pin
+pub async fn fetch_pin(session: &Session, id: &str) -> Result<Vec<PinMedia>> {
+    let request = ResourceRequest::new("PinResource")
+        .source_url(format!("/pin/{}/", id))
+        .options(json!({ "id": id, "field_set_key": "detailed" }));
+    let (status, body) = session.fetch_resource(&request).await?;
+    if !status.is_success() {
+        return Err(Error::NotFound(format!("Pin {} not found: {}", id, status)));
+    }
+    let body: Value = serde_json::from_str(&body)?;
+    Ok(pin_media(&body["resource_response"]["data"]))
+}
+
+/// Collects up to `limit` images of the pins Pinterest shows as related to a pin
+pub async fn fetch_related(session: &Session, id: &str, limit: usize) -> Result<Vec<PinMedia>> {
+    let request = ResourceRequest::new("RelatedPinFeedResource")
+        .source_url(format!("/pin/{}/", id))
+        .options(json!({ "pin": id, "add_vase": true, "field_set_key": "unauth_react" }));
+    let pins = fetch_feed(session, request, limit).await?;
+    Ok(collect_media(&pins, limit))
+}
+
+/// Lists the boards of a user that the session can see: public ones, plus secret
+/// and group boards when logged in as that user
+pub async fn fetch_user_boards(session: &Session, user: &str) -> Result<Vec<Value>> {
+    let request = ResourceRequest::new("BoardsResource").source_url(format!("/{}/boards/", user)).options(json!({
+        "username": user,
+        "privacy_filter": "all",
+        "include_archived": true,
+        "field_set_key": "detailed",
+    }));
+    fetch_feed(session, request, 0).await
+}
+
+/// Lists the sections of a board
+pub async fn fetch_sections(session: &Session, board_id: &str, board_url: &str) -> Result<Vec<BoardSection>> {
+    let request = ResourceRequest::new("BoardSectionsResource").source_url(board_url).option("board_id", board_id);
+    let items = fetch_feed(session, request, 0).await?;
+    let sections = items
+        .iter()
+        .filter_map(|item| {
+            let id = resource_id(item)?;
+            let slug = item["slug"].as_str()?.to_string();
+            let title = item["title"].as_str().unwrap_or(&slug).to_string();
+            let pin_count = item["pin_count"].as_u64().unwrap_or(0);
+            Some(BoardSection { id, slug, title, pin_count })
+        })
+        .collect();
+    Ok(sections)
+}
+
+/// Collects the images of one section of a board, stopping after `limit` images (0 = no limit)
+pub async fn fetch_section(session: &Session, section: &BoardSection, section_url: &str, limit: usize) -> Result<Vec<PinMedia>> {
+    limited(section_media(session, section, section_url), limit).try_collect().await
+}
+
+/// Streams the images of one section of a board, a page at a time
+pub fn section_media<'a>(session: &'a Session, section: &BoardSection, section_url: &str) -> BoxStream<'a, Result<PinMedia>> {
+    let request = ResourceRequest::new("BoardSectionPinsResource")
+        .source_url(section_url)
+        .option("section_id", section.id.as_str());
+    feed_media(feed_pages(session, request, 0))
+}
+
+/// Streams the images of a board's feed, a page at a time
+pub fn board_media<'a>(session: &'a Session, board_id: &str, board_url: &str) -> BoxStream<'a, Result<PinMedia>> {
+    feed_media(feed_pages(session, board_feed_request(board_id, board_url), 0))
+}
+
+/// Finds a section of a board by the slug in its URL
+pub async fn find_section(session: &Session, user: &str, board: &str, slug: &str) -> Result<BoardSection> {
+    let board_url = format!("/{}/{}/", user, board);
+    let (board_id, _) = fetch_board_info(session, user, board).await?;
+    fetch_sections(session, &board_id, &board_url)
+        .await?
+        .into_iter()
+        .find(|section| section.slug.eq_ignore_ascii_case(slug))
+        .ok_or_else(|| Error::NotFound(format!("Board {} has no section '{}'", board_url, slug)))
+}
+
+/// The id of a board, section or pin, which Pinterest sends as a string or a number
+pub fn resource_id(item: &Value) -> Option<String> {
+    match &item["id"] {
+        Value::String(id) => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// The request for the pins of a board's feed
+fn board_feed_request(board_id: &str, board_url: &str) -> ResourceRequest {
+    ResourceRequest::new("BoardFeedResource")
+        .source_url(board_url)
+        .options(json!({ "board_id": board_id, "board_url": board_url, "field_set_key": "react_grid_pin" }))
+}
+
+/// Fetches the pins of a board's feed
+pub async fn fetch_board_pins(session: &Session, board_id: &str, board_url: &str, limit: usize) -> Result<Vec<Value>> {
+    fetch_feed(session, board_feed_request(board_id, board_url), limit).await
+}
+
+/// Fetches every page of a paginated resource, following its bookmarks, until the
+/// feed ends or `limit` items (0 = no limit) have been collected
+pub async fn fetch_feed(session: &Session, request: ResourceRequest, limit: usize) -> Result<Vec<Value>> {
+    feed_pages(session, request, limit).try_concat().await
+}
+
+/// Streams the pages of a paginated resource, following its bookmarks, until the
+/// feed ends or `limit` items (0 = no limit) have been fetched. A page is only
+/// fetched once the previous one has been read from the stream.
+pub fn feed_pages(session: &Session, request: ResourceRequest, limit: usize) -> BoxStream<'_, Result<Vec<Value>>> {
+    let request = request.option("page_size", PAGE_SIZE);
+    stream::try_unfold((Some(request), 1, 0), move |(request, page, fetched)| async move {
+        let Some(request) = request else { return Ok(None) };
+        let resource = request.name().to_string();
+        let (status, body) = session.fetch_resource(&request).await?;
+        if !status.is_success() {
+            return Err(Error::Http { what: resource, status });
+        }
+        let body: Value = serde_json::from_str(&body)?;
+        // Most feeds are a list; searches wrap theirs in `results`
+        let data = &body["resource_response"]["data"];
+        let data = data.as_array().or_else(|| data["results"].as_array()).cloned().unwrap_or_default();
+        debug!(resource, page, count = data.len(), "Fetched page");
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        let fetched = fetched + data.len();
+        let next = match next_bookmark(&body) {
+            _ if limit > 0 && fetched >= limit => None,
+            Some(_) if page == MAX_PAGES => {
+                warn!(resource, "Stopped after {} pages", MAX_PAGES);
+                None
+            }
+            Some(bookmark) => Some(request.bookmark(&bookmark)),
+            None => None,
+        };
+        Ok(Some((data, (next, page + 1, fetched))))
+    })
+    .boxed()
+}
+
+/// Streams the media of the pins in a feed's pages, skipping feed items that aren't
+/// pins and files already seen
+pub fn feed_media<'a>(pages: BoxStream<'a, Result<Vec<Value>>>) -> BoxStream<'a, Result<PinMedia>> {
+    let mut seen = HashSet::new();
+    pages
+        .map_ok(|pins| stream::iter(pins.iter().flat_map(pin_media).map(Ok).collect::<Vec<_>>()))
+        .try_flatten()
+        .try_filter(move |media| future::ready(seen.insert(media.url.clone())))
+        .boxed()
+}
+
+/// Ends a stream after `limit` items (0 = no limit)
+pub fn limited<'a, T: Send + 'a>(stream: BoxStream<'a, T>, limit: usize) -> BoxStream<'a, T> {
+    if limit > 0 {
+        stream.take(limit).boxed()
+    } else {
+        stream
+    }
+}
+
+/// The bookmark of the next page, if there is one
+fn next_bookmark(body: &Value) -> Option<String> {
+    body.pointer("/resource_response/bookmark")
+        .or_else(|| body.pointer("/resource/options/bookmarks/0"))
+        .and_then(Value::as_str)
+        .filter(|bookmark| !bookmark.is_empty() && *bookmark != END_BOOKMARK)
+        .map(str::to_string)
+}
+
+/// Picks the media of each pin, skipping feed items that aren't pins, until `limit`
+/// files (0 = no limit)
+pub fn collect_media(pins: &[Value], limit: usize) -> Vec<PinMedia> {
+    let mut seen = HashSet::new();
+    let mut media: Vec<PinMedia> = pins
+        .iter()
+        .flat_map(pin_media)
+        .filter(|media| seen.insert(media.url.clone()))
+        .collect();
+    if limit > 0 {
+        media.truncate(limit);
+    }
+    media
+}
+
+/// The files of one pin: a file per page for Idea and carousel pins, else its best media
+pub fn pin_media(pin: &Value) -> Vec<PinMedia> {
+    let pin_id = resource_id(pin);
+    let pages = pin_pages(pin);
+    if pages.is_empty() {
+        return best_media(pin).map(|url| PinMedia { url, pin_id, page: None }).into_iter().collect();
+    }
+    pages
+        .into_iter()
+        .map(|page| PinMedia { url: page.url.clone(), pin_id: pin_id.clone(), page: Some(page) })
+        .collect()
+}
+
+/// What to download for a pin: its video, the animated original of a GIF, or else its best image
+pub fn best_media(pin: &Value) -> Option<String> {
+    media::best_video(pin).or_else(|| media::animated_gif(pin)).or_else(|| best_image(pin))
+}
+
+/// The original image of a pin, or else its largest rendition
+pub fn best_image(pin: &Value) -> Option<String> {
+    let images = pin["images"].as_object()?;
+    let original = images.get("orig").or_else(|| images.get("originals"));
+    if let Some(url) = original.and_then(|image| image["url"].as_str()) {
+        return Some(url.to_string());
+    }
+    images
+        .values()
+        .filter_map(|image| Some((image["width"].as_u64().unwrap_or(0), image["url"].as_str()?)))
+        .max_by_key(|(width, _)| *width)
+        .map(|(_, url)| improve_image_quality(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Query;
+    use axum::routing::get;
+    use crate::test_support;
+    use axum::{Json, Router};
+    use std::collections::HashMap;
+
+    fn options(params: &HashMap<String, String>) -> Value {
+        let data: Value = serde_json::from_str(&params["data"]).unwrap();
+        data["options"].clone()
+    }
+
+    fn pin(id: u32) -> Value {
+        json!({ "id": id.to_string(), "images": {
+            "orig": { "width": 1200, "url": format!("https://i.pinimg.com/originals/aa/bb/{}.jpg", id) },
+        }})
+    }
+
+    fn urls(media: Vec<PinMedia>) -> Vec<String> {
+        media.into_iter().map(|media| media.url).collect()
+    }
+
+    /// A board with two sections, listed over two pages
+    async fn mock_pinterest() -> Session {
+        async fn board() -> Json<Value> {
+            Json(json!({ "resource_response": { "data": { "id": "10", "url": "/alice/cats/" } } }))
+        }
+        async fn sections(Query(params): Query<HashMap<String, String>>) -> Json<Value> {
+            assert_eq!(options(&params)["board_id"], "10");
+            let page = if options(&params)["bookmarks"][0] == "next" {
+                json!({ "data": [{ "id": 21, "slug": "big-cats", "title": "Big cats", "pin_count": 1 }] })
+            } else {
+                json!({ "data": [{ "id": "20", "slug": "kittens", "title": "Kittens", "pin_count": 2 }], "bookmark": "next" })
+            };
+            Json(json!({ "resource_response": page }))
+        }
+        async fn section_pins(Query(params): Query<HashMap<String, String>>) -> Json<Value> {
+            let pins = match options(&params)["section_id"].as_str() {
+                Some("20") => vec![pin(1), pin(2)],
+                Some("21") => vec![pin(3)],
+                _ => vec![],
+            };
+            Json(json!({ "resource_response": { "data": pins } }))
+        }
+        async fn pin_resource(Query(params): Query<HashMap<String, String>>) -> Json<Value> {
+            let id: u32 = options(&params)["id"].as_str().unwrap().parse().unwrap();
+            if id == 42 {
+                // An Idea pin, whose cover is one of its pages
+                let page = |n: u32| json!({ "blocks": [
+                    { "type": "story_pin_image_block", "image": pin(n) },
+                    { "type": "story_pin_paragraph_block", "text": format!("Step {}", n) },
+                ]});
+                return Json(json!({ "resource_response": { "data": {
+                    "id": "42", "images": pin(1)["images"], "story_pin_data": { "pages": [page(1), page(2)] },
+                }}}));
+            }
+            Json(json!({ "resource_response": { "data": pin(id) } }))
+        }
+        async fn board_feed(Query(params): Query<HashMap<String, String>>) -> Result<Json<Value>, axum::http::StatusCode> {
+            if options(&params)["bookmarks"][0] == "next" {
+                // The second page is broken, to tell when it's fetched
+                return Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+            }
+            Ok(Json(json!({ "resource_response": { "data": [pin(1), pin(2)], "bookmark": "next" } })))
+        }
+        async fn related(Query(params): Query<HashMap<String, String>>) -> Json<Value> {
+            assert_eq!(options(&params)["pin"], "7");
+            Json(json!({ "resource_response": { "data": [pin(8), pin(9), pin(8), pin(10)] } }))
+        }
+
+        let app = Router::new()
+            .route("/resource/BoardResource/get/", get(board))
+            .route("/resource/BoardFeedResource/get/", get(board_feed))
+            .route("/resource/BoardSectionsResource/get/", get(sections))
+            .route("/resource/BoardSectionPinsResource/get/", get(section_pins))
+            .route("/resource/PinResource/get/", get(pin_resource))
+            .route("/resource/RelatedPinFeedResource/get/", get(related));
+        test_support::session(&test_support::serve(app).await, &[])
+    }
+
+    #[tokio::test]
+    async fn lists_sections_across_pages() {
+        let session = mock_pinterest().await;
+        let sections = fetch_sections(&session, "10", "/alice/cats/").await.unwrap();
+        let slugs: Vec<&str> = sections.iter().map(|section| section.slug.as_str()).collect();
+        assert_eq!(slugs, ["kittens", "big-cats"]);
+        assert_eq!(sections[1].id, "21");
+        assert_eq!(sections[0].title, "Kittens");
+    }
+
+    #[tokio::test]
+    async fn fetches_section_pins_by_slug() {
+        let session = mock_pinterest().await;
+        let section = find_section(&session, "alice", "cats", "Big-Cats").await.unwrap();
+        let images = fetch_section(&session, &section, "/alice/cats/big-cats/", 0).await.unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].url, "https://i.pinimg.com/originals/aa/bb/3.jpg");
+        assert_eq!(images[0].pin_id.as_deref(), Some("3"));
+
+        assert!(find_section(&session, "alice", "cats", "dogs").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn streams_a_feed_a_page_at_a_time() {
+        let session = mock_pinterest().await;
+
+        // Pins of the first page arrive before the second fails
+        let results: Vec<Result<PinMedia>> = board_media(&session, "10", "/alice/cats/").collect().await;
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].as_ref().unwrap().url, "https://i.pinimg.com/originals/aa/bb/2.jpg");
+        assert_eq!(results[2].as_ref().unwrap_err().status(), Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR));
+
+        // The second page isn't fetched once the limit is reached
+        let limited: Vec<PinMedia> = limited(board_media(&session, "10", "/alice/cats/"), 2).try_collect().await.unwrap();
+        assert_eq!(urls(limited), ["https://i.pinimg.com/originals/aa/bb/1.jpg", "https://i.pinimg.com/originals/aa/bb/2.jpg"]);
+    }
+
+    #[tokio::test]
+    async fn fetches_a_pin_and_its_related_pins() {
+        let session = mock_pinterest().await;
+        let image = fetch_pin(&session, "7").await.unwrap();
+        assert_eq!(urls(image), ["https://i.pinimg.com/originals/aa/bb/7.jpg"]);
+
+        let related = fetch_related(&session, "7", 2).await.unwrap();
+        assert_eq!(urls(related), ["https://i.pinimg.com/originals/aa/bb/8.jpg", "https://i.pinimg.com/originals/aa/bb/9.jpg"]);
+    }
+
+    #[tokio::test]
+    async fn fetches_every_page_of_an_idea_pin() {
+        let session = mock_pinterest().await;
+        let pages = fetch_pin(&session, "42").await.unwrap();
+        assert_eq!(urls(pages.clone()), ["https://i.pinimg.com/originals/aa/bb/1.jpg", "https://i.pinimg.com/originals/aa/bb/2.jpg"]);
+        assert!(pages.iter().all(|media| media.pin_id.as_deref() == Some("42")));
+        let second = pages[1].page.as_ref().unwrap();
+        assert_eq!(second.number, 2);
+        assert_eq!(second.text, ["Step 2"]);
+    }
+}
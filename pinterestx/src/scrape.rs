@@ -0,0 +1,485 @@
+//! Reading pins from Pinterest's pages rather than its resources.
+//!
+//! These are the fallbacks for when the JSON resources fail: the pin data a page
+//! embeds, its Open Graph metadata, the `initial_state` blob, and in the end any
+//! `pinimg.com` URL found in the page's scripts, markup or text. URLs of smaller
+//! renditions are rewritten to the original size.
+
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use tracing::{debug, info, instrument, warn};
+use url::Url;
+
+use crate::error::{Error, Result};
+use crate::extract::PinCandidate;
+use crate::media::MediaKind;
+use crate::pages;
+use crate::resources;
+use crate::session::Session;
+
+/// Reads a pin from its page: from the pin data the page embeds, or else from its Open
+/// Graph metadata. Unlike the rest of the page, neither mixes in other pins.
+pub async fn scrape_pin(url: &Url, id: &str, session: &Session) -> Result<Vec<PinCandidate>> {
+    let html_content = fetch_page(url, session).await?;
+    let embedded = pages::embedded_pins(&html_content, Some(id));
+    if let Some(pin) = embedded.first().filter(|pin| resources::resource_id(pin).as_deref() == Some(id)) {
+        let media = resources::pin_media(pin);
+        if !media.is_empty() {
+            return Ok(media.into_iter().map(PinCandidate::from).collect());
+        }
+    }
+
+    let document = Html::parse_document(&html_content);
+    let selector = Selector::parse(r#"meta[property="og:image"], meta[name="og:image"]"#).expect("valid selector");
+    let image = document
+        .select(&selector)
+        .filter_map(|meta| meta.value().attr("content"))
+        .find(|content| is_pinterest_image_url(content))
+        .map(|content| PinCandidate { pin_id: Some(id.to_string()), ..improve_image_quality(content).into() });
+    Ok(image.into_iter().collect())
+}
+
+/// Last resort: parses the page's HTML for image URLs
+pub async fn scrape_html(url: &Url, session: &Session, is_search_page: bool) -> Result<Vec<String>> {
+    let html_content = fetch_page(url, session).await?;
+    info!(strategy = "html", bytes = html_content.len(), "Fetched page content ({} bytes)", html_content.len());
+    Ok(extract_image_urls(&html_content, is_search_page))
+}
+
+/// Fetches the HTML content of a given URL.
+#[instrument(skip_all)]
+pub async fn fetch_page(url: &Url, session: &Session) -> Result<String> {
+    let response = session.page(url).send().await?;
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.text().await?;
+
+    if let Some(dump) = session.debug_dump() {
+        match dump.save_page(url, status, &headers, &body) {
+            Ok(report) => info!(report = %report.display(), "Saved {} for analysis", url),
+            Err(e) => warn!(error = %e, "Couldn't save {} for analysis", url),
+        }
+    }
+
+    if !status.is_success() {
+        return Err(Error::Http { what: url.to_string(), status });
+    }
+    Ok(body)
+}
+
+/// Extracts potential image URLs from HTML content.
+/// Updated to handle modern Pinterest HTML structure.
+#[instrument(skip_all, fields(strategy = "html"))]
+pub fn extract_image_urls(html_content: &str, is_search_page: bool) -> Vec<String> {
+    let document = Html::parse_document(html_content);
+    let mut urls = HashSet::new(); // Use HashSet to avoid duplicates
+
+    // Strategy 1: Look for JSON data in scripts - usually most reliable
+    debug!(selector = "script", "Searching for image URLs in embedded JSON data");
+    let script_selector = Selector::parse("script").expect("valid selector");
+    
+    for script in document.select(&script_selector) {
+        let script_content = script.inner_html();
+        // Check for various JSON structures containing image data
+        if script_content.contains("\"images\"") || 
+           script_content.contains("\"image_url\"") || 
+           script_content.contains("\"orig\"") ||
+           script_content.contains("\"url\":") ||
+           // Pinterest search specific JSON data
+           (is_search_page && (
+               script_content.contains("\"resource_response\"") || 
+               script_content.contains("\"data\"") || 
+               script_content.contains("\"results\"") ||
+               script_content.contains("\"pins\"") ||
+               script_content.contains("\"items\"")
+           )) {
+            // Try to find JSON-like content
+            if let Some(json_start) = script_content.find('{') {
+                let potential_json = &script_content[json_start..];
+                // Very basic approach - find URL patterns in the script content
+                find_image_urls_in_text(potential_json, &mut urls);
+            }
+        }
+    }
+
+    // Strategy 2: Direct raw search for image URL patterns in the HTML - often works well with search pages
+    if urls.is_empty() || is_search_page {
+        debug!("Performing raw HTML search for Pinterest image URLs");
+        scan_urls(html_content, IMAGE_URL_PREFIXES, is_pinterest_image_url, &mut urls);
+    }
+
+    // Strategy 3: Look for modern Pinterest image containers
+    if urls.is_empty() || is_search_page {
+        debug!("Searching for images in container elements");
+        for selector_str in CONTAINER_SELECTORS {
+            if let Ok(selector) = Selector::parse(selector_str) {
+                for img in document.select(&selector) {
+                    // Try multiple attribute names Pinterest might use for image URLs
+                    let src_attributes = ["src", "data-src", "srcset", "data-srcset"];
+                    for attr in src_attributes {
+                        if let Some(src) = img.value().attr(attr) {
+                            // For srcset, we need to extract the URL from the format
+                            if attr == "srcset" || attr == "data-srcset" {
+                                for srcset_part in src.split(',') {
+                                    if let Some(url) = srcset_part.split_whitespace().next() {
+                                        if is_pinterest_image_url(url) {
+                                            urls.insert(url.to_string());
+                                        }
+                                    }
+                                }
+                            } else if is_pinterest_image_url(src) {
+                                urls.insert(src.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Strategy 4: Fallback to basic image search
+    if urls.is_empty() {
+        debug!(selector = "img", "Falling back to basic image tag search");
+        let img_selector = Selector::parse("img").expect("valid selector");
+
+        for element in document.select(&img_selector) {
+            for attr_name in ["src", "data-src", "srcset", "data-srcset"] {
+                if let Some(src) = element.value().attr(attr_name) {
+                    if is_pinterest_image_url(src) {
+                        urls.insert(src.to_string());
+                    } else if src.contains("pinterest") || src.contains("pinimg") {
+                        // Check for partial URLs that might be Pinterest related
+                        debug!(src, "Found potential Pinterest-related image");
+                    }
+                }
+            }
+        }
+    }
+    
+    // Process image URLs to get highest available quality
+    let mut processed_urls: Vec<String> = urls.into_iter()
+        .map(|url| improve_image_quality(&url))
+        .collect();
+    
+    if processed_urls.is_empty() {
+        warn!("No image URLs found using current selectors. The HTML structure might have changed.");
+        warn!("Save the page with --debug-dump <dir> to see which selectors and patterns still match.");
+    } else {
+        // Remove duplicates that might have been created during quality improvement
+        processed_urls.sort();
+        processed_urls.dedup();
+        debug!(count = processed_urls.len(), "After processing, found {} unique image URLs", processed_urls.len());
+    }
+
+    processed_urls
+}
+
+/// Selectors of the elements holding pin images, in the layouts Pinterest has used
+pub const CONTAINER_SELECTORS: &[&str] = &[
+    // Search page specific selectors (2023-2024 versions)
+    ".GrowthUnauthPinImage img",
+    ".PinImage img",
+    ".pinWrapper img",
+    "div[data-test-id=\"pin\"] img",
+    ".searchImgContainer img",
+    ".gridCentered img",
+    // Generic img tags with source set
+    "img[src*=\"pinimg.com\"]",
+    "img[data-src*=\"pinimg.com\"]",
+    // Search page specific selectors
+    ".SearchPageContent img",
+    ".Grid__Item img",
+    "div[role='list'] div[role='listitem'] img",
+    // Fallback selectors for various Pinterest layouts
+    "div.Pin img",
+    ".pinHolder img",
+    ".pinImageWrapper img",
+    "[data-test-id=\"pinrep-image\"]",
+    ".GrowthPinImage img",
+];
+
+/// Image URL prefixes looked for in raw page content
+pub const IMAGE_URL_PREFIXES: &[&str] = &[
+    "https://i.pinimg.com/originals/",
+    "https://i.pinimg.com/736x/",
+    "https://i.pinimg.com/474x/",
+    "https://i.pinimg.com/236x/",
+];
+
+/// Media URL prefixes looked for in JSON data, which also holds videos
+pub const MEDIA_URL_PREFIXES: &[&str] = &[
+    "https://i.pinimg.com/originals/",
+    "https://i.pinimg.com/736x/",
+    "https://i.pinimg.com/474x/",
+    "https://i.pinimg.com/236x/",
+    "https://www.pinimg.com/",
+    "https://pin.it/",
+    // Videos
+    "https://v.pinimg.com/",
+    "https://v1.pinimg.com/videos/",
+];
+
+/// Finds image URLs in text/JSON content
+pub fn find_image_urls_in_text(text: &str, urls: &mut HashSet<String>) {
+    scan_urls(text, MEDIA_URL_PREFIXES, |url| is_pinterest_image_url(url) || is_pinterest_video_url(url), urls);
+}
+
+/// Collects the URLs in `text` that start with one of `prefixes`, each running up to
+/// the next quote, space, closing bracket or backslash, if `accept` takes them
+pub fn scan_urls(text: &str, prefixes: &[&str], accept: impl Fn(&str) -> bool, urls: &mut HashSet<String>) {
+    for prefix in prefixes {
+        for (start, _) in text.match_indices(prefix) {
+            if let Some(end) = text[start..].find(['"', '\'', ' ', ')', '}', '\\']) {
+                let url = &text[start..start + end];
+                if accept(url) {
+                    urls.insert(url.to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Checks if a URL is likely a Pinterest image URL
+pub fn is_pinterest_image_url(url: &str) -> bool {
+    let url = url.trim();
+    
+    // More permissive check for Pinterest image URLs
+    (url.contains("pinimg.com") || url.contains("pin.it")) &&
+    (url.ends_with(".jpg") || url.ends_with(".jpeg") || 
+     url.ends_with(".png") || url.ends_with(".webp") || 
+     url.ends_with(".gif") || url.contains("/originals/") ||
+     url.contains("/736x/") || url.contains("/474x/") || url.contains("/236x/") ||
+     // Additional checks for newer URL formats
+     url.contains("images.") || url.contains("media-amazon") ||
+     url.contains("i.pinimg.com"))
+}
+
+/// Checks if a URL is a Pinterest video, as an MP4 file or an HLS playlist
+pub fn is_pinterest_video_url(url: &str) -> bool {
+    let url = url.trim();
+    url.contains("pinimg.com/videos/") || (url.contains("v.pinimg.com") && MediaKind::of(url) == MediaKind::Video)
+}
+
+/// Attempts to improve the quality of an image URL
+pub fn improve_image_quality(url: &str) -> String {
+    // Videos have their own sizes, picked from the pin's list of encodings
+    if is_pinterest_video_url(url) {
+        return url.to_string();
+    }
+
+    // Try to replace lower quality URL paths with originals
+    let improved = url
+        .replace("/236x/", "/originals/")
+        .replace("/474x/", "/originals/")
+        .replace("/736x/", "/originals/");
+    
+    // If the URL doesn't already have quality indicators, check if we can add them
+    if !improved.contains("/originals/") && !improved.contains("/736x/") {
+        // Add originals path if it looks like a Pinterest image URL pattern
+        if improved.contains("pinimg.com") && improved.contains("/") {
+            // Try to insert originals into the path
+            let parts: Vec<&str> = improved.split('/').collect();
+            if parts.len() >= 4 {
+                // Very basic attempt to insert quality - would need better path analysis for production
+                let mut result = String::new();
+                let mut inserted = false;
+                for (i, part) in parts.iter().enumerate() {
+                    if !inserted && i > 2 && i < parts.len() - 1 {
+                        result.push_str("/originals/");
+                        inserted = true;
+                    } else {
+                        result.push('/');
+                        result.push_str(part);
+                    }
+                }
+                if inserted {
+                    return result.trim_start_matches('/').to_string();
+                }
+            }
+        }
+    }
+    
+    improved
+}
+
+/// Try to fetch images from modern Pinterest search pages with source_module_id
+#[instrument(skip_all, fields(strategy = "modern_search"))]
+pub async fn try_fetch_from_modern_search(url: &Url, session: &Session) -> Result<Vec<String>> {
+    debug!("Parsing modern Pinterest search URL");
+    
+    // Extract the module ID 
+    let module_id = url.query_pairs()
+        .find_map(|(key, value)| {
+            if key == "source_module_id" {
+                Some(value.to_string())
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| Error::InvalidInput("No source_module_id found in URL".to_string()))?;
+    
+    // Extract the search query
+    let search_query = url.query_pairs()
+        .find_map(|(key, value)| {
+            if key == "q" {
+                Some(value.to_string())
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| Error::InvalidInput("No search query found in URL".to_string()))?;
+    
+    debug!(module_id = %module_id, query = %search_query, "Parsed modern search URL");
+    
+    // Fetch the search page, which also picks up any cookies or tokens it sets
+    debug!("Fetching search page");
+    let html_content = fetch_page(url, session).await?;
+    debug!(bytes = html_content.len(), "Got initial page, extracting from HTML");
+    
+    // Look for image URLs directly in the HTML content
+    let mut urls = HashSet::new();
+    scan_urls(&html_content, IMAGE_URL_PREFIXES, is_pinterest_image_url, &mut urls);
+    
+    // Also try to extract JSON data that might contain image URLs
+    debug!("Extracting from JSON data in scripts");
+    let document = Html::parse_document(&html_content);
+    let script_selector = Selector::parse("script").expect("valid selector");
+    
+    for script in document.select(&script_selector) {
+        let script_content = script.inner_html();
+        if script_content.contains("\"sourceUrl\"") || 
+           script_content.contains("\"images\"") || 
+           script_content.contains("\"image_url\"") {
+            find_image_urls_in_text(&script_content, &mut urls);
+        }
+    }
+    
+    // Process URLs for highest quality
+    let mut processed_urls: Vec<String> = urls.into_iter()
+        .map(|url| improve_image_quality(&url))
+        .collect();
+    
+    // Remove duplicates
+    processed_urls.sort();
+    processed_urls.dedup();
+    
+    debug!(count = processed_urls.len(), "Found unique images on modern search page");
+    Ok(processed_urls)
+}
+
+/// Most effective way to extract Pinterest images in 2024 by directly accessing their internal
+/// data structure from within the page HTML
+#[instrument(skip_all, fields(strategy = "direct"))]
+pub async fn try_direct_pinterest_extraction(url: &Url, session: &Session) -> Result<Vec<String>> {
+    debug!("Using direct Pinterest data extraction");
+    
+    debug!("Making initial request to Pinterest");
+    let html_content = fetch_page(url, session).await?;
+    debug!(bytes = html_content.len(), "Received page content");
+    
+    // Extract image URLs using three different methods
+    let mut all_urls = HashSet::new();
+    
+    // Method 1: Search for specific image constants in initial_state data
+    if let Some(json_data) = initial_state(&html_content) {
+        debug!(bytes = json_data.len(), "Found initial_state data");
+
+        // Look for all URLs in the JSON data
+        find_image_urls_in_text(json_data, &mut all_urls);
+    }
+    
+    // Method 2: Look directly for "original": { "url": "https://... patterns
+    // This is specific to Pinterest's image data structure
+    debug!("Searching for Pinterest's original image URL patterns");
+    find_original_urls(&html_content, &mut all_urls);
+    
+    // Method 3: General image URL pattern search throughout the entire HTML
+    scan_urls(&html_content, IMAGE_URL_PREFIXES, is_pinterest_image_url, &mut all_urls);
+    
+    // Process the URLs to get the highest quality versions
+    let mut processed_urls: Vec<String> = all_urls.into_iter()
+        .map(|url| improve_image_quality(&url))
+        .collect();
+    
+    // Remove duplicates and sort
+    processed_urls.sort();
+    processed_urls.dedup();
+    
+    if processed_urls.is_empty() {
+        return Err(Error::UnexpectedResponse("Could not extract any image URLs".to_string()));
+    }
+    
+    debug!(count = processed_urls.len(), "Direct extraction found unique images");
+    Ok(processed_urls)
+}
+
+/// Finds the URLs of `"original": { "url": "..." }` objects, the full-size renditions in
+/// Pinterest's page data. The `"url"` key has to follow within a few bytes.
+fn find_original_urls(html_content: &str, urls: &mut HashSet<String>) {
+    const ORIGINAL: &str = "\"original\"";
+    const URL_KEY: &str = "\"url\"";
+
+    for (pos, _) in html_content.match_indices(ORIGINAL) {
+        let after = &html_content[pos + ORIGINAL.len()..];
+        let Some(key_pos) = prefix_within(after, 100).find(URL_KEY) else { continue };
+        let value = after[key_pos + URL_KEY.len()..].trim_start();
+        let Some(value) = value.strip_prefix(':').map(str::trim_start).and_then(|v| v.strip_prefix('"')) else {
+            continue;
+        };
+        let Some(end) = value.find('"') else { continue };
+        let url = &value[..end];
+        if is_pinterest_image_url(url) || url.contains("pinimg.com") {
+            urls.insert(url.to_string());
+        }
+    }
+}
+
+/// The start of `text`, at most `max_bytes` long and cut at a character boundary
+fn prefix_within(text: &str, max_bytes: usize) -> &str {
+    let mut end = max_bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// The `initial_state` object embedded in a page, as JSON text
+pub fn initial_state(html_content: &str) -> Option<&str> {
+    let pos = html_content.find("\"initial_state\"")?;
+    // Find the actual JSON object start
+    let json_start = pos + html_content[pos..].find('{')?;
+
+    // Find the end of this JSON object by counting brackets
+    let mut bracket_count = 1;
+    for (i, c) in html_content[json_start + 1..].char_indices() {
+        if c == '{' {
+            bracket_count += 1;
+        } else if c == '}' {
+            bracket_count -= 1;
+            if bracket_count == 0 {
+                return Some(&html_content[json_start..json_start + i + 2]);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scans_text_for_image_urls() {
+        let text = r#"{"url":"https://i.pinimg.com/736x/aa/bb/one.jpg"} <img src='https://i.pinimg.com/236x/aa/bb/two.png'>
+            "https:\/\/x" "https://i.pinimg.com/originals/aa/bb/three.jpg\u0022" https://example.com/four.jpg"#;
+        let mut urls = HashSet::new();
+        scan_urls(text, IMAGE_URL_PREFIXES, is_pinterest_image_url, &mut urls);
+        let mut urls: Vec<String> = urls.into_iter().collect();
+        urls.sort();
+        assert_eq!(urls, [
+            "https://i.pinimg.com/236x/aa/bb/two.png",
+            "https://i.pinimg.com/736x/aa/bb/one.jpg",
+            "https://i.pinimg.com/originals/aa/bb/three.jpg",
+        ]);
+    }
+}
@@ -0,0 +1,256 @@
+//! Searches: what to search for, and how to ask Pinterest for it.
+//!
+//! A search starts from a search URL, whose scope and filter parameters can be
+//! changed or added to from the command line or the web form: the kind of results
+//! (pins, boards, videos, users), refinement terms like the chips of Pinterest's
+//! guided search, and filters such as a color. The search page URL and the options
+//! of `BaseSearchResource` are both built from it, with every value encoded by
+//! `url` and `serde_json` so queries with quotes, `&` or any other character survive.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use tracing::debug;
+use url::Url;
+
+use crate::boards::{folder_name, stream_board_images};
+use crate::error::Result;
+use crate::extract::PinCandidate;
+use crate::request::ResourceRequest;
+use crate::resources::{self, fetch_feed};
+use crate::session::Session;
+use crate::source::SearchScope;
+
+/// URL parameters of a search page that aren't result filters
+const NON_FILTER_PARAMS: &[&str] = &["source_module_id", "eq", "etslf", "term_meta[]"];
+
+/// Search options from the command line or the web form, applied to a search URL
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct SearchArgs {
+    /// Kind of search results to download, instead of the search URL's
+    #[cfg_attr(feature = "clap", arg(long, value_enum))]
+    pub scope: Option<SearchScope>,
+
+    /// Term to narrow a search with, like Pinterest's guided search chips (repeatable)
+    #[cfg_attr(feature = "clap", arg(long = "refine", value_name = "TERM"))]
+    pub refinements: Vec<String>,
+
+    /// Search filter as KEY=VALUE, e.g. color=red (repeatable)
+    #[cfg_attr(feature = "clap", arg(long = "search-filter", value_name = "KEY=VALUE", value_parser = parse_filter))]
+    pub filters: Vec<(String, String)>,
+}
+
+impl SearchArgs {
+    /// Whether any search option was given
+    pub fn is_set(&self) -> bool {
+        self.scope.is_some() || !self.refinements.is_empty() || !self.filters.is_empty()
+    }
+}
+
+/// Parses a `KEY=VALUE` filter
+pub fn parse_filter(filter: &str) -> Result<(String, String), String> {
+    match filter.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.trim().to_string())),
+        _ => Err(format!("invalid filter '{}': expected KEY=VALUE", filter)),
+    }
+}
+
+/// A search to run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Search {
+    pub query: String,
+    pub scope: SearchScope,
+    pub refinements: Vec<String>,
+    /// Parameters of the search URL besides the query: filters, and others such as `source_module_id`
+    pub params: Vec<(String, String)>,
+}
+
+impl Search {
+    /// The search of a search URL, with the given options applied: a scope replaces
+    /// the URL's, refinements are added, and filters are added or replace the URL's
+    pub fn new(query: &str, scope: SearchScope, url_params: &[(String, String)], args: &SearchArgs) -> Self {
+        let mut params: Vec<(String, String)> = url_params
+            .iter()
+            .filter(|(key, _)| !args.filters.iter().any(|(filter, _)| filter == key))
+            .cloned()
+            .collect();
+        params.extend(args.filters.iter().cloned());
+        Self {
+            query: query.trim().to_string(),
+            scope: args.scope.unwrap_or(scope),
+            refinements: args.refinements.iter().map(|term| term.trim().to_string()).filter(|term| !term.is_empty()).collect(),
+            params,
+        }
+    }
+
+    /// What is searched for: the query followed by any refinement terms
+    pub fn terms(&self) -> String {
+        std::iter::once(self.query.as_str())
+            .chain(self.refinements.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// How the search was started, as Pinterest records it: typed, or refined with guide terms
+    fn origin(&self) -> &'static str {
+        if self.refinements.is_empty() {
+            "typed"
+        } else {
+            "guide"
+        }
+    }
+
+    /// The filters of the search, the URL parameters that narrow down its results
+    pub fn filters(&self) -> impl Iterator<Item = &(String, String)> {
+        self.params.iter().filter(|(key, _)| !NON_FILTER_PARAMS.contains(&key.as_str()))
+    }
+
+    /// The search page's URL on a Pinterest host
+    pub fn page_url(&self, base_url: &Url) -> Result<Url> {
+        let mut url = base_url.join(&format!("/search/{}/", self.scope.as_str()))?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.clear().append_pair("q", &self.terms()).append_pair("rs", self.origin());
+            for (index, term) in self.refinements.iter().enumerate() {
+                query.append_pair("term_meta[]", &format!("{}|guide|word|{}", term, index + 1));
+            }
+            for (key, value) in &self.params {
+                query.append_pair(key, value);
+            }
+        }
+        Ok(url)
+    }
+
+    /// The search page's path and query, as the `source_url` of resource requests
+    pub fn source_url(&self) -> String {
+        let url = self.page_url(&Url::parse("https://www.pinterest.com/").expect("valid URL")).expect("valid search path");
+        match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        }
+    }
+
+    /// Options of the `BaseSearchResource` request for the search
+    pub fn options(&self) -> Value {
+        let filters: Map<String, Value> = self.filters().map(|(key, value)| (key.clone(), json!(value))).collect();
+        let source_module_id = self.params.iter().find(|(key, _)| key == "source_module_id").map(|(_, value)| value);
+        json!({
+            "query": self.terms(),
+            "scope": self.scope.as_str(),
+            "rs": self.origin(),
+            "filters": filters,
+            "source_url": self.source_url(),
+            "source_module_id": source_module_id,
+            "auto_correction_disabled": false,
+            "redux_normalize_feed": true,
+        })
+    }
+}
+
+/// Fetches the results of a search from `BaseSearchResource`, page by page, until
+/// `limit` results (0 = no limit): pins, or boards or users for those scopes
+pub async fn fetch_results(session: &Session, search: &Search, limit: usize) -> Result<Vec<Value>> {
+    let request = ResourceRequest::new("BaseSearchResource").source_url(search.source_url()).options(search.options());
+    fetch_feed(session, request, limit).await
+}
+
+/// Streams the images of a search from Pinterest's search resource, a page of results
+/// at a time: the pins found, or for a board search the pins of each board found, in
+/// a folder per board
+pub fn stream_images<'a>(session: &'a Session, search: &Search, limit: usize) -> BoxStream<'a, Result<PinCandidate>> {
+    let request = ResourceRequest::new("BaseSearchResource").source_url(search.source_url()).options(search.options());
+    let pages = resources::feed_pages(session, request, 0);
+    if search.scope != SearchScope::Boards {
+        return resources::limited(resources::feed_media(pages).map_ok(PinCandidate::from).boxed(), limit);
+    }
+
+    let images = pages
+        .map_ok(move |boards| {
+            stream::iter(boards).flat_map(move |board| {
+                let (Some(id), Some(board_url)) = (resources::resource_id(&board), board["url"].as_str()) else {
+                    return stream::empty().boxed();
+                };
+                let slug = board_url.trim_matches('/').rsplit('/').next().unwrap_or(board_url);
+                debug!(board = %board_url, "Fetching board found by search");
+                stream_board_images(session, &id, board_url, Some(&folder_name(slug)), None, 0)
+            })
+        })
+        .try_flatten();
+    resources::limited(images.boxed(), limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(query: &str, params: &[(&str, &str)], args: &SearchArgs) -> Search {
+        let params: Vec<(String, String)> = params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Search::new(query, SearchScope::Pins, &params, args)
+    }
+
+    #[test]
+    fn applies_options_to_the_url_search() {
+        let args = SearchArgs {
+            scope: Some(SearchScope::Videos),
+            refinements: vec!["dark".into(), " ".into()],
+            filters: vec![("color".into(), "red".into())],
+        };
+        let search = search("cats", &[("color", "blue"), ("source_module_id", "abc")], &args);
+        assert_eq!(search.scope, SearchScope::Videos);
+        assert_eq!(search.terms(), "cats dark");
+        assert_eq!(search.filters().cloned().collect::<Vec<_>>(), [("color".to_string(), "red".to_string())]);
+        assert_eq!(
+            search.source_url(),
+            "/search/videos/?q=cats+dark&rs=guide&term_meta%5B%5D=dark%7Cguide%7Cword%7C1&source_module_id=abc&color=red"
+        );
+    }
+
+    #[test]
+    fn encodes_queries_with_special_characters() {
+        let search = search(r#"tom & jerry "1940" café"#, &[], &SearchArgs::default());
+        assert_eq!(search.source_url(), "/search/pins/?q=tom+%26+jerry+%221940%22+caf%C3%A9&rs=typed");
+        let options = search.options();
+        assert_eq!(options["query"], r#"tom & jerry "1940" café"#);
+        assert_eq!(options["filters"], json!({}));
+        assert_eq!(options["source_module_id"], Value::Null);
+
+        let url = search.page_url(&Url::parse("https://www.pinterest.com/").unwrap()).unwrap();
+        let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert_eq!(query[0], ("q".to_string(), r#"tom & jerry "1940" café"#.to_string()));
+    }
+
+    #[test]
+    fn parses_filters() {
+        assert_eq!(parse_filter("color = red"), Ok(("color".into(), "red".into())));
+        assert_eq!(parse_filter("type=video=x"), Ok(("type".into(), "video=x".into())));
+        assert!(parse_filter("color").is_err());
+        assert!(parse_filter("=red").is_err());
+    }
+
+    #[tokio::test]
+    async fn fetches_results_with_encoded_options() {
+        use axum::extract::Query;
+        use axum::routing::get;
+        use crate::test_support;
+        use axum::{Json, Router};
+        use std::collections::HashMap;
+
+        async fn base_search(Query(params): Query<HashMap<String, String>>) -> Json<Value> {
+            let data: Value = serde_json::from_str(&params["data"]).unwrap();
+            let options = &data["options"];
+            assert_eq!(options["query"], r#"a&b "c""#);
+            assert_eq!(options["filters"], json!({ "color": "red" }));
+            assert_eq!(params["source_url"], "/search/pins/?q=a%26b+%22c%22&rs=typed&color=red");
+            Json(json!({ "resource_response": { "data": { "results": [{ "id": "1" }, { "id": "2" }] }, "bookmark": "-end-" } }))
+        }
+
+        let app = Router::new().route("/resource/BaseSearchResource/get/", get(base_search));
+        let session = test_support::session(&test_support::serve(app).await, &[]);
+
+        let args = SearchArgs { filters: vec![("color".into(), "red".into())], ..Default::default() };
+        let results = fetch_results(&session, &search(r#"a&b "c""#, &[], &args), 0).await.unwrap();
+        assert_eq!(results.len(), 2);
+    }
+}
@@ -0,0 +1,269 @@
+//! The HTTP session used for every request to Pinterest.
+//!
+//! Per account, a single `Client` backed by the persistent cookie jar, so session cookies and the
+//! CSRF token Pinterest hands out on the first page load carry through to later API
+//! calls, and the updated jar can be saved back to disk at the end of a run.
+//! Requests to Pinterest's own endpoints are built against the session's base URL,
+//! and images and videos can be fetched from another media host, so tests can point
+//! both at local mock servers.
+
+use reqwest::{redirect, Client, RequestBuilder, StatusCode};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::cookies::{self, CookieJar};
+use crate::diagnostics::DebugDump;
+use crate::error::Result;
+use crate::request::ResourceRequest;
+use crate::resolve::{is_pinterest_host, is_short_link_host};
+
+/// Browser user agent sent with every request
+const USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+/// Where Pinterest's pages and internal resources are served from
+const PINTEREST_BASE_URL: &str = "https://www.pinterest.com/";
+
+/// Name of the cookie holding Pinterest's CSRF token
+const CSRF_COOKIE: &str = "csrftoken";
+
+/// Name of the cookie Pinterest keeps its (possibly logged-in) session in
+const SESSION_COOKIE: &str = "_pinterest_sess";
+
+/// HTTP client and cookie jar shared by all requests; cheap to clone
+#[derive(Clone)]
+pub struct Session {
+    client: Client,
+    /// Same as `client`, but returns redirects instead of following them
+    redirect_client: Client,
+    jar: CookieJar,
+    account: String,
+    base_url: Url,
+    /// Server standing in for Pinterest's pages, when they aren't fetched from Pinterest
+    page_host: Option<Url>,
+    /// Server standing in for Pinterest's image and video hosts (`*.pinimg.com`)
+    media_host: Option<Url>,
+    /// Where to save the pages and resource responses fetched, for `--debug-dump`
+    debug_dump: Option<Arc<DebugDump>>,
+}
+
+impl Session {
+    /// Creates a session from an account's stored cookies
+    pub fn load(account: &str) -> Result<Self> {
+        Self::with_jar(cookies::load_jar(account)?, account)
+    }
+
+    /// Creates a session around a cookie jar
+    pub fn with_jar(jar: CookieJar, account: &str) -> Result<Self> {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .cookie_provider(jar.clone())
+            .build()?;
+        let redirect_client = Client::builder()
+            .user_agent(USER_AGENT)
+            .cookie_provider(jar.clone())
+            .redirect(redirect::Policy::none())
+            .build()?;
+        let base_url = Url::parse(PINTEREST_BASE_URL)?;
+        Ok(Self {
+            client,
+            redirect_client,
+            jar,
+            account: account.to_string(),
+            base_url,
+            page_host: None,
+            media_host: None,
+            debug_dump: None,
+        })
+    }
+
+    /// Sends Pinterest requests to another server, e.g. a local mock: its resources,
+    /// and the pages of Pinterest URLs, keeping their path and query
+    pub fn with_base_url(mut self, base_url: Url) -> Self {
+        self.page_host = Some(base_url.clone());
+        self.base_url = base_url;
+        self
+    }
+
+    /// Fetches images and videos from another server instead of `*.pinimg.com`,
+    /// keeping their path and query
+    pub fn with_media_base_url(mut self, media_base_url: Url) -> Self {
+        self.media_host = Some(media_base_url);
+        self
+    }
+
+    /// Saves every page fetched, with what's embedded in it and a report of what
+    /// matched, to `dump`
+    pub fn with_debug_dump(mut self, dump: DebugDump) -> Self {
+        self.debug_dump = Some(Arc::new(dump));
+        self
+    }
+
+    /// Where the pages fetched are saved, if anywhere
+    pub fn debug_dump(&self) -> Option<&DebugDump> {
+        self.debug_dump.as_deref()
+    }
+
+    /// Where to fetch an image or video from: its own URL, or the same path on the
+    /// media server set with [`Session::with_media_base_url`]
+    pub fn media_url(&self, url: &Url) -> Url {
+        let is_media_host = url.host_str().is_some_and(|host| host == "pinimg.com" || host.ends_with(".pinimg.com"));
+        match &self.media_host {
+            Some(media_host) if is_media_host => rebase(url, media_host),
+            _ => url.clone(),
+        }
+    }
+
+    /// Where to fetch a page from: its own URL, or the same path on the server set
+    /// with [`Session::with_base_url`] for Pinterest pages and short links
+    fn page_url(&self, url: &Url) -> Url {
+        let is_pinterest = url.host_str().is_some_and(|host| {
+            is_pinterest_host(host) || is_short_link_host(host) || host.ends_with(".pinterest.com")
+        });
+        match &self.page_host {
+            Some(page_host) if is_pinterest => rebase(url, page_host),
+            _ => url.clone(),
+        }
+    }
+
+    /// The underlying client, for plain requests such as image downloads
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Starts a GET request that doesn't follow redirects, to resolve them one hop at a time
+    pub fn get_without_redirects(&self, url: &Url) -> RequestBuilder {
+        self.redirect_client.get(self.page_url(url))
+    }
+
+    /// Starts a GET request for an HTML page
+    pub fn page(&self, url: &Url) -> RequestBuilder {
+        self.client
+            .get(self.page_url(url))
+            .header("Accept", "text/html,application/xhtml+xml,application/xml")
+            .header("Referer", self.base_url.as_str())
+    }
+
+    /// Starts a GET request for an internal resource such as `BoardFeedResource`, with
+    /// the headers Pinterest's web app sends along: the handler of the page it's made
+    /// from, and the CSRF token from the cookie jar if one has been issued
+    pub fn resource(&self, resource: &ResourceRequest) -> Result<RequestBuilder> {
+        let url = resource.url(&self.base_url)?;
+        let referer = self.base_url.join(resource.page().trim_start_matches('/'))?;
+        let mut request = self
+            .client
+            .get(url)
+            .header("Accept", "application/json, text/javascript, */*; q=0.01")
+            .header("Referer", referer.as_str())
+            .header("X-Requested-With", "XMLHttpRequest")
+            .header("X-Pinterest-AppState", "active")
+            .header("X-Pinterest-PWS-Handler", resource.pws_handler())
+            .header("X-Pinterest-Source-Url", resource.page());
+        if let Some(token) = self.csrf_token() {
+            request = request.header("X-CSRFToken", token);
+        }
+        Ok(request)
+    }
+
+    /// Fetches an internal resource, returning the status and body of the response.
+    /// With a debug dump set, the response is saved to it along with its headers.
+    pub async fn fetch_resource(&self, resource: &ResourceRequest) -> Result<(StatusCode, String)> {
+        let response = self.resource(resource)?.send().await?;
+        let status = response.status();
+        let url = response.url().clone();
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+
+        if let Some(dump) = self.debug_dump() {
+            match dump.save_resource(resource.name(), &url, status, &headers, &body) {
+                Ok(path) => info!(path = %path.display(), "Saved {} response for analysis", resource.name()),
+                Err(e) => warn!(error = %e, "Couldn't save {} response for analysis", resource.name()),
+            }
+        }
+        Ok((status, body))
+    }
+
+    /// The CSRF token Pinterest set in the cookie jar, if any
+    pub fn csrf_token(&self) -> Option<String> {
+        self.cookie(CSRF_COOKIE)
+    }
+
+    /// Whether the jar holds a Pinterest session cookie, i.e. whether it's worth
+    /// asking Pinterest who is logged in
+    pub fn has_session_cookie(&self) -> bool {
+        self.cookie(SESSION_COOKIE).is_some()
+    }
+
+    /// Value of a cookie the jar would send to Pinterest
+    fn cookie(&self, name: &str) -> Option<String> {
+        let store = self.jar.lock().unwrap_or_else(|e| e.into_inner());
+        let value = store
+            .matches(&self.base_url)
+            .into_iter()
+            .find(|cookie| cookie.name() == name)
+            .map(|cookie| cookie.value().to_string());
+        value
+    }
+
+    /// Writes the cookie jar back to disk, dropping expired cookies
+    pub fn save(&self) -> Result<()> {
+        cookies::save_jar(&self.jar, &self.account)?;
+        debug!(account = %self.account, "Session saved");
+        Ok(())
+    }
+}
+
+/// The URL with the path and query of `url` on the server `base`
+fn rebase(url: &Url, base: &Url) -> Url {
+    let mut rebased = base.clone();
+    rebased.set_path(url.path());
+    rebased.set_query(url.query());
+    rebased
+}
+
+/// Sessions of every account used so far by the web server, loaded on first use
+/// and shared by all jobs of the same account
+#[derive(Clone, Default)]
+pub struct SessionPool {
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+impl SessionPool {
+    /// Gets the session of an account, loading its cookies if needed
+    pub fn get(&self, account: &str) -> Result<Session> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(session) = sessions.get(account) {
+            return Ok(session.clone());
+        }
+        let session = Session::load(account)?;
+        sessions.insert(account.to_string(), session.clone());
+        Ok(session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+
+    #[test]
+    fn routes_pages_and_media_to_the_mock_servers() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        let jar = Arc::new(CookieStoreMutex::new(CookieStore::default()));
+        let session = Session::with_jar(jar, "test").unwrap();
+        let tabby = url("https://i.pinimg.com/originals/aa/01/tabby.jpg");
+        let board = url("https://www.pinterest.com/alice/cats/?q=x");
+        assert_eq!(session.media_url(&tabby), tabby);
+        assert_eq!(session.page_url(&board), board);
+
+        let session = session.with_base_url(url("http://127.0.0.1:1/")).with_media_base_url(url("http://127.0.0.1:2/"));
+        assert_eq!(session.media_url(&tabby).as_str(), "http://127.0.0.1:2/originals/aa/01/tabby.jpg");
+        assert_eq!(session.page_url(&board).as_str(), "http://127.0.0.1:1/alice/cats/?q=x");
+        // Other sites are fetched as they are
+        let other = url("https://example.com/cat.jpg");
+        assert_eq!(session.media_url(&other), other);
+        assert_eq!(session.page_url(&other), other);
+    }
+}
@@ -0,0 +1,270 @@
+//! What a Pinterest URL points to.
+//!
+//! URLs are parsed into a [`PinterestSource`] from their path segments and query
+//! parameters (after [`crate::resolve`] has normalized them), and each kind of source
+//! is downloaded by its own extractor.
+
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use url::Url;
+
+use crate::error::Error;
+use crate::resolve::is_pinterest_host;
+
+/// First path segments of Pinterest pages that aren't user profiles
+const RESERVED_PATHS: &[&str] = &[
+    "pin", "search", "ideas", "topics", "today", "explore", "settings", "resource", "business",
+    "categories", "news_hub", "me", "_", "login", "logout", "about", "url_shortener", "videos",
+    "password", "signup", "homefeed", "notifications", "messages",
+];
+
+/// Profile tabs, which show a user's profile rather than one of their boards
+const PROFILE_TABS: &[&str] = &["_saved", "_created", "_shop", "pins", "boards", "followers", "following"];
+
+/// Search parameters that aren't filters
+const SEARCH_PARAMS: &[&str] = &["q", "rs"];
+
+/// What kind of results a search returns
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SearchScope {
+    Pins,
+    Boards,
+    Videos,
+    #[cfg_attr(feature = "clap", value(alias = "people"))]
+    Users,
+}
+
+impl SearchScope {
+    fn parse(segment: &str) -> Option<Self> {
+        match segment {
+            "pins" => Some(SearchScope::Pins),
+            "boards" => Some(SearchScope::Boards),
+            "videos" => Some(SearchScope::Videos),
+            "users" | "people" => Some(SearchScope::Users),
+            _ => None,
+        }
+    }
+
+    /// Name of the scope in Pinterest's URLs and resources
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SearchScope::Pins => "pins",
+            SearchScope::Boards => "boards",
+            SearchScope::Videos => "videos",
+            SearchScope::Users => "users",
+        }
+    }
+}
+
+/// A downloadable Pinterest page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinterestSource {
+    /// A single pin, `/pin/<id>/`
+    Pin { id: String },
+    /// A board, `/<user>/<board>/`
+    Board { user: String, slug: String },
+    /// A section of a board, `/<user>/<board>/<section>/`
+    Section { user: String, board: String, section: String },
+    /// A user profile, `/<user>/` or one of its tabs
+    User { name: String },
+    /// Search results, `/search/<scope>/?q=...` plus any filter parameters
+    Search { query: String, scope: SearchScope, filters: Vec<(String, String)> },
+    /// A topic page, `/ideas/<name>/<id>/` or `/topics/<name>/`
+    Topic { name: String },
+    /// The ideas (explore) landing page, `/ideas/`
+    Ideas,
+}
+
+impl PinterestSource {
+    /// Parses a Pinterest URL; `None` for other sites and for Pinterest pages that
+    /// aren't a downloadable source
+    pub fn parse(url: &Url) -> Option<Self> {
+        if !url.host_str().is_some_and(is_pinterest_host) {
+            return None;
+        }
+        let segments: Vec<String> = url
+            .path_segments()?
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+        match segments.as_slice() {
+            ["pin", id, ..] if is_pin_id(id) => Some(PinterestSource::Pin { id: id.to_string() }),
+            ["search"] => Self::parse_search(url, SearchScope::Pins),
+            ["search", scope] => Self::parse_search(url, SearchScope::parse(scope)?),
+            ["ideas"] => Some(PinterestSource::Ideas),
+            ["ideas", name, ..] | ["topics", name, ..] => Some(PinterestSource::Topic { name: name.to_string() }),
+            [first, ..] if RESERVED_PATHS.contains(first) || !is_user_name(first) => None,
+            [name] => Some(PinterestSource::User { name: name.to_string() }),
+            [name, tab] if PROFILE_TABS.contains(tab) => Some(PinterestSource::User { name: name.to_string() }),
+            [user, slug] if !slug.starts_with('_') => {
+                Some(PinterestSource::Board { user: user.to_string(), slug: slug.to_string() })
+            }
+            [user, board, section] if !board.starts_with('_') && !section.starts_with('_') => Some(PinterestSource::Section {
+                user: user.to_string(),
+                board: board.to_string(),
+                section: section.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn parse_search(url: &Url, scope: SearchScope) -> Option<Self> {
+        let query = url
+            .query_pairs()
+            .find(|(key, _)| key == "q")
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|query| !query.is_empty())?;
+        let filters = url
+            .query_pairs()
+            .filter(|(key, _)| !SEARCH_PARAMS.contains(&key.as_ref()))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        Some(PinterestSource::Search { query, scope, filters })
+    }
+
+    /// Short name of the kind of source, e.g. `board`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PinterestSource::Pin { .. } => "pin",
+            PinterestSource::Board { .. } => "board",
+            PinterestSource::Section { .. } => "section",
+            PinterestSource::User { .. } => "user",
+            PinterestSource::Search { .. } => "search",
+            PinterestSource::Topic { .. } => "topic",
+            PinterestSource::Ideas => "ideas",
+        }
+    }
+}
+
+impl std::str::FromStr for SearchScope {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Error> {
+        Self::parse(&value.trim().to_lowercase())
+            .ok_or_else(|| Error::InvalidInput(format!("Invalid search scope '{}': expected pins, boards, videos or users", value)))
+    }
+}
+
+impl fmt::Display for PinterestSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PinterestSource::Pin { id } => write!(f, "pin {}", id),
+            PinterestSource::Board { user, slug } => write!(f, "board {}/{}", user, slug),
+            PinterestSource::Section { user, board, section } => write!(f, "section {}/{}/{}", user, board, section),
+            PinterestSource::User { name } => write!(f, "user {}", name),
+            PinterestSource::Search { query, scope, .. } => write!(f, "{} search '{}'", scope.as_str(), query),
+            PinterestSource::Topic { name } => write!(f, "topic {}", name),
+            PinterestSource::Ideas => write!(f, "ideas"),
+        }
+    }
+}
+
+fn is_pin_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn is_user_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Option<PinterestSource> {
+        PinterestSource::parse(&Url::parse(input).unwrap())
+    }
+
+    fn board(user: &str, slug: &str) -> Option<PinterestSource> {
+        Some(PinterestSource::Board { user: user.into(), slug: slug.into() })
+    }
+
+    fn search(query: &str, scope: SearchScope, filters: &[(&str, &str)]) -> Option<PinterestSource> {
+        let filters = filters.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Some(PinterestSource::Search { query: query.into(), scope, filters })
+    }
+
+    #[test]
+    fn parses_sources() {
+        let pin = |id: &str| Some(PinterestSource::Pin { id: id.into() });
+        let user = |name: &str| Some(PinterestSource::User { name: name.into() });
+        let topic = |name: &str| Some(PinterestSource::Topic { name: name.into() });
+        let section = |user: &str, board: &str, section: &str| {
+            Some(PinterestSource::Section { user: user.into(), board: board.into(), section: section.into() })
+        };
+
+        let table: &[(&str, Option<PinterestSource>)] = &[
+            // Pins
+            ("https://www.pinterest.com/pin/123456789/", pin("123456789")),
+            ("https://www.pinterest.com/pin/123456789", pin("123456789")),
+            ("https://www.pinterest.com/pin/AVx3_abc-1/", pin("AVx3_abc-1")),
+            ("https://www.pinterest.com/pin/123/sent/?invite_code=x", pin("123")),
+            ("https://www.pinterest.com/pin/", None),
+            // Boards, including names that look like query strings
+            ("https://www.pinterest.com/alice/cats/", board("alice", "cats")),
+            ("https://www.pinterest.com/alice/faq=/", board("alice", "faq=")),
+            ("https://www.pinterest.com/alice/query=art/", board("alice", "query=art")),
+            ("https://www.pinterest.com/alice/search/", board("alice", "search")),
+            ("https://www.pinterest.com/alice/source_module_id/", board("alice", "source_module_id")),
+            ("https://www.pinterest.com/alice/caf%C3%A9/", board("alice", "café")),
+            ("https://www.pinterest.com/alice/cats/?q=dogs", board("alice", "cats")),
+            ("https://www.pinterest.com/alice.b/cats/", board("alice.b", "cats")),
+            // Sections
+            ("https://www.pinterest.com/alice/cats/kittens/", section("alice", "cats", "kittens")),
+            ("https://www.pinterest.com/alice/cats/_tools/", None),
+            ("https://www.pinterest.com/alice/cats/kittens/more/", None),
+            // Users and their tabs
+            ("https://www.pinterest.com/alice/", user("alice")),
+            ("https://www.pinterest.com/alice/_saved/", user("alice")),
+            ("https://www.pinterest.com/alice/_created/", user("alice")),
+            ("https://www.pinterest.com/alice/pins/", user("alice")),
+            ("https://www.pinterest.com/alice/boards/", user("alice")),
+            ("https://www.pinterest.com/alice/_unknown/", None),
+            // Searches
+            ("https://www.pinterest.com/search/pins/?q=cats", search("cats", SearchScope::Pins, &[])),
+            (
+                "https://www.pinterest.com/search/pins/?q=dark%20gothic%20art&rs=typed",
+                search("dark gothic art", SearchScope::Pins, &[]),
+            ),
+            ("https://www.pinterest.com/search/boards/?q=cats", search("cats", SearchScope::Boards, &[])),
+            ("https://www.pinterest.com/search/videos/?q=cats", search("cats", SearchScope::Videos, &[])),
+            ("https://www.pinterest.com/search/users/?q=cats", search("cats", SearchScope::Users, &[])),
+            ("https://www.pinterest.com/search/?q=cats", search("cats", SearchScope::Pins, &[])),
+            (
+                "https://www.pinterest.com/search/pins/?q=cats&source_module_id=abc&eq=cat",
+                search("cats", SearchScope::Pins, &[("source_module_id", "abc"), ("eq", "cat")]),
+            ),
+            ("https://www.pinterest.com/search/pins/?q=a+b%2Bc", search("a b+c", SearchScope::Pins, &[])),
+            ("https://www.pinterest.com/search/pins/", None),
+            ("https://www.pinterest.com/search/pins/?q=%20", None),
+            ("https://www.pinterest.com/search/pins/?query=cats", None),
+            ("https://www.pinterest.com/search/stories/?q=cats", None),
+            // Topics and ideas
+            ("https://www.pinterest.com/ideas/", Some(PinterestSource::Ideas)),
+            ("https://www.pinterest.com/ideas/dark-art/912345678/", topic("dark-art")),
+            ("https://www.pinterest.com/topics/dark-art/", topic("dark-art")),
+            // Other Pinterest pages
+            ("https://www.pinterest.com/", None),
+            ("https://www.pinterest.com/today/", None),
+            ("https://www.pinterest.com/settings/profile/", None),
+            ("https://www.pinterest.com/resource/BoardResource/get/", None),
+            ("https://www.pinterest.com/me/pins/", None),
+            // Regional hosts parse the same way
+            ("https://se.pinterest.com/alice/cats/", board("alice", "cats")),
+            ("https://pinterest.co.uk/pin/42/", pin("42")),
+            // Other sites
+            ("https://example.com/alice/cats/", None),
+            ("https://pin.it/abc123", None),
+            ("https://i.pinimg.com/originals/aa/bb/cc.jpg", None),
+        ];
+
+        for (input, expected) in table {
+            assert_eq!(&parse(input), expected, "{}", input);
+        }
+    }
+}
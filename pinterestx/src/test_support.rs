@@ -0,0 +1,31 @@
+//! Local servers standing in for Pinterest, for tests.
+//!
+//! Used by the unit tests of this crate, and by the fixture-based integration tests,
+//! whose `tests/mock` includes this file as a module of its own. It only names the
+//! [`Session`] of its parent module, so it builds the same in both places.
+
+use axum::Router;
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use std::sync::Arc;
+use url::Url;
+
+use super::Session;
+
+/// Serves `app` on a free local port until the test ends, returning its base URL
+pub async fn serve(app: Router) -> Url {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+/// A session whose Pinterest requests go to `base_url`, with `cookies` (such as
+/// `"csrftoken=abc"`) set for it in its jar
+pub fn session(base_url: &Url, cookies: &[&str]) -> Session {
+    let mut store = CookieStore::default();
+    for cookie in cookies {
+        store.parse(cookie, base_url).unwrap();
+    }
+    let jar = Arc::new(CookieStoreMutex::new(store));
+    Session::with_jar(jar, "test").unwrap().with_base_url(base_url.clone())
+}
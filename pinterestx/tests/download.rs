@@ -0,0 +1,202 @@
+//! Whole download runs against the mock Pinterest: extraction, the download loop,
+//! deduplication, failures and sidecars, and the checks on what's downloaded.
+
+mod mock;
+
+use mock::{isolate_home, media_bytes, MockPinterest};
+use pinterestx::boards::{BoardFilters, SectionsMode};
+use pinterestx::download::{download_image_with_deduplication, sidecar_path, ImageMetadata};
+use pinterestx::formats::MediaFormat;
+use pinterestx::progress::ProgressEvent;
+use pinterestx::{download, DownloadOptions, DownloadSummary, Error, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use url::Url;
+
+/// Options for a run saved under its own genre, one image at a time so which copy
+/// of a duplicate is kept doesn't depend on timing
+fn options(url: &str, genre: &str) -> DownloadOptions {
+    DownloadOptions {
+        url: url.to_string(),
+        genre: Some(genre.to_string()),
+        workers: 1,
+        ..Default::default()
+    }
+}
+
+async fn run(options: &DownloadOptions) -> DownloadSummary {
+    let pinterest = MockPinterest::start().await;
+    download(options, &pinterest.session(), |_| {}).await.unwrap()
+}
+
+fn metadata(summary: &DownloadSummary, file: &str) -> ImageMetadata {
+    let content = std::fs::read(sidecar_path(&summary.output_dir.join(file))).unwrap();
+    serde_json::from_slice(&content).unwrap()
+}
+
+#[tokio::test]
+async fn downloads_a_board_feed_across_pages() {
+    let summary = run(&options("https://www.pinterest.com/alice/cats/", "board")).await;
+
+    // The third pin repeats the first one's image, the fourth is gone
+    assert_eq!(summary.files, ["image_001.jpg", "image_002.png"]);
+    assert_eq!(summary.stats.downloaded, 2);
+    assert_eq!(summary.stats.duplicates, 1);
+    assert_eq!(summary.stats.failed, 1);
+    let failed: Vec<&String> = summary.stats.failures.values().flatten().collect();
+    assert_eq!(failed, ["https://i.pinimg.com/originals/aa/04/missing.jpg"]);
+    assert!(summary.output_dir.ends_with("board/Pinterest"));
+
+    let saved = std::fs::read(summary.output_dir.join("image_001.jpg")).unwrap();
+    assert_eq!(saved, media_bytes("/originals/aa/01/tabby.jpg").unwrap().1);
+
+    // Sidecars record where the image is on Pinterest, not where the mock served it
+    let tabby = metadata(&summary, "image_001.jpg");
+    assert_eq!(tabby.source_url, "https://i.pinimg.com/originals/aa/01/tabby.jpg");
+    assert_eq!(tabby.page_url, "https://www.pinterest.com/alice/cats/");
+    assert_eq!(tabby.pin_id.as_deref(), Some("1001"));
+    // Without an original, the largest rendition is upgraded to one
+    assert_eq!(metadata(&summary, "image_002.png").source_url, "https://i.pinimg.com/originals/aa/02/calico.png");
+}
+
+#[tokio::test]
+async fn reports_progress_to_the_callback() {
+    let pinterest = MockPinterest::start().await;
+    let events = Mutex::new(Vec::new());
+    let options = options("https://www.pinterest.com/alice/cats/", "progress");
+    download(&options, &pinterest.session(), |event| {
+        let event = match event {
+            ProgressEvent::Started { total, workers, .. } => format!("started {} on {}", total, workers),
+            ProgressEvent::Saved { index, filename, .. } => format!("saved {} as {}", index, filename),
+            ProgressEvent::Duplicate { index, .. } => format!("duplicate {}", index),
+            ProgressEvent::Failed { index, kind, .. } => format!("failed {}: {}", index, kind),
+            ProgressEvent::FileStarted { .. } | ProgressEvent::Bytes { .. } => return,
+        };
+        events.lock().unwrap().push(event);
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(
+        events.into_inner().unwrap(),
+        ["started 4 on 1", "saved 1 as image_001.jpg", "saved 2 as image_002.png", "duplicate 3", "failed 4: HTTP 404"]
+    );
+}
+
+#[tokio::test]
+async fn downloads_board_sections_into_folders() {
+    let options = DownloadOptions { sections: Some(SectionsMode::Folders), ..options("https://www.pinterest.com/alice/cats/", "sections") };
+    let summary = run(&options).await;
+
+    assert_eq!(summary.files, ["image_001.jpg", "image_002.png", "kittens/image_001.jpg"]);
+    assert_eq!(metadata(&summary, "kittens/image_001.jpg").pin_id.as_deref(), Some("1005"));
+}
+
+#[tokio::test]
+async fn limits_the_number_of_images() {
+    let options = DownloadOptions { max_images: 1, ..options("https://www.pinterest.com/alice/cats/", "limited") };
+    let summary = run(&options).await;
+
+    assert_eq!(summary.files, ["image_001.jpg"]);
+    assert_eq!(summary.stats.downloaded, 1);
+}
+
+#[tokio::test]
+async fn downloads_a_pin() {
+    let summary = run(&options("https://www.pinterest.com/pin/2001/", "pin")).await;
+
+    assert_eq!(summary.files, ["image_001.jpg"]);
+    assert_eq!(metadata(&summary, "image_001.jpg").source_url, "https://i.pinimg.com/originals/bb/01/fox.jpg");
+}
+
+#[tokio::test]
+async fn downloads_search_results() {
+    let summary = run(&options("https://www.pinterest.com/search/pins/?q=forest", "search")).await;
+
+    assert_eq!(summary.files, ["image_001.jpg", "image_002.gif"]);
+}
+
+#[tokio::test]
+async fn downloads_a_profile_board_by_board() {
+    let options = DownloadOptions {
+        boards: BoardFilters { exclude: vec!["Dogs".to_string()], ..Default::default() },
+        ..options("https://www.pinterest.com/alice/", "profile")
+    };
+    let summary = run(&options).await;
+
+    assert!(summary.output_dir.ends_with("profile/alice"));
+    assert_eq!(summary.files, ["cats/image_001.jpg", "cats/image_002.png"]);
+}
+
+#[tokio::test]
+async fn caps_each_profile_board_after_skipping_pins_of_earlier_boards() {
+    // The Dogs board starts with the tabby that the Cats board has already given
+    let options = DownloadOptions {
+        boards: BoardFilters { per_board_max: 1, ..Default::default() },
+        ..options("https://www.pinterest.com/alice/", "profile-capped")
+    };
+    let summary = run(&options).await;
+
+    assert_eq!(summary.files, ["cats/image_001.jpg", "dogs/image_001.jpg"]);
+    assert_eq!(metadata(&summary, "dogs/image_001.jpg").source_url, "https://i.pinimg.com/originals/dd/01/beagle.jpg");
+}
+
+#[tokio::test]
+async fn downloads_what_the_html_strategy_scrapes() {
+    let options = DownloadOptions { strategies: vec!["html".to_string()], ..options("https://www.pinterest.com/alice/cats/", "html") };
+    let summary = run(&options).await;
+
+    assert_eq!(summary.files, ["image_001.jpg", "image_002.png"]);
+    assert_eq!(summary.stats.duplicates, 1);
+}
+
+/// Downloads one image from the mock's `i.pinimg.com` to `<dir>/image_001.jpg`
+async fn fetch(path: &str, dir: &Path) -> Result<Option<(PathBuf, u64)>> {
+    let pinterest = MockPinterest::start().await;
+    let session = pinterest.session();
+    let url = session.media_url(&Url::parse("https://i.pinimg.com/").unwrap().join(path).unwrap());
+    std::fs::create_dir_all(dir).unwrap();
+    download_image_with_deduplication(session.client(), &url, &dir.join("image_001.jpg"), &Mutex::new(HashSet::new()), |_, _| {}).await
+}
+
+#[tokio::test]
+async fn saves_images_with_the_extension_of_their_format() {
+    let dir = isolate_home().join("formats");
+    let (saved, size) = fetch("originals/ee/01/owl-webp.jpg", &dir).await.unwrap().unwrap();
+
+    assert_eq!(saved, dir.join("image_001.webp"));
+    let content = std::fs::read(&saved).unwrap();
+    assert_eq!(content.len() as u64, size);
+    assert_eq!(&content[8..12], b"WEBP");
+}
+
+#[tokio::test]
+async fn saves_hls_streams_as_mpeg_ts() {
+    let dir = isolate_home().join("hls");
+    let (saved, size) = fetch("videos/hls/aa/clip.m3u8", &dir).await.unwrap().unwrap();
+
+    // The segments are joined as they are, so the file is a transport stream
+    assert_eq!(saved, dir.join("image_001.ts"));
+    let content = std::fs::read(&saved).unwrap();
+    assert_eq!(size, 2 * 188);
+    assert_eq!(MediaFormat::sniff(&content), Some(MediaFormat::MpegTs));
+
+    let stills = fetch("videos/hls/aa/clip-stills.m3u8", &dir).await.unwrap_err();
+    assert!(matches!(stills, Error::NotMedia { ref reason, .. } if reason == "the stream's segments are jpg rather than ts"), "{:?}", stills);
+}
+
+#[tokio::test]
+async fn rejects_pages_served_instead_of_images() {
+    let dir = isolate_home().join("rejected");
+
+    // Turned away by its content type, before the body is read
+    let login = fetch("originals/ee/02/login-wall.jpg", &dir).await.unwrap_err();
+    assert!(matches!(login, Error::NotMedia { ref reason, .. } if reason == "served as text/html"), "{:?}", login);
+
+    // Claims to be a JPEG, but its bytes say otherwise
+    let mislabeled = fetch("originals/ee/03/mislabeled.jpg", &dir).await.unwrap_err();
+    assert!(mislabeled.to_string().contains("got an HTML page instead"), "{}", mislabeled);
+
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+}
@@ -0,0 +1,219 @@
+//! Scraping the fixture pages, directly and through the mock Pinterest.
+
+mod mock;
+
+use mock::{fixture, MockPinterest};
+use pinterestx::diagnostics::{compare_strategies, render_comparison, DebugDump};
+use pinterestx::metrics::Outcome;
+use pinterestx::resources;
+use pinterestx::scrape::{extract_image_urls, scrape_pin, try_direct_pinterest_extraction};
+use pinterestx::source::PinterestSource;
+use pinterestx::{DownloadOptions, Error, Registry, Target};
+use url::Url;
+
+fn pinterest_url(path: &str) -> Url {
+    Url::parse("https://www.pinterest.com/").unwrap().join(path).unwrap()
+}
+
+#[test]
+fn extracts_originals_from_a_board_page() {
+    assert_eq!(extract_image_urls(&fixture("pages/board.html"), false), [
+        "https://i.pinimg.com/originals/aa/01/tabby.jpg",
+        "https://i.pinimg.com/originals/aa/02/calico.png",
+        "https://i.pinimg.com/originals/aa/03/tabby-copy.jpg",
+    ]);
+}
+
+#[test]
+fn extracts_grid_images_from_a_search_page() {
+    assert_eq!(extract_image_urls(&fixture("pages/search.html"), true), [
+        "https://i.pinimg.com/originals/cc/01/forest.jpg",
+        "https://i.pinimg.com/originals/cc/02/lake.gif",
+        "https://i.pinimg.com/originals/cc/03/mountain.webp",
+    ]);
+}
+
+#[test]
+fn extracts_board_covers_from_a_profile_page() {
+    assert_eq!(extract_image_urls(&fixture("pages/profile.html"), false), [
+        "https://i.pinimg.com/originals/aa/01/tabby.jpg",
+        "https://i.pinimg.com/originals/dd/01/beagle.jpg",
+    ]);
+}
+
+#[test]
+fn finds_nothing_on_a_page_without_pins() {
+    assert!(extract_image_urls(&fixture("pages/empty.html"), false).is_empty());
+}
+
+#[tokio::test]
+async fn extracts_page_data_directly() {
+    let pinterest = MockPinterest::start().await;
+    let session = pinterest.session();
+
+    let board = try_direct_pinterest_extraction(&pinterest_url("alice/cats/"), &session).await.unwrap();
+    assert_eq!(board, [
+        "https://i.pinimg.com/originals/aa/01/tabby.jpg",
+        "https://i.pinimg.com/originals/aa/02/calico.png",
+        "https://i.pinimg.com/originals/aa/03/tabby-copy.jpg",
+    ]);
+
+    // The section page only has its image in an `"original": { "url": ... }` object
+    let section = try_direct_pinterest_extraction(&pinterest_url("alice/cats/kittens/"), &session).await.unwrap();
+    assert_eq!(section, ["https://i.pinimg.com/originals/aa/05/kitten.jpg"]);
+}
+
+#[tokio::test]
+async fn direct_extraction_copes_with_truncated_and_non_ascii_pages() {
+    let pinterest = MockPinterest::start().await;
+    let session = pinterest.session();
+
+    // The page ends right after an `"original"` key
+    let tail = try_direct_pinterest_extraction(&pinterest_url("carol/tail/"), &session).await.unwrap();
+    assert_eq!(tail, ["https://i.pinimg.com/originals/ee/01/fox.jpg"]);
+
+    // Multibyte text follows `"original"` within the bytes searched for its URL
+    let neko = try_direct_pinterest_extraction(&pinterest_url("carol/neko/"), &session).await.unwrap();
+    assert_eq!(neko, ["https://i.pinimg.com/originals/ee/02/neko.jpg"]);
+}
+
+#[tokio::test]
+async fn direct_extraction_reports_empty_and_missing_pages() {
+    let pinterest = MockPinterest::start().await;
+    let session = pinterest.session();
+
+    let empty = try_direct_pinterest_extraction(&pinterest_url("bob/empty/"), &session).await.unwrap_err();
+    assert!(matches!(empty, Error::UnexpectedResponse(_)), "{:?}", empty);
+
+    let missing = try_direct_pinterest_extraction(&pinterest_url("bob/gone/"), &session).await.unwrap_err();
+    assert_eq!(missing.status(), Some(reqwest::StatusCode::NOT_FOUND));
+}
+
+#[tokio::test]
+async fn scrapes_a_pin_from_its_page_data_or_open_graph_image() {
+    let pinterest = MockPinterest::start().await;
+    let session = pinterest.session();
+
+    // The page also embeds a related pin, which isn't the pin's image
+    let pin = scrape_pin(&pinterest_url("pin/2001/"), "2001", &session).await.unwrap();
+    let urls: Vec<&str> = pin.iter().map(|candidate| candidate.url.as_str()).collect();
+    assert_eq!(urls, ["https://i.pinimg.com/originals/bb/01/fox.jpg"]);
+    assert_eq!(pin[0].pin_id.as_deref(), Some("2001"));
+
+    let og_only = scrape_pin(&pinterest_url("pin/2003/"), "2003", &session).await.unwrap();
+    assert_eq!(og_only[0].url, "https://i.pinimg.com/originals/bb/03/heron.jpg");
+    assert_eq!(og_only[0].pin_id.as_deref(), Some("2003"));
+}
+
+#[tokio::test]
+async fn dumps_fetched_pages_for_analysis() {
+    let pinterest = MockPinterest::start().await;
+    let dir = std::env::temp_dir().join(format!("pinterestx-dump-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let session = pinterest.session().with_debug_dump(DebugDump::new(&dir).unwrap());
+
+    try_direct_pinterest_extraction(&pinterest_url("alice/cats/"), &session).await.unwrap();
+    // Pages that fail are saved too
+    try_direct_pinterest_extraction(&pinterest_url("bob/gone/"), &session).await.unwrap_err();
+
+    let mut files: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files, [
+        "001-alice_cats.__PWS_INITIAL_PROPS__.json",
+        "001-alice_cats.headers.txt",
+        "001-alice_cats.html",
+        "001-alice_cats.initial_state.json",
+        "001-alice_cats.report.txt",
+        "002-bob_gone.headers.txt",
+        "002-bob_gone.html",
+        "002-bob_gone.report.txt",
+    ]);
+    assert_eq!(std::fs::read_to_string(dir.join("001-alice_cats.html")).unwrap(), fixture("pages/board.html"));
+    let headers = std::fs::read_to_string(dir.join("002-bob_gone.headers.txt")).unwrap();
+    assert!(headers.starts_with("GET https://www.pinterest.com/bob/gone/\n404 Not Found\n"), "{}", headers);
+    let report = std::fs::read_to_string(dir.join("001-alice_cats.report.txt")).unwrap();
+    assert!(report.contains("Embedded pins: 3"), "{}", report);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn dumps_resource_responses_for_analysis() {
+    let pinterest = MockPinterest::start().await;
+    let dir = std::env::temp_dir().join(format!("pinterestx-resource-dump-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let session = pinterest.session().with_debug_dump(DebugDump::new(&dir).unwrap());
+
+    resources::fetch_board_info(&session, "alice", "cats").await.unwrap();
+    // Responses that fail are saved too
+    resources::fetch_related(&session, "1001", 0).await.unwrap_err();
+
+    let mut files: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files, [
+        "001-BoardResource.headers.txt",
+        "001-BoardResource.json",
+        "002-RelatedPinFeedResource.headers.txt",
+        "002-RelatedPinFeedResource.json",
+    ]);
+    let board: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("001-BoardResource.json")).unwrap()).unwrap();
+    let expected: serde_json::Value = serde_json::from_str(&fixture("resources/BoardResource.json")).unwrap();
+    assert_eq!(board, expected);
+    let headers = std::fs::read_to_string(dir.join("001-BoardResource.headers.txt")).unwrap();
+    assert!(headers.contains("/resource/BoardResource/get/?"), "{}", headers);
+    assert!(headers.contains("\n200 OK\ncontent-type: application/json\n"), "{}", headers);
+    let headers = std::fs::read_to_string(dir.join("002-RelatedPinFeedResource.headers.txt")).unwrap();
+    assert!(headers.contains("\n404 Not Found\n"), "{}", headers);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn compares_every_strategy_on_a_board() {
+    let pinterest = MockPinterest::start().await;
+    let session = pinterest.session();
+    let url = pinterest_url("alice/cats/");
+    let source = PinterestSource::parse(&url);
+    let options = DownloadOptions { url: url.to_string(), ..Default::default() };
+    let target = Target::new(&url, source.as_ref(), &options);
+
+    let reports = compare_strategies(&Registry::default(), &target, &session, 50).await;
+    let outcomes: Vec<(&str, Option<Outcome>)> = reports.iter().map(|report| (report.name, report.outcome.clone())).collect();
+    assert_eq!(outcomes, [
+        ("resource", Some(Outcome::Found(4))),
+        ("api", None),
+        ("modern_search", None),
+        ("pin_page", None),
+        ("direct", Some(Outcome::Found(3))),
+        ("html", Some(Outcome::Found(3))),
+    ]);
+
+    // Only the board feed has the image that's gone from Pinterest
+    let table = render_comparison(&reports);
+    let resource_row = table.lines().find(|line| line.trim_start().starts_with("resource")).unwrap();
+    assert!(resource_row.trim_end().ends_with(" 1"), "{}", table);
+    assert!(table.contains("first: https://i.pinimg.com/originals/aa/01/tabby.jpg"), "{}", table);
+    assert!(reports.iter().all(|report| !report.capped));
+}
+
+#[tokio::test]
+async fn stops_each_strategy_at_the_limit() {
+    let pinterest = MockPinterest::start().await;
+    let session = pinterest.session();
+    let url = pinterest_url("alice/cats/");
+    let source = PinterestSource::parse(&url);
+    let options = DownloadOptions { url: url.to_string(), max_images: 2, ..Default::default() };
+    let target = Target::new(&url, source.as_ref(), &options);
+
+    let reports = compare_strategies(&Registry::default(), &target, &session, 2).await;
+    let found: Vec<(&str, usize, bool)> =
+        reports.iter().filter(|report| report.outcome.is_some()).map(|report| (report.name, report.urls.len(), report.capped)).collect();
+    assert_eq!(found, [("resource", 2, true), ("direct", 2, true), ("html", 2, true)]);
+
+    let table = render_comparison(&reports);
+    assert!(table.lines().any(|line| line.trim_start().starts_with("resource       found 2+")), "{}", table);
+}
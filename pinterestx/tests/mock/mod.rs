@@ -0,0 +1,169 @@
+//! A local stand-in for Pinterest, for offline tests: `www.pinterest.com`'s pages and
+//! resources, served from the fixtures, and `i.pinimg.com`'s images.
+//!
+//! Pages are looked up by path in [`PAGES`]. A resource is served from
+//! `fixtures/resources/<name>.json`, or `<name>-<bookmark>.json` for the page after
+//! a bookmark. Images are generated from their path (see [`media_bytes`]), so every
+//! file has its own content except `-copy` files, which repeat the original, and
+//! `missing` files, which aren't found.
+//!
+//! The servers are started with the library's own test helpers (`src/test_support.rs`),
+//! which the unit tests share.
+
+#![allow(dead_code)]
+
+use axum::extract::{Path, Query};
+use axum::http::{header, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use image::{ImageFormat, Rgb, RgbImage};
+use pinterestx::{paths, Session};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Once;
+use url::Url;
+
+#[path = "../../src/test_support.rs"]
+mod test_support;
+
+/// Fixture pages, by the path they're served at
+pub const PAGES: &[(&str, &str)] = &[
+    ("/alice/", "profile.html"),
+    ("/alice/cats/", "board.html"),
+    ("/alice/cats/kittens/", "section.html"),
+    ("/pin/2001/", "pin.html"),
+    ("/pin/2003/", "pin-og-only.html"),
+    ("/search/pins/", "search.html"),
+    ("/bob/empty/", "empty.html"),
+    ("/carol/tail/", "original-at-end.html"),
+    ("/carol/neko/", "original-unicode.html"),
+];
+
+/// The mock servers of a test
+pub struct MockPinterest {
+    /// Stands in for `https://www.pinterest.com/`
+    pub base_url: Url,
+    /// Stands in for `https://i.pinimg.com/`
+    pub media_url: Url,
+}
+
+impl MockPinterest {
+    /// Starts both servers on free local ports, after isolating the data directories
+    pub async fn start() -> Self {
+        isolate_home();
+        let pinterest = Router::new().route("/resource/:name/get/", get(resource)).fallback(page);
+        let pinimg = Router::new().fallback(media);
+        Self { base_url: test_support::serve(pinterest).await, media_url: test_support::serve(pinimg).await }
+    }
+
+    /// A session without cookies whose requests all go to the mock servers
+    pub fn session(&self) -> Session {
+        test_support::session(&self.base_url, &[]).with_media_base_url(self.media_url.clone())
+    }
+}
+
+/// Path of a fixture file, relative to `tests/fixtures`
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+/// Content of a fixture file, relative to `tests/fixtures`
+pub fn fixture(name: &str) -> String {
+    std::fs::read_to_string(fixture_path(name)).unwrap_or_else(|e| panic!("Can't read fixture {}: {}", name, e))
+}
+
+async fn page(uri: Uri) -> Response {
+    match PAGES.iter().find(|(path, _)| *path == uri.path()) {
+        Some((_, name)) => ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], fixture(&format!("pages/{}", name))).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Serves `resources/<name>.json`, or `<name>-board<id>.json` for a board that has a
+/// fixture of its own, and `<name>-<bookmark>.json` for the pages after the first
+async fn resource(Path(name): Path<String>, Query(params): Query<HashMap<String, String>>) -> Response {
+    let data: Value = params.get("data").and_then(|data| serde_json::from_str(data).ok()).unwrap_or_default();
+    let name = match data["options"]["board_id"].as_str() {
+        Some(board) if fixture_path(&format!("resources/{}-board{}.json", name, board)).exists() => format!("{}-board{}", name, board),
+        _ => name,
+    };
+    let file = match data["options"]["bookmarks"][0].as_str() {
+        Some(bookmark) => format!("{}-{}.json", name, bookmark),
+        None => format!("{}.json", name),
+    };
+    match std::fs::read_to_string(fixture_path(&format!("resources/{}", file))) {
+        Ok(body) => ([(header::CONTENT_TYPE, "application/json")], body).into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn media(uri: Uri) -> Response {
+    match media_bytes(uri.path()) {
+        Some((content_type, body)) => ([(header::CONTENT_TYPE, content_type)], body).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// A login page, served instead of images that are behind a login wall
+pub const LOGIN_WALL: &str = "<!DOCTYPE html><html><head><title>Log in | Pinterest</title></head><body></body></html>";
+
+/// The content type and bytes served for an image path: a 2x2 image in the format of
+/// its extension, colored after its name with any `-copy` suffix dropped. A name
+/// ending in `-webp` is a WebP whatever its extension, `login-wall` is the
+/// [`LOGIN_WALL`] page, and `mislabeled` is that page served as `image/jpeg`.
+/// An `.m3u8` is an HLS playlist of two `.ts` segments, or of two JPEGs for a name
+/// ending in `-stills`, and a `.ts` a single MPEG-TS packet. `None` for `missing` files.
+pub fn media_bytes(path: &str) -> Option<(&'static str, Vec<u8>)> {
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    match stem {
+        "missing" => return None,
+        "login-wall" => return Some(("text/html; charset=utf-8", LOGIN_WALL.as_bytes().to_vec())),
+        "mislabeled" => return Some(("image/jpeg", LOGIN_WALL.as_bytes().to_vec())),
+        _ => {}
+    }
+    let extension = if stem.ends_with("-webp") { "webp" } else { extension };
+    let (content_type, format) = match extension {
+        "png" => ("image/png", ImageFormat::Png),
+        "gif" => ("image/gif", ImageFormat::Gif),
+        "webp" => ("image/webp", ImageFormat::WebP),
+        "mp4" => return Some(("video/mp4", b"\0\0\0\x18ftypmp42\0\0\0\0".to_vec())),
+        "m3u8" => {
+            let segments = if stem.ends_with("-stills") { ["still-1.jpg", "still-2.jpg"] } else { ["part-1.ts", "part-2.ts"] };
+            let playlist = format!("#EXTM3U\n#EXTINF:4.0,\n{}\n#EXTINF:4.0,\n{}\n#EXT-X-ENDLIST\n", segments[0], segments[1]);
+            return Some(("application/vnd.apple.mpegurl", playlist.into_bytes()));
+        }
+        "ts" => {
+            let mut packet = vec![0xFF; 188];
+            packet[..4].copy_from_slice(&[0x47, 0x1F, 0xFF, 0x10]);
+            return Some(("video/mp2t", packet));
+        }
+        _ => ("image/jpeg", ImageFormat::Jpeg),
+    };
+    let mut hasher = DefaultHasher::new();
+    stem.trim_end_matches("-copy").hash(&mut hasher);
+    let [red, green, blue, ..] = hasher.finish().to_le_bytes();
+    let mut body = Cursor::new(Vec::new());
+    RgbImage::from_pixel(2, 2, Rgb([red, green, blue])).write_to(&mut body, format).unwrap();
+    Some((content_type, body.into_inner()))
+}
+
+/// Points the data and output directories at a fresh directory for this test run, so
+/// downloads, metrics and settings don't touch the user's own. Every mock server
+/// does this when it starts.
+pub fn isolate_home() -> PathBuf {
+    static INIT: Once = Once::new();
+    let home = std::env::temp_dir().join(format!("pinterestx-tests-{}", std::process::id()));
+    INIT.call_once(|| {
+        let _ = std::fs::remove_dir_all(&home);
+        std::fs::create_dir_all(&home).unwrap();
+        paths::set_app_data_dir(home.join("data")).unwrap();
+        paths::set_output_root(home.join("Pictures")).unwrap();
+    });
+    home
+}